@@ -1,4 +1,9 @@
-use std::{env, fs, path::Path, process::Command};
+use std::{env, fs, path::Path};
+
+#[path = "src/shader_permutation.rs"]
+mod shader_permutation;
+#[path = "src/shader_compiler.rs"]
+mod shader_compiler;
 
 fn main() {
     // Compile Constants
@@ -44,19 +49,38 @@ const ICOSAHEDRON_VERTICES: [[f32; 3]; 12] = {:?};",
     fs::write(&dest_path, source_code).unwrap();
 
     // Compile Shader Modules
-    Command::new("make").status().unwrap();
-    /*for entry in fs::read_dir("src/shader/").unwrap() {
-        let src_path = entry.unwrap().path();
-        if src_path.is_dir() {
-            continue;
+    // They are written to OUT_DIR and the directory is emptied first, so that no modules of deleted shaders remain
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/shader");
+    let dst_path = Path::new(&out_dir).join("shader_modules");
+    if dst_path.exists() {
+        fs::remove_dir_all(&dst_path).unwrap();
+    }
+    fs::create_dir_all(&dst_path).unwrap();
+    let mut shader_compiler = shader_compiler::ShaderCompiler::new();
+    let mut src_paths: Vec<_> = fs::read_dir("src/shader/").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|src_path| !src_path.is_dir())
+        .collect();
+    src_paths.sort();
+    let errors: Vec<String> = src_paths.iter()
+        .filter_map(|src_path| shader_compiler.compile_file(src_path, &dst_path).err())
+        .collect();
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{}", error);
         }
-        let dst_path = Path::new(&out_dir).join(src_path.file_stem().unwrap()).with_extension("spv");
-        let module_type = src_path.file_stem().unwrap().to_str().unwrap().split('_').last().unwrap();
-        Command::new("glslangValidator").args(&[
-            "-S", module_type,
-            "-V460",
-            "-o", dst_path.to_str().unwrap(),
-            src_path.to_str().unwrap()
-        ]).status().unwrap();
-    }*/
+        panic!("{} shader modules failed to compile", errors.len());
+    }
+
+    // Embed Shader Modules
+    // The binary does not depend on OUT_DIR at runtime, the table maps each variant name to its SPIR-V
+    let mut module_paths: Vec<_> = fs::read_dir(&dst_path).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    module_paths.sort();
+    let entries: Vec<String> = module_paths.iter()
+        .map(|module_path| format!("    ({:?}, include_bytes!({:?})),\n", module_path.file_stem().unwrap().to_str().unwrap(), module_path.to_str().unwrap()))
+        .collect();
+    fs::write(Path::new(&out_dir).join("shader_modules.rs"), format!("&[\n{}]\n", entries.concat())).unwrap();
 }
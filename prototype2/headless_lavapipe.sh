@@ -0,0 +1,7 @@
+#!/bin/sh
+# Renders headless.json without a GPU on Mesa's software Vulkan driver lavapipe, e.g. in CI.
+# Needs the Mesa Vulkan drivers (Debian / Ubuntu: mesa-vulkan-drivers), the ICD can be overridden.
+set -e
+cd "$(dirname "$0")"
+export VK_ICD_FILENAMES="${VK_ICD_FILENAMES:-/usr/share/vulkan/icd.d/lvp_icd.x86_64.json}"
+cargo run --release -- --headless "${1:-headless.json}"
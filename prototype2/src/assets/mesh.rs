@@ -112,4 +112,11 @@ impl Mesh {
         render_pass.set_index_buffer(self.index_buffer.slice(..));
         render_pass.draw_indexed(0..self.index_count as u32, 0, instances_indices);
     }
+
+    /// Only binds the vertices, as the shadow pass has no use for the material
+    pub fn render_shadow<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, instances_indices: std::ops::Range<u32>) {
+        render_pass.set_vertex_buffer(3, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        render_pass.draw_indexed(0..self.index_count as u32, 0, instances_indices);
+    }
 }
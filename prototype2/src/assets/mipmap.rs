@@ -1,8 +1,9 @@
 use iced_wgpu::wgpu;
 use crate::assets::{AssetPack, Texture};
+use crate::pipeline_layout::{BindGroupLayout, PipelineLayout, PipelineLayoutDescriptor};
 
 struct MipmapGeneratorPipeline {
-    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group_layout: BindGroupLayout,
     pot_pipeline: wgpu::ComputePipeline,
     general_pipeline: wgpu::ComputePipeline,
 }
@@ -18,13 +19,15 @@ impl MipmapGenerator {
             wgpu::TextureFormat::Rgba8UnormSrgb,
             wgpu::TextureFormat::R16Uint,
             wgpu::TextureFormat::Rgba16Uint,
+            wgpu::TextureFormat::Rgba16Float,
         ];
         let mut pipelines: Vec<std::collections::HashMap<wgpu::TextureFormat, MipmapGeneratorPipeline>> = Vec::new();
         for dimensions in 2..4 {
             let dimension = if dimensions == 2 { wgpu::TextureViewDimension::D2 } else { wgpu::TextureViewDimension::D3 };
+            let dimensions_define = format!("DIMENSIONS={}", dimensions);
             let mut dimension_pipelines = std::collections::HashMap::new();
             for format in &formats {
-                let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                let bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
                     label: None,
                     entries: &[
                         wgpu::BindGroupLayoutEntry {
@@ -49,20 +52,20 @@ impl MipmapGenerator {
                         },
                     ],
                 });
-                let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                let pipeline_layout = PipelineLayout::new(device, &PipelineLayoutDescriptor {
                     label: None,
                     bind_group_layouts: &[&bind_group_layout],
                     push_constant_ranges: &[],
                 });
-                let pot_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                let pot_pipeline = pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
                     label: None,
                     layout: Some(&pipeline_layout),
-                    compute_stage: shader_module!(asset_pack, format!("assets/shader_modules/mipmap_{}d_pot_comp", dimensions)),
+                    compute_stage: shader_module!(asset_pack, "assets/shader_modules/mipmap_comp", &dimensions_define, "POT"),
                 });
-                let general_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                let general_pipeline = pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
                     label: None,
                     layout: Some(&pipeline_layout),
-                    compute_stage: shader_module!(asset_pack, format!("assets/shader_modules/mipmap_{}d_general_comp", dimensions)),
+                    compute_stage: shader_module!(asset_pack, "assets/shader_modules/mipmap_comp", &dimensions_define),
                 });
                 dimension_pipelines.insert(*format, MipmapGeneratorPipeline {
                     bind_group_layout,
@@ -4,20 +4,35 @@ use rayon::prelude::*;
 pub use texture::Texture;
 pub use mipmap::MipmapGenerator;
 pub use mesh::{Vertex, Mesh};
+pub use shader_reflection::ShaderReflection;
 
 mod texture;
 mod mipmap;
 mod mesh;
 mod gltf;
+mod shader_reflection;
+
+/// The build script compiles the shaders into this directory, so that modules of deleted shaders do not linger in the source tree.
+/// Only the shader hot reloading writes and reads it at runtime, otherwise the modules are embedded into the binary.
+#[cfg(feature = "shader-reload")]
+pub const SHADER_MODULE_DIRECTORY: &str = concat!(env!("OUT_DIR"), "/shader_modules");
+/// Variant name and SPIR-V of every module the build script compiled, e.g. ("mipmap_comp[DIMENSIONS=2,POT]", ...)
+const SHADER_MODULES: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/shader_modules.rs"));
+/// Shader modules are looked up by this directory joined with their name, e.g. "assets/shader_modules/mipmap_comp"
+const SHADER_MODULE_KEY_DIRECTORY: &str = "assets/shader_modules";
 
 #[derive(Debug)]
 pub enum AssetError {
     IoError(std::io::Error),
     SerdeJsonError(serde_json::Error),
     ImageError(image::ImageError),
+    SpirvError(std::path::PathBuf, String),
     // AlreadyLoaded, // TODO
     ArrayTextureSizeMismatch,
     ArrayTextureFormatMismatch,
+    NoAdapter,
+    MissingFeatures(wgpu::Features),
+    RequestDeviceError(wgpu::RequestDeviceError),
 }
 
 impl From<std::io::Error> for AssetError {
@@ -52,18 +67,29 @@ macro_rules! map_and_collect_error {
 #[derive(Default)]
 pub struct AssetPack {
     pub shader_modules: std::collections::HashMap<std::path::PathBuf, wgpu::ShaderModule>,
+    pub shader_reflections: std::collections::HashMap<std::path::PathBuf, ShaderReflection>,
     pub textures: std::collections::HashMap<std::path::PathBuf, Texture>,
     pub meshes: std::collections::HashMap<std::path::PathBuf, Mesh>,
     // TODO: Sound, Localization
 }
 
-fn load_spirv(device: &wgpu::Device, path: std::path::PathBuf) -> Result<(std::path::PathBuf, wgpu::ShaderModule), AssetError> {
+fn shader_module_key(entry_path: &std::path::Path) -> std::path::PathBuf {
+    std::path::Path::new(SHADER_MODULE_KEY_DIRECTORY).join(entry_path.file_stem().unwrap())
+}
+
+fn load_spirv(device: &wgpu::Device, path: std::path::PathBuf, bytes: &[u8]) -> Result<(std::path::PathBuf, wgpu::ShaderModule, ShaderReflection), AssetError> {
+    // Embedded bytes are not aligned for u32, so the words are copied
+    let source: Vec<u32> = bytes.chunks_exact(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect();
+    let shader_reflection = ShaderReflection::new(&source).map_err(|error| AssetError::SpirvError(path.clone(), error))?;
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleSource::SpirV(std::borrow::Cow::from(source)));
+    Ok((path, shader_module, shader_reflection))
+}
+
+fn load_spirv_file(device: &wgpu::Device, path: std::path::PathBuf) -> Result<(std::path::PathBuf, wgpu::ShaderModule, ShaderReflection), AssetError> {
     let mut file = std::fs::File::open(&path)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
-    let source = unsafe { crate::transmute_slice::<u8, u32>(&buffer[..]) };
-    let shader_module = device.create_shader_module(wgpu::ShaderModuleSource::SpirV(std::borrow::Cow::from(source)));
-    Ok((path, shader_module))
+    load_spirv(device, path, &buffer)
 }
 
 impl AssetPack {
@@ -96,12 +122,28 @@ impl AssetPack {
         }
     }
 
-    pub fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, bind_group_layout: Option<&wgpu::BindGroupLayout>, sampler: Option<&wgpu::Sampler>, mipmap_generator: Option<&MipmapGenerator>, path_pool: &std::collections::HashMap<String, std::collections::HashSet<std::path::PathBuf>>) -> Result<(), AssetError> {
+    /// Loads the shader modules which are embedded into the binary
+    pub fn load_shader_modules(&mut self, device: &wgpu::Device) -> Result<(), AssetError> {
+        let shader_module_entries = map_and_collect_error!(SHADER_MODULES.par_iter(), {|(name, bytes)| {
+            load_spirv(device, std::path::PathBuf::from(format!("{}.spv", name)), bytes)
+        }}, (std::path::PathBuf, wgpu::ShaderModule, ShaderReflection));
+        for (entry_path, shader_module, shader_reflection) in shader_module_entries {
+            let path = shader_module_key(&entry_path);
+            self.shader_modules.insert(path.clone(), shader_module);
+            self.shader_reflections.insert(path, shader_reflection);
+        }
+        Ok(())
+    }
+
+    /// With mipmaps the textures get mip levels, which have to be generated with generate_mipmaps afterwards
+    pub fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, bind_group_layout: Option<&wgpu::BindGroupLayout>, sampler: Option<&wgpu::Sampler>, mipmaps: bool, path_pool: &std::collections::HashMap<String, std::collections::HashSet<std::path::PathBuf>>) -> Result<(), AssetError> {
         let shader_module_entries = map_and_collect_error!((&path_pool.get(&"shader_modules".to_owned()).unwrap()).into_par_iter(), {|entry_path| {
-            load_spirv(device, entry_path.clone())
-        }}, (std::path::PathBuf, wgpu::ShaderModule));
-        for (entry_path, shader_module) in shader_module_entries {
-            self.shader_modules.insert(entry_path.parent().unwrap().join(entry_path.file_stem().unwrap()), shader_module);
+            load_spirv_file(device, entry_path.clone())
+        }}, (std::path::PathBuf, wgpu::ShaderModule, ShaderReflection));
+        for (entry_path, shader_module, shader_reflection) in shader_module_entries {
+            let path = shader_module_key(&entry_path);
+            self.shader_modules.insert(path.clone(), shader_module);
+            self.shader_reflections.insert(path, shader_reflection);
         }
 
         let mut array_texture_layers: Vec<(std::path::PathBuf, Option<(std::path::PathBuf, u32)>)> = Vec::new();
@@ -119,7 +161,7 @@ impl AssetPack {
                         height: size_descriptor.next().unwrap().parse::<u32>().unwrap(),
                         depth: size_descriptor.next().unwrap().parse::<u32>().unwrap(),
                     };
-                    let texture = Texture::new(device, size, mipmap_generator.is_some(), false, wgpu::TextureDimension::D2, wgpu::TextureFormat::Rgba8UnormSrgb);
+                    let texture = Texture::new(device, size, mipmaps, false, wgpu::TextureDimension::D2, wgpu::TextureFormat::Rgba8UnormSrgb);
                     let texture_path = entry_path.parent().unwrap().join(&file_stem);
                     self.textures.insert(texture_path.clone(), texture);
                     for i in 0..size.depth {
@@ -133,7 +175,7 @@ impl AssetPack {
             .map(|entry_path| (entry_path.clone(), Option::<(std::path::PathBuf, u32)>::None))
             .collect();
         let texture_entries = map_and_collect_error!(texture_load_tasks.into_par_iter().chain(array_texture_layers.into_par_iter()), {|(entry_path, array_texture)| {
-            Texture::load(device, queue, mipmap_generator.is_some(), array_texture.and_then(|(dir_path, i)| Some(texture::ArrayTextureLayer {
+            Texture::load(device, queue, mipmaps, array_texture.and_then(|(dir_path, i)| Some(texture::ArrayTextureLayer {
                 layer_index: i,
                 texture: &self.textures.get(&dir_path).unwrap(),
             })), entry_path)
@@ -143,12 +185,6 @@ impl AssetPack {
                 self.textures.insert(entry_path.parent().unwrap().join(entry_path.file_stem().unwrap()), texture);
             }
         }
-        if let Some(mipmap_generator) = mipmap_generator {
-            for (_path, texture) in self.textures.iter() {
-                mipmap_generator.generate(device, encoder, &texture, None);
-            }
-        }
-
         let mesh_entry_entries = map_and_collect_error!((&path_pool.get(&"meshes".to_owned()).unwrap()).into_par_iter(), {|entry_path| {
             gltf::load_gltf(device, bind_group_layout.unwrap(), sampler.unwrap(), self, entry_path)
         }}, Vec<(std::path::PathBuf, Mesh)>);
@@ -160,4 +196,35 @@ impl AssetPack {
 
         Ok(())
     }
+
+    /// Generates the mip levels of all textures from their first level, e.g. again after the mipmap shader was reloaded
+    pub fn generate_mipmaps(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, mipmap_generator: &MipmapGenerator) {
+        for (_path, texture) in self.textures.iter() {
+            mipmap_generator.generate(device, encoder, &texture, None);
+        }
+    }
+
+    /// Looks up the module compiled from a shader with the given defines, panics if that permutation was not built
+    pub fn get_shader_module<P: AsRef<std::path::Path>>(&self, path: P, defines: &[&str]) -> &wgpu::ShaderModule {
+        let path = path.as_ref();
+        let path = path.with_file_name(crate::shader_permutation::variant_name(path.file_name().unwrap().to_str().unwrap(), defines));
+        self.shader_modules.get(&path).unwrap_or_else(|| panic!("Shader module {:?} is not loaded", path))
+    }
+
+    /// Finds the path and interface of a shader module which was loaded by this pack
+    pub fn get_shader_reflection(&self, shader_module: &wgpu::ShaderModule) -> Option<(&std::path::PathBuf, &ShaderReflection)> {
+        let (path, _) = self.shader_modules.iter().find(|(_, loaded_module)| std::ptr::eq(*loaded_module, shader_module))?;
+        Some((path, self.shader_reflections.get(path)?))
+    }
+
+    /// Replaces a single shader module, pipelines which use it have to be recreated afterwards.
+    /// Returns false if the module was not loaded before, then no pipeline was created from it.
+    #[cfg(feature = "shader-reload")]
+    pub fn reload_shader_module(&mut self, device: &wgpu::Device, path: std::path::PathBuf) -> Result<bool, AssetError> {
+        let (entry_path, shader_module, shader_reflection) = load_spirv_file(device, path)?;
+        let path = shader_module_key(&entry_path);
+        let replaced = self.shader_modules.insert(path.clone(), shader_module).is_some();
+        self.shader_reflections.insert(path, shader_reflection);
+        Ok(replaced)
+    }
 }
@@ -0,0 +1,365 @@
+use iced_wgpu::wgpu;
+
+const SPIRV_MAGIC_NUMBER: u32 = 0x07230203;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+/// Kind of resource a shader expects at a binding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescriptorKind {
+    UniformBuffer,
+    StorageBuffer,
+    Sampler,
+    SampledTexture,
+    StorageTexture,
+    /// Not supported by wgpu, textures and samplers have to be bound separately
+    CombinedTextureSampler,
+}
+
+impl DescriptorKind {
+    pub fn matches(&self, binding_type: &wgpu::BindingType) -> bool {
+        match (self, binding_type) {
+            (Self::UniformBuffer, wgpu::BindingType::UniformBuffer { .. }) |
+            (Self::StorageBuffer, wgpu::BindingType::StorageBuffer { .. }) |
+            (Self::Sampler, wgpu::BindingType::Sampler { .. }) |
+            (Self::SampledTexture, wgpu::BindingType::SampledTexture { .. }) |
+            (Self::StorageTexture, wgpu::BindingType::StorageTexture { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub kind: DescriptorKind,
+    /// Size of the buffer block without its runtime array, zero for textures and samplers
+    pub size: u32,
+}
+
+/// Interface of a shader module which pipelines have to match
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    pub stage: wgpu::ShaderStage,
+    pub bindings: Vec<DescriptorBinding>,
+    /// Zero if the shader has no push constant block
+    pub push_constant_size: u32,
+    /// Locations consumed by the inputs of a vertex shader, matrices consume one per column
+    pub input_locations: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Decorations {
+    block: bool,
+    buffer_block: bool,
+    built_in: bool,
+    array_stride: Option<u32>,
+    location: Option<u32>,
+    binding: Option<u32>,
+    descriptor_set: Option<u32>,
+}
+
+#[derive(Default)]
+struct MemberDecorations {
+    offset: u32,
+    matrix_stride: Option<u32>,
+}
+
+enum Type {
+    Scalar(u32),
+    Vector(u32, u32),
+    Matrix(u32, u32),
+    Image(u32),
+    Sampler,
+    SampledImage,
+    Array(u32, u32),
+    RuntimeArray(u32),
+    Struct(Vec<u32>),
+    Pointer(u32, u32),
+}
+
+/// Only the parts of the module which are needed to describe its interface are parsed
+struct Module {
+    stage: Option<wgpu::ShaderStage>,
+    types: std::collections::HashMap<u32, Type>,
+    constants: std::collections::HashMap<u32, u32>,
+    decorations: std::collections::HashMap<u32, Decorations>,
+    member_decorations: std::collections::HashMap<(u32, u32), MemberDecorations>,
+    variables: Vec<(u32, u32, u32)>,
+}
+
+impl Module {
+    fn parse(code: &[u32]) -> Result<Self, String> {
+        if code.len() < 5 || code[0] != SPIRV_MAGIC_NUMBER {
+            return Err("not a SPIR-V module".to_string());
+        }
+        let mut module = Self {
+            stage: None,
+            types: std::collections::HashMap::new(),
+            constants: std::collections::HashMap::new(),
+            decorations: std::collections::HashMap::new(),
+            member_decorations: std::collections::HashMap::new(),
+            variables: Vec::new(),
+        };
+        let mut offset = 5;
+        while offset < code.len() {
+            let word_count = (code[offset]>>16) as usize;
+            let opcode = code[offset]&0xFFFF;
+            if word_count == 0 || offset+word_count > code.len() {
+                return Err(format!("truncated instruction at word {}", offset));
+            }
+            let operands = &code[offset+1..offset+word_count];
+            offset += word_count;
+            let operand = |index: usize| -> Result<u32, String> {
+                operands.get(index).cloned().ok_or_else(|| format!("instruction {} is missing operands", opcode))
+            };
+            match opcode {
+                OP_ENTRY_POINT => {
+                    module.stage = Some(match operand(0)? {
+                        0 => wgpu::ShaderStage::VERTEX,
+                        4 => wgpu::ShaderStage::FRAGMENT,
+                        5 => wgpu::ShaderStage::COMPUTE,
+                        execution_model => return Err(format!("unsupported execution model {}", execution_model)),
+                    });
+                },
+                OP_TYPE_INT | OP_TYPE_FLOAT => { module.types.insert(operand(0)?, Type::Scalar(operand(1)?/8)); },
+                OP_TYPE_VECTOR => { module.types.insert(operand(0)?, Type::Vector(operand(1)?, operand(2)?)); },
+                OP_TYPE_MATRIX => { module.types.insert(operand(0)?, Type::Matrix(operand(1)?, operand(2)?)); },
+                OP_TYPE_IMAGE => { module.types.insert(operand(0)?, Type::Image(operand(6)?)); },
+                OP_TYPE_SAMPLER => { module.types.insert(operand(0)?, Type::Sampler); },
+                OP_TYPE_SAMPLED_IMAGE => { module.types.insert(operand(0)?, Type::SampledImage); },
+                OP_TYPE_ARRAY => { module.types.insert(operand(0)?, Type::Array(operand(1)?, operand(2)?)); },
+                OP_TYPE_RUNTIME_ARRAY => { module.types.insert(operand(0)?, Type::RuntimeArray(operand(1)?)); },
+                OP_TYPE_STRUCT => { module.types.insert(operand(0)?, Type::Struct(operands.get(1..).unwrap_or(&[]).to_vec())); },
+                OP_TYPE_POINTER => { module.types.insert(operand(0)?, Type::Pointer(operand(1)?, operand(2)?)); },
+                OP_CONSTANT => { module.constants.insert(operand(1)?, operand(2)?); },
+                OP_VARIABLE => { module.variables.push((operand(0)?, operand(1)?, operand(2)?)); },
+                OP_DECORATE => {
+                    let decorations = module.decorations.entry(operand(0)?).or_default();
+                    match operand(1)? {
+                        DECORATION_BLOCK => decorations.block = true,
+                        DECORATION_BUFFER_BLOCK => decorations.buffer_block = true,
+                        DECORATION_BUILT_IN => decorations.built_in = true,
+                        DECORATION_ARRAY_STRIDE => decorations.array_stride = Some(operand(2)?),
+                        DECORATION_LOCATION => decorations.location = Some(operand(2)?),
+                        DECORATION_BINDING => decorations.binding = Some(operand(2)?),
+                        DECORATION_DESCRIPTOR_SET => decorations.descriptor_set = Some(operand(2)?),
+                        _ => {},
+                    }
+                },
+                OP_MEMBER_DECORATE => {
+                    let member_decorations = module.member_decorations.entry((operand(0)?, operand(1)?)).or_default();
+                    match operand(2)? {
+                        DECORATION_OFFSET => member_decorations.offset = operand(3)?,
+                        DECORATION_MATRIX_STRIDE => member_decorations.matrix_stride = Some(operand(3)?),
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+        Ok(module)
+    }
+
+    fn get_type(&self, type_id: u32) -> Result<&Type, String> {
+        self.types.get(&type_id).ok_or_else(|| format!("unknown type %{}", type_id))
+    }
+
+    fn get_array_length(&self, length_id: u32) -> Result<u32, String> {
+        self.constants.get(&length_id).cloned().ok_or_else(|| format!("array length %{} is not a constant", length_id))
+    }
+
+    /// Size in bytes according to the explicit layout decorations, runtime arrays have no size
+    fn get_size(&self, type_id: u32, matrix_stride: Option<u32>) -> Result<u32, String> {
+        Ok(match self.get_type(type_id)? {
+            Type::Scalar(size) => *size,
+            Type::Vector(component_type, component_count) => self.get_size(*component_type, None)?*component_count,
+            Type::Matrix(column_type, column_count) => match matrix_stride {
+                Some(matrix_stride) => matrix_stride*column_count,
+                None => self.get_size(*column_type, None)?*column_count,
+            },
+            Type::Array(element_type, length_id) => {
+                let length = self.get_array_length(*length_id)?;
+                match self.decorations.get(&type_id).and_then(|decorations| decorations.array_stride) {
+                    Some(array_stride) => array_stride*length,
+                    None => self.get_size(*element_type, matrix_stride)?*length,
+                }
+            },
+            Type::RuntimeArray(_) => 0,
+            Type::Struct(member_types) => {
+                let mut size = 0;
+                for (member_index, member_type) in member_types.iter().enumerate() {
+                    let member_decorations = self.member_decorations.get(&(type_id, member_index as u32));
+                    let offset = member_decorations.map(|member_decorations| member_decorations.offset).unwrap_or(0);
+                    let member_size = self.get_size(*member_type, member_decorations.and_then(|member_decorations| member_decorations.matrix_stride))?;
+                    size = size.max(offset+member_size);
+                }
+                size
+            },
+            _ => 0,
+        })
+    }
+
+    fn get_location_count(&self, type_id: u32) -> Result<u32, String> {
+        Ok(match self.get_type(type_id)? {
+            Type::Matrix(_, column_count) => *column_count,
+            Type::Array(element_type, length_id) => self.get_location_count(*element_type)?*self.get_array_length(*length_id)?,
+            _ => 1,
+        })
+    }
+
+    /// Arrays of textures and samplers are bound as one binding, so the element type decides the kind
+    fn get_descriptor_kind(&self, type_id: u32, storage_class: u32) -> Result<DescriptorKind, String> {
+        let decorations = self.decorations.get(&type_id);
+        Ok(match (self.get_type(type_id)?, storage_class) {
+            (Type::Array(element_type, _), _) | (Type::RuntimeArray(element_type), _) => self.get_descriptor_kind(*element_type, storage_class)?,
+            (Type::Struct(_), STORAGE_CLASS_STORAGE_BUFFER) => DescriptorKind::StorageBuffer,
+            (Type::Struct(_), STORAGE_CLASS_UNIFORM) if decorations.map_or(false, |decorations| decorations.buffer_block) => DescriptorKind::StorageBuffer,
+            (Type::Struct(_), STORAGE_CLASS_UNIFORM) if decorations.map_or(false, |decorations| decorations.block) => DescriptorKind::UniformBuffer,
+            (Type::Sampler, _) => DescriptorKind::Sampler,
+            (Type::Image(2), _) => DescriptorKind::StorageTexture,
+            (Type::Image(_), _) => DescriptorKind::SampledTexture,
+            (Type::SampledImage, _) => DescriptorKind::CombinedTextureSampler,
+            _ => return Err(format!("unsupported resource type %{}", type_id)),
+        })
+    }
+}
+
+impl ShaderReflection {
+    pub fn new(code: &[u32]) -> Result<Self, String> {
+        let module = Module::parse(code)?;
+        let mut reflection = Self {
+            stage: module.stage.ok_or_else(|| "no entry point".to_string())?,
+            bindings: Vec::new(),
+            push_constant_size: 0,
+            input_locations: Vec::new(),
+        };
+        for (pointer_type, variable_id, storage_class) in module.variables.iter() {
+            let pointee_type = match module.get_type(*pointer_type)? {
+                Type::Pointer(_, pointee_type) => *pointee_type,
+                _ => return Err(format!("variable %{} is not a pointer", variable_id)),
+            };
+            let decorations = module.decorations.get(variable_id);
+            match *storage_class {
+                STORAGE_CLASS_UNIFORM_CONSTANT | STORAGE_CLASS_UNIFORM | STORAGE_CLASS_STORAGE_BUFFER => {
+                    let decorations = decorations.ok_or_else(|| format!("resource %{} has no binding", variable_id))?;
+                    let kind = module.get_descriptor_kind(pointee_type, *storage_class)?;
+                    reflection.bindings.push(DescriptorBinding {
+                        set: decorations.descriptor_set.unwrap_or(0),
+                        binding: decorations.binding.ok_or_else(|| format!("resource %{} has no binding", variable_id))?,
+                        kind,
+                        size: match kind {
+                            DescriptorKind::UniformBuffer | DescriptorKind::StorageBuffer => module.get_size(pointee_type, None)?,
+                            _ => 0,
+                        },
+                    });
+                },
+                STORAGE_CLASS_PUSH_CONSTANT => {
+                    reflection.push_constant_size = module.get_size(pointee_type, None)?;
+                },
+                STORAGE_CLASS_INPUT if reflection.stage == wgpu::ShaderStage::VERTEX => {
+                    // Built-ins like gl_VertexIndex are either decorated themselves or are members of a decorated block
+                    if let Some(location) = decorations.filter(|decorations| !decorations.built_in).and_then(|decorations| decorations.location) {
+                        reflection.input_locations.extend(location..location+module.get_location_count(pointee_type)?);
+                    }
+                },
+                _ => {},
+            }
+        }
+        Ok(reflection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reflects a module which the build script compiled from src/shader
+    fn reflect(name: &str) -> ShaderReflection {
+        let (_, bytes) = crate::assets::SHADER_MODULES.iter().find(|(module_name, _)| *module_name == name).unwrap();
+        let code: Vec<u32> = bytes.chunks(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect();
+        ShaderReflection::new(&code).unwrap()
+    }
+
+    fn find_binding(reflection: &ShaderReflection, set: u32, binding: u32) -> DescriptorBinding {
+        *reflection.bindings.iter().find(|descriptor_binding| descriptor_binding.set == set && descriptor_binding.binding == binding)
+            .unwrap_or_else(|| panic!("binding {} of set {} was not reflected", binding, set))
+    }
+
+    #[test]
+    fn rejects_invalid_modules() {
+        assert!(ShaderReflection::new(&[]).is_err());
+        assert!(ShaderReflection::new(&[0, 0, 0, 0, 0]).is_err());
+        // Word count of the first instruction exceeds the module
+        assert!(ShaderReflection::new(&[SPIRV_MAGIC_NUMBER, 0x00010000, 0, 1, 0, (4<<16)|OP_ENTRY_POINT]).is_err());
+    }
+
+    #[test]
+    fn fragment_shader_bindings() {
+        let reflection = reflect("motion_vector_frag");
+        assert_eq!(reflection.stage, wgpu::ShaderStage::FRAGMENT);
+        assert_eq!(reflection.bindings.len(), 6);
+        let g_buffer_instance = find_binding(&reflection, 0, 0);
+        assert_eq!(g_buffer_instance.kind, DescriptorKind::SampledTexture);
+        assert_eq!(g_buffer_instance.size, 0);
+        assert_eq!(find_binding(&reflection, 0, 1).kind, DescriptorKind::SampledTexture);
+        // The camera uniforms of the include are four matrices
+        let camera_uniforms = find_binding(&reflection, 1, 0);
+        assert_eq!(camera_uniforms.kind, DescriptorKind::StorageBuffer);
+        assert_eq!(camera_uniforms.size, 256);
+        // Blocks which only consist of a runtime array have no static size
+        let instances_previous_world_matrix = find_binding(&reflection, 2, 0);
+        assert_eq!(instances_previous_world_matrix.kind, DescriptorKind::StorageBuffer);
+        assert_eq!(instances_previous_world_matrix.size, 0);
+        assert_eq!(find_binding(&reflection, 2, 1).size, 0);
+        let previous_camera_uniforms = find_binding(&reflection, 2, 2);
+        assert_eq!(previous_camera_uniforms.kind, DescriptorKind::UniformBuffer);
+        assert_eq!(previous_camera_uniforms.size, 256);
+        assert_eq!(reflection.push_constant_size, 16);
+        assert!(reflection.input_locations.is_empty());
+    }
+
+    #[test]
+    fn vertex_shader_input_locations() {
+        let reflection = reflect("surface_pass_vert");
+        assert_eq!(reflection.stage, wgpu::ShaderStage::VERTEX);
+        assert!(reflection.bindings.is_empty());
+        assert_eq!(reflection.push_constant_size, 0);
+        // Three matrices of four columns each, then position, normal and texture coordinates, but not gl_InstanceIndex
+        let mut input_locations = reflection.input_locations.clone();
+        input_locations.sort();
+        assert_eq!(input_locations, (0..15).collect::<Vec<u32>>());
+    }
+}
@@ -6,13 +6,13 @@ fn ray_point_distance(origin: glam::Vec3A, direction: glam::Vec3A, point: glam::
     orthogonal.length()
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct BoundingBox {
     pub min: glam::Vec3A,
     pub max: glam::Vec3A,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct BoundingPyramid {
     pub near: f32,
     pub far: f32,
@@ -20,19 +20,19 @@ pub struct BoundingPyramid {
     pub height_at_far: f32,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct BoundingSphere {
     pub radius: f32,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct BoundingCone {
     pub near: f32,
     pub far: f32,
     pub radius_at_far: f32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum BoundingVolume {
     Box(BoundingBox),
     Pyramid(BoundingPyramid),
@@ -49,6 +49,13 @@ impl Default for BoundingVolume {
     }
 }
 
+fn segment_point_distance(start: glam::Vec2, end: glam::Vec2, point: glam::Vec2) -> f32 {
+    let segment = end-start;
+    let length_squared = segment.dot(segment);
+    let t = if length_squared > 0.0 { ((point-start).dot(segment)/length_squared).max(0.0).min(1.0) } else { 0.0 };
+    (start+segment*t-point).length()
+}
+
 pub struct HalfPlane {
     normal: glam::Vec3A,
     dist: f32,
@@ -69,8 +76,8 @@ pub fn points_half_plane_intersection(points: &[glam::Vec3A], half_plane: &HalfP
 
 pub fn project_point_onto_half_plane(point: glam::Vec3A, half_plane: &HalfPlane) -> glam::Vec3A {
     let dist = point.dot(half_plane.normal);
-    if dist < half_plane.dist {
-        point+(half_plane.dist-dist)*half_plane.normal
+    if dist > half_plane.dist {
+        point-(dist-half_plane.dist)*half_plane.normal
     } else {
         point
     }
@@ -87,13 +94,16 @@ pub fn point_double_plane_intersection(point: glam::Vec3A, double_plane: &Double
     double_plane.min <= dist && dist <= double_plane.max
 }
 
+/// True if the convex hull of the points overlaps the double plane, not only if a point is inside of it
 pub fn points_double_plane_intersection(points: &[glam::Vec3A], double_plane: &DoublePlane) -> bool {
+    let mut min = f32::INFINITY;
+    let mut max = -f32::INFINITY;
     for p in points {
-        if point_double_plane_intersection(*p, &double_plane) {
-            return true;
-        }
+        let dist = p.dot(double_plane.normal);
+        min = min.min(dist);
+        max = max.max(dist);
     }
-    false
+    min <= double_plane.max && double_plane.min <= max
 }
 
 pub fn project_point_into_double_plane(point: glam::Vec3A, double_plane: &DoublePlane) -> glam::Vec3A {
@@ -133,7 +143,7 @@ macro_rules! half_plane_of_pyramid {
     ($center:expr, $z_axis:expr, $far_point:expr) => {
         {
             let diagonal = $far_point-$center;
-            let normal = $z_axis.cross(diagonal).cross(diagonal);
+            let normal = $z_axis.cross(diagonal).cross(diagonal).normalize();
             HalfPlane {
                 normal,
                 dist: $center.dot(normal),
@@ -153,7 +163,7 @@ pub fn half_planes_of_pyramid(pyramid: &BoundingPyramid, world_matrix_pyramid: &
         half_plane_of_pyramid!(center, z_axis, world_matrix_pyramid.mul_vec4(glam::Vec4::new(0.0, pyramid.height_at_far, pyramid.far, 1.0)).truncate()),
         HalfPlane {
             normal: -z_axis,
-            dist: center_dist+pyramid.near,
+            dist: -center_dist-pyramid.near,
         },
         HalfPlane {
             normal: z_axis,
@@ -176,9 +186,8 @@ pub fn points_of_bounding_box(bb: &BoundingBox, world_matrix_bb: &glam::Mat4) ->
 }
 
 pub fn points_of_bounding_pyramid(pyramid: &BoundingPyramid, world_matrix_pyramid: &glam::Mat4) -> [glam::Vec3A; 8] {
-    let near_factor = pyramid.near/pyramid.far;
-    let width_at_near = near_factor*pyramid.width_at_far;
-    let height_at_near = near_factor*pyramid.height_at_far;
+    let width_at_near = radius_at_near(pyramid.near, pyramid.far, pyramid.width_at_far);
+    let height_at_near = radius_at_near(pyramid.near, pyramid.far, pyramid.height_at_far);
     [
         world_matrix_pyramid.mul_vec4(glam::Vec4::new(-width_at_near, -height_at_near, pyramid.near, 1.0)).truncate(),
         world_matrix_pyramid.mul_vec4(glam::Vec4::new(width_at_near, -height_at_near, pyramid.near, 1.0)).truncate(),
@@ -234,8 +243,7 @@ pub fn box_sphere_intersection(a: &BoundingBox, b: &BoundingSphere, world_matrix
 }
 
 pub fn box_cone_intersection(a: &BoundingBox, b: &BoundingCone, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
-    // TODO
-    unimplemented!();
+    crate::gjk::gjk_intersection(a, b, world_matrix_a, world_matrix_b)
 }
 
 pub fn half_pyramid_pyramid_intersection(a: &BoundingPyramid, b: &BoundingPyramid, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
@@ -268,10 +276,7 @@ pub fn pyramid_sphere_intersection(a: &BoundingPyramid, b: &BoundingSphere, worl
         let center = world_matrix_a.w_axis().truncate();
         let (x_dist, y_dist) = if behind_near_plane {
             closest_point = project_point_onto_half_plane(point_of_sphere_b, &half_planes_of_pyramid_a[4]);
-            let near_factor = a.near/a.far;
-            let width_at_near = near_factor*a.width_at_far;
-            let height_at_near = near_factor*a.height_at_far;
-            (width_at_near, height_at_near)
+            (radius_at_near(a.near, a.far, a.width_at_far), radius_at_near(a.near, a.far, a.height_at_far))
         } else {
             closest_point = project_point_onto_half_plane(point_of_sphere_b, &half_planes_of_pyramid_a[5]);
             (a.width_at_far, a.height_at_far)
@@ -295,8 +300,7 @@ pub fn pyramid_sphere_intersection(a: &BoundingPyramid, b: &BoundingSphere, worl
 }
 
 pub fn pyramid_cone_intersection(a: &BoundingPyramid, b: &BoundingCone, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
-    // TODO
-    unimplemented!();
+    crate::gjk::gjk_intersection(a, b, world_matrix_a, world_matrix_b)
 }
 
 pub fn sphere_sphere_intersection(a: &BoundingSphere, b: &BoundingSphere, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
@@ -305,47 +309,37 @@ pub fn sphere_sphere_intersection(a: &BoundingSphere, b: &BoundingSphere, world_
 }
 
 pub fn sphere_cone_intersection(a: &BoundingSphere, b: &BoundingCone, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
-    let origin = world_matrix_a.w_axis().truncate();
-    let direction = world_matrix_a.z_axis().truncate();
-    let point = world_matrix_b.z_axis().truncate();
-    let diagonal = point-origin;
+    let origin = world_matrix_b.w_axis().truncate();
+    let direction = world_matrix_b.z_axis().truncate();
+    let diagonal = world_matrix_a.w_axis().truncate()-origin;
     let parallel_distance = diagonal.dot(direction);
     if parallel_distance < b.near-a.radius || parallel_distance > b.far+a.radius {
         return false;
     }
-    let orthogonal = (diagonal-direction*parallel_distance).normalize();
-    let near_end = origin+direction*b.near+orthogonal*(b.radius_at_far*b.near/b.far);
-    let far_end = origin+direction*b.far+orthogonal*b.radius_at_far;
-    let normal = direction.cross(orthogonal).cross(far_end-origin).normalize();
-    let normal_distance = diagonal.dot(normal);
-    if normal_distance > a.radius {
-        return false;
-    }
-    if normal_distance < 0.0 {
-        return true;
-    }
-    if parallel_distance < b.near && (near_end-point).length() > a.radius {
-        return false;
-    }
-    if parallel_distance > b.far && (far_end-point).length() > a.radius {
-        return false;
+    // The closest point lies in the half plane spanned by the axis and the sphere center,
+    // in which the cone is a trapezoid between the axis, its caps and its side
+    let point = glam::Vec2::new(parallel_distance, (diagonal-direction*parallel_distance).length());
+    let radius_at_near = radius_at_near(b.near, b.far, b.radius_at_far);
+    if b.near <= point.x() && point.x() <= b.far {
+        let radius = if b.far > b.near {
+            radius_at_near+(b.radius_at_far-radius_at_near)*(point.x()-b.near)/(b.far-b.near)
+        } else {
+            b.radius_at_far
+        };
+        if point.y() <= radius {
+            return true;
+        }
     }
-    true
+    let near_end = glam::Vec2::new(b.near, radius_at_near);
+    let far_end = glam::Vec2::new(b.far, b.radius_at_far);
+    let distance = segment_point_distance(near_end, far_end, point)
+        .min(segment_point_distance(glam::Vec2::new(b.near, 0.0), near_end, point))
+        .min(segment_point_distance(glam::Vec2::new(b.far, 0.0), far_end, point));
+    distance <= a.radius
 }
 
 pub fn cone_cone_intersection(a: &BoundingCone, b: &BoundingCone, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
-    /*let origin_a = world_matrix_a.w_axis().truncate();
-    let direction_a = world_matrix_a.z_axis().truncate();
-    let origin_b = world_matrix_b.w_axis().truncate();
-    let direction_b = world_matrix_b.z_axis().truncate();
-    let normal = direction_a.cross(direction_b);
-    let normal_length = normal.length();
-    if normal_length == 0.0 {
-        // TODO
-    }
-    let normal = normal/normal_length;*/
-
-    unimplemented!();
+    crate::gjk::gjk_intersection(a, b, world_matrix_a, world_matrix_b)
 }
 
 pub fn bounding_volume_intersection(a: &BoundingVolume, b: &BoundingVolume, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
@@ -386,6 +380,153 @@ pub fn bounding_volume_intersection(a: &BoundingVolume, b: &BoundingVolume, worl
 }
 
 
+pub fn bounding_box_of_points(points: &[glam::Vec3A]) -> BoundingBox {
+    let mut min = glam::Vec3A::splat(f32::INFINITY);
+    let mut max = glam::Vec3A::splat(-f32::INFINITY);
+    for p in points {
+        min = min.min(*p);
+        max = max.max(*p);
+    }
+    BoundingBox { min, max }
+}
+
+fn truncated_cone_to_sphere(near: f32, far: f32, radius_at_near: f32, radius_at_far: f32) -> (glam::Vec3A, BoundingSphere) {
+    let center_z = if far > near {
+        ((far*far+radius_at_far*radius_at_far-near*near-radius_at_near*radius_at_near)/(2.0*(far-near))).max(near).min(far)
+    } else {
+        near
+    };
+    let radius = f32::max(
+        ((near-center_z)*(near-center_z)+radius_at_near*radius_at_near).sqrt(),
+        ((far-center_z)*(far-center_z)+radius_at_far*radius_at_far).sqrt()
+    );
+    (glam::Vec3A::new(0.0, 0.0, center_z), BoundingSphere { radius })
+}
+
+pub fn box_to_sphere(bb: &BoundingBox) -> (glam::Vec3A, BoundingSphere) {
+    let center = (bb.min+bb.max)*0.5;
+    (center, BoundingSphere { radius: (bb.max-center).length() })
+}
+
+/// The apex is at the origin, a degenerate frustum with far == 0 is a disc
+pub(crate) fn radius_at_near(near: f32, far: f32, radius_at_far: f32) -> f32 {
+    if far > 0.0 {
+        radius_at_far*near/far
+    } else {
+        radius_at_far
+    }
+}
+
+/// Infinite frusta (e.g. an infinite far plane) are enclosed by an infinite sphere
+fn frustum_to_sphere(near: f32, far: f32, radius_at_far: f32) -> (glam::Vec3A, BoundingSphere) {
+    if !far.is_finite() || !radius_at_far.is_finite() {
+        return (glam::Vec3A::splat(0.0), BoundingSphere { radius: f32::INFINITY });
+    }
+    truncated_cone_to_sphere(near, far, radius_at_near(near, far, radius_at_far), radius_at_far)
+}
+
+pub fn pyramid_to_sphere(pyramid: &BoundingPyramid) -> (glam::Vec3A, BoundingSphere) {
+    let radius_at_far = (pyramid.width_at_far*pyramid.width_at_far+pyramid.height_at_far*pyramid.height_at_far).sqrt();
+    frustum_to_sphere(pyramid.near, pyramid.far, radius_at_far)
+}
+
+pub fn cone_to_sphere(cone: &BoundingCone) -> (glam::Vec3A, BoundingSphere) {
+    frustum_to_sphere(cone.near, cone.far, cone.radius_at_far)
+}
+
+/// Returns the center in local space and the enclosing sphere
+pub fn bounding_volume_to_sphere(bv: &BoundingVolume) -> (glam::Vec3A, BoundingSphere) {
+    match bv {
+        BoundingVolume::Box(bb) => box_to_sphere(bb),
+        BoundingVolume::Pyramid(pyramid) => pyramid_to_sphere(pyramid),
+        BoundingVolume::Sphere(sphere) => (glam::Vec3A::splat(0.0), *sphere),
+        BoundingVolume::Cone(cone) => cone_to_sphere(cone),
+    }
+}
+
+fn max_scale_of_matrix(world_matrix: &glam::Mat4) -> f32 {
+    world_matrix.x_axis().truncate().length()
+        .max(world_matrix.y_axis().truncate().length())
+        .max(world_matrix.z_axis().truncate().length())
+}
+
+pub fn transform_sphere(center: glam::Vec3A, sphere: &BoundingSphere, world_matrix: &glam::Mat4) -> (glam::Vec3A, BoundingSphere) {
+    (world_matrix.mul_vec4(center.extend(1.0)).truncate(), BoundingSphere { radius: sphere.radius*max_scale_of_matrix(world_matrix) })
+}
+
+pub fn box_box_union(a: &BoundingBox, b: &BoundingBox) -> BoundingBox {
+    BoundingBox {
+        min: a.min.min(b.min),
+        max: a.max.max(b.max),
+    }
+}
+
+pub fn sphere_sphere_union(center_a: glam::Vec3A, a: &BoundingSphere, center_b: glam::Vec3A, b: &BoundingSphere) -> (glam::Vec3A, BoundingSphere) {
+    let diagonal = center_b-center_a;
+    let distance = diagonal.length();
+    if distance+b.radius <= a.radius {
+        return (center_a, *a);
+    }
+    if distance+a.radius <= b.radius {
+        return (center_b, *b);
+    }
+    let radius = (distance+a.radius+b.radius)*0.5;
+    (center_a+diagonal*((radius-a.radius)/distance), BoundingSphere { radius })
+}
+
+macro_rules! extent_of_disc {
+    ($world_matrix:expr, $radius:expr) => {
+        {
+            let x_axis = $world_matrix.x_axis().truncate();
+            let y_axis = $world_matrix.y_axis().truncate();
+            let squared = x_axis*x_axis+y_axis*y_axis;
+            glam::Vec3A::new(squared[0].sqrt(), squared[1].sqrt(), squared[2].sqrt())*$radius
+        }
+    }
+}
+
+/// Axis aligned box in world space which encloses the bounding volume
+pub fn bounding_volume_to_world_box(bv: &BoundingVolume, world_matrix: &glam::Mat4) -> BoundingBox {
+    match bv {
+        BoundingVolume::Box(bb) => bounding_box_of_points(&points_of_bounding_box(bb, world_matrix)),
+        BoundingVolume::Pyramid(pyramid) => bounding_box_of_points(&points_of_bounding_pyramid(pyramid, world_matrix)),
+        BoundingVolume::Sphere(sphere) => {
+            let center = world_matrix.w_axis().truncate();
+            let x_axis = world_matrix.x_axis().truncate();
+            let y_axis = world_matrix.y_axis().truncate();
+            let z_axis = world_matrix.z_axis().truncate();
+            let squared = x_axis*x_axis+y_axis*y_axis+z_axis*z_axis;
+            let extent = glam::Vec3A::new(squared[0].sqrt(), squared[1].sqrt(), squared[2].sqrt())*sphere.radius;
+            BoundingBox {
+                min: center-extent,
+                max: center+extent,
+            }
+        },
+        BoundingVolume::Cone(cone) => {
+            let near_center = world_matrix.mul_vec4(glam::Vec4::new(0.0, 0.0, cone.near, 1.0)).truncate();
+            let far_center = world_matrix.mul_vec4(glam::Vec4::new(0.0, 0.0, cone.far, 1.0)).truncate();
+            let near_extent = extent_of_disc!(world_matrix, radius_at_near(cone.near, cone.far, cone.radius_at_far));
+            let far_extent = extent_of_disc!(world_matrix, cone.radius_at_far);
+            BoundingBox {
+                min: (near_center-near_extent).min(far_center-far_extent),
+                max: (near_center+near_extent).max(far_center+far_extent),
+            }
+        },
+    }
+}
+
+pub fn bounding_volume_union(a: &BoundingVolume, b: &BoundingVolume, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> BoundingBox {
+    box_box_union(&bounding_volume_to_world_box(a, world_matrix_a), &bounding_volume_to_world_box(b, world_matrix_b))
+}
+
+pub fn bounding_volume_sphere_union(a: &BoundingVolume, b: &BoundingVolume, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> (glam::Vec3A, BoundingSphere) {
+    let (center_a, sphere_a) = bounding_volume_to_sphere(a);
+    let (center_a, sphere_a) = transform_sphere(center_a, &sphere_a, world_matrix_a);
+    let (center_b, sphere_b) = bounding_volume_to_sphere(b);
+    let (center_b, sphere_b) = transform_sphere(center_b, &sphere_b, world_matrix_b);
+    sphere_sphere_union(center_a, &sphere_a, center_b, &sphere_b)
+}
+
 /*#[cfg(test)]
 mod tests {
     use super::*;
@@ -406,3 +547,129 @@ mod tests {
         assert_eq!(sphere_cone_intersection(&sphere, &cone, world_matrix_a, world_matrix_b), true);
     }
 }*/
+
+#[cfg(test)]
+mod conversion_tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn assert_sphere_contains(center: glam::Vec3A, sphere: &BoundingSphere, point: glam::Vec3A) {
+        assert!((point-center).length() <= sphere.radius+EPSILON, "{:?} is not inside {:?} at {:?}", point, sphere, center);
+    }
+
+    pub(super) fn corners_of_box(bb: &BoundingBox) -> Vec<glam::Vec3A> {
+        (0..8).map(|i| glam::Vec3A::new(
+            if i&1 == 0 { bb.min[0] } else { bb.max[0] },
+            if i&2 == 0 { bb.min[1] } else { bb.max[1] },
+            if i&4 == 0 { bb.min[2] } else { bb.max[2] },
+        )).collect()
+    }
+
+    #[test]
+    fn test_box_to_sphere() {
+        let bb = BoundingBox {
+            min: glam::Vec3A::new(-1.0, 2.0, -3.0),
+            max: glam::Vec3A::new(3.0, 4.0, 5.0),
+        };
+        let (center, sphere) = box_to_sphere(&bb);
+        assert!((center-glam::Vec3A::new(1.0, 3.0, 1.0)).length() < EPSILON);
+        assert!((sphere.radius-21.0f32.sqrt()).abs() < EPSILON);
+        for corner in corners_of_box(&bb) {
+            assert_sphere_contains(center, &sphere, corner);
+        }
+    }
+
+    #[test]
+    fn test_transform_sphere() {
+        let bb = BoundingBox {
+            min: glam::Vec3A::splat(-1.0),
+            max: glam::Vec3A::new(1.0, 2.0, 1.0),
+        };
+        let world_matrix = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::new(2.0, 0.5, 3.0),
+            glam::Quat::from_rotation_ypr(0.3, -0.7, 1.1),
+            glam::Vec3::new(10.0, -5.0, 2.0),
+        );
+        let (center, sphere) = box_to_sphere(&bb);
+        let (center, sphere) = transform_sphere(center, &sphere, &world_matrix);
+        for corner in corners_of_box(&bb) {
+            assert_sphere_contains(center, &sphere, world_matrix.mul_vec4(corner.extend(1.0)).truncate());
+        }
+    }
+
+    #[test]
+    fn test_sphere_sphere_union() {
+        let cases = [
+            (glam::Vec3A::new(0.0, 0.0, 0.0), 1.0, glam::Vec3A::new(5.0, 0.0, 0.0), 2.0),
+            (glam::Vec3A::new(0.0, 0.0, 0.0), 3.0, glam::Vec3A::new(1.0, 1.0, 0.0), 1.0),
+            (glam::Vec3A::new(1.0, 1.0, 0.0), 1.0, glam::Vec3A::new(0.0, 0.0, 0.0), 3.0),
+            (glam::Vec3A::new(-1.0, 2.0, 3.0), 2.0, glam::Vec3A::new(0.0, 1.0, 2.0), 2.5),
+            (glam::Vec3A::new(2.0, 2.0, 2.0), 1.0, glam::Vec3A::new(2.0, 2.0, 2.0), 1.0),
+        ];
+        for (center_a, radius_a, center_b, radius_b) in cases.iter() {
+            let a = BoundingSphere { radius: *radius_a };
+            let b = BoundingSphere { radius: *radius_b };
+            let (center, sphere) = sphere_sphere_union(*center_a, &a, *center_b, &b);
+            assert!(sphere.radius <= radius_a.max(*radius_b)+(*center_b-*center_a).length()*0.5+EPSILON);
+            for direction in corners_of_box(&BoundingBox { min: glam::Vec3A::splat(-1.0), max: glam::Vec3A::splat(1.0) }) {
+                let direction = direction.normalize();
+                assert_sphere_contains(center, &sphere, *center_a+direction*a.radius);
+                assert_sphere_contains(center, &sphere, *center_b+direction*b.radius);
+            }
+        }
+    }
+
+    #[test]
+    fn test_frustum_to_sphere() {
+        let cone = BoundingCone {
+            near: 1.0,
+            far: 10.0,
+            radius_at_far: 5.0,
+        };
+        let (center, sphere) = cone_to_sphere(&cone);
+        for point in [glam::Vec3A::new(0.5, 0.0, 1.0), glam::Vec3A::new(0.0, -0.5, 1.0), glam::Vec3A::new(5.0, 0.0, 10.0), glam::Vec3A::new(0.0, -5.0, 10.0)].iter() {
+            assert_sphere_contains(center, &sphere, *point);
+        }
+        let pyramid = BoundingPyramid {
+            near: 0.0,
+            far: 0.0,
+            width_at_far: 3.0,
+            height_at_far: 4.0,
+        };
+        let (center, sphere) = pyramid_to_sphere(&pyramid);
+        assert_eq!(center, glam::Vec3A::splat(0.0));
+        assert!((sphere.radius-5.0).abs() < EPSILON);
+        let infinite_cone = BoundingCone {
+            near: 1.0,
+            far: f32::INFINITY,
+            radius_at_far: f32::INFINITY,
+        };
+        let (center, sphere) = cone_to_sphere(&infinite_cone);
+        assert!(!center[0].is_nan() && !center[1].is_nan() && !center[2].is_nan());
+        assert_eq!(sphere.radius, f32::INFINITY);
+    }
+}
+
+#[cfg(test)]
+mod intersection_tests {
+    use super::*;
+
+    #[test]
+    fn test_points_double_plane_intersection() {
+        let double_plane = DoublePlane {
+            normal: glam::Vec3A::new(1.0, 0.0, 0.0),
+            min: -0.5,
+            max: 0.5,
+        };
+        let bb = BoundingBox {
+            min: glam::Vec3A::new(-2.0, -1.0, -1.0),
+            max: glam::Vec3A::new(2.0, 1.0, 1.0),
+        };
+        let straddling_corners = conversion_tests::corners_of_box(&bb);
+        assert!(straddling_corners.iter().all(|corner| !point_double_plane_intersection(*corner, &double_plane)), "No corner is inside of the slab");
+        assert!(points_double_plane_intersection(&straddling_corners, &double_plane), "The box straddles the slab");
+        let separated_corners: Vec<glam::Vec3A> = straddling_corners.iter().map(|corner| *corner+glam::Vec3A::new(3.0, 0.0, 0.0)).collect();
+        assert!(!points_double_plane_intersection(&separated_corners, &double_plane));
+    }
+}
@@ -16,10 +16,15 @@ pub fn ray_sphere_intersection(ray: &Ray, sphere_radius: f32) -> Option<(f32, gl
     return Some((parallel_dist*2.0, origin-parallel_dist*ray.direction, origin+parallel_dist*ray.direction));
 }
 
+/// Depth of the culling volume if the far plane is at infinity
+const INFINITE_FAR_CULLING_DISTANCE: f32 = 1.0e9;
+
+/// Projections are reverse-Z: the near plane maps to depth 1 and the far plane to depth 0
 #[derive(Default, Debug)]
 pub struct Camera {
     near: f32,
     far: f32,
+    fov_y_radians: f32,
     world_matrix: glam::Mat4,
     inverse_world_matrix: glam::Mat4,
     projection_matrix: glam::Mat4,
@@ -38,13 +43,14 @@ impl Camera {
     pub fn set_orthographic(&mut self, near: f32, far: f32, width: f32, height: f32) {
         self.near = near;
         self.far = far;
+        self.fov_y_radians = 0.0;
         self.projection_matrix = glam::Mat4::orthographic_rh(
             -0.5*width,
             0.5*width,
             -0.5*height,
             0.5*height,
-            self.near,
-            self.far
+            self.far,
+            self.near
         );
         self.bounding_volume = crate::bounding_volume::BoundingVolume::Box(crate::bounding_volume::BoundingBox {
             min: glam::Vec3A::new(-0.5*width, -0.5*height, near),
@@ -53,19 +59,30 @@ impl Camera {
         self.update();
     }
 
+    /// Pass std::f32::INFINITY as far for an infinite far plane
     pub fn set_perspective(&mut self, near: f32, far: f32, fov_y_radians: f32, aspect_ratio: f32) {
         self.near = near;
         self.far = far;
-        self.projection_matrix = glam::Mat4::perspective_rh(
-            fov_y_radians,
-            aspect_ratio,
-            self.near,
-            self.far
-        );
-        let height_at_far = fov_y_radians.tan()*far;
+        self.fov_y_radians = fov_y_radians;
+        self.projection_matrix = if far.is_infinite() {
+            glam::Mat4::perspective_infinite_reverse_rh(
+                fov_y_radians,
+                aspect_ratio,
+                self.near
+            )
+        } else {
+            glam::Mat4::perspective_rh(
+                fov_y_radians,
+                aspect_ratio,
+                self.far,
+                self.near
+            )
+        };
+        let culling_far = if far.is_infinite() { INFINITE_FAR_CULLING_DISTANCE } else { far };
+        let height_at_far = (fov_y_radians*0.5).tan()*culling_far;
         self.bounding_volume = crate::bounding_volume::BoundingVolume::Pyramid(crate::bounding_volume::BoundingPyramid {
             near,
-            far,
+            far: culling_far,
             width_at_far: height_at_far*aspect_ratio,
             height_at_far,
         });
@@ -77,6 +94,27 @@ impl Camera {
         self.update();
     }
 
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+
+    pub fn get_fov_y_radians(&self) -> f32 {
+        self.fov_y_radians
+    }
+
+    pub fn get_bounding_volume(&self) -> &crate::bounding_volume::BoundingVolume {
+        &self.bounding_volume
+    }
+
+    /// Bounding volumes extend along +z, but the camera looks along -z
+    pub fn get_bounding_volume_world_matrix(&self) -> glam::Mat4 {
+        self.world_matrix*glam::Mat4::from_rotation_y(std::f32::consts::PI)
+    }
+
     pub fn get_world_matrix(&self) -> glam::Mat4{
         self.world_matrix
     }
@@ -94,7 +132,8 @@ impl Camera {
     }
 
     pub fn get_view_ray(&self, x: f32, y: f32) -> Ray {
-        let ndc_pos = glam::Vec3A::new(x, y, 0.0);
+        // Reverse-Z: depth 1 is the near plane, depth 0 would be at infinity
+        let ndc_pos = glam::Vec3A::new(x, y, 1.0);
         let mut clip_pos = ndc_pos.extend(self.projection_matrix.w_axis().z()/(ndc_pos.z()-self.projection_matrix.z_axis().z()/self.projection_matrix.z_axis().w()));
         *clip_pos.x_mut() *= clip_pos.w();
         *clip_pos.y_mut() *= clip_pos.w();
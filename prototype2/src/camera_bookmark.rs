@@ -0,0 +1,131 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub view_quaternion: glam::Quat,
+    pub view_zoom: f32,
+    pub target: glam::Vec3,
+}
+
+impl CameraBookmark {
+    pub fn from_orbit_camera(name: String, orbit_camera: &crate::orbit_camera::OrbitCamera) -> Self {
+        Self {
+            name,
+            view_quaternion: orbit_camera.get_view_quaternion(),
+            view_zoom: orbit_camera.target_view_zoom,
+            target: glam::Vec3::from(orbit_camera.follow_target),
+        }
+    }
+
+    /// Jumps to the bookmark without animation
+    pub fn apply_to_orbit_camera(&self, orbit_camera: &mut crate::orbit_camera::OrbitCamera) {
+        orbit_camera.cancel_animation();
+        orbit_camera.set_view_quaternion(self.view_quaternion);
+        orbit_camera.view_zoom = self.view_zoom;
+        orbit_camera.target_view_zoom = self.view_zoom;
+        orbit_camera.target = glam::Vec3A::from(self.target);
+        orbit_camera.follow_target = orbit_camera.target;
+    }
+
+    pub fn animate_orbit_camera(&self, orbit_camera: &mut crate::orbit_camera::OrbitCamera, duration: f32) {
+        orbit_camera.follow_target = glam::Vec3A::from(self.target);
+        orbit_camera.animate_to(self.view_quaternion, self.view_zoom, duration, None);
+    }
+}
+
+/// Named views and the view of the last session
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct CameraBookmarks {
+    pub bookmarks: Vec<CameraBookmark>,
+    pub last_view: Option<CameraBookmark>,
+}
+
+impl CameraBookmarks {
+    pub fn load(path: &std::path::Path) -> Result<Self, crate::assets::AssetError> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// A missing file yields no bookmarks, other errors (e.g. a malformed file) are returned
+    pub fn load_or_default(path: &std::path::Path) -> Result<Self, crate::assets::AssetError> {
+        match Self::load(path) {
+            Err(crate::assets::AssetError::IoError(error)) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CameraBookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    /// Replaces a bookmark of the same name
+    pub fn insert(&mut self, bookmark: CameraBookmark) {
+        if let Some(index) = self.bookmarks.iter().position(|other| other.name == bookmark.name) {
+            self.bookmarks[index] = bookmark;
+        } else {
+            self.bookmarks.push(bookmark);
+            self.bookmarks.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CameraBookmark> {
+        self.bookmarks.iter().position(|bookmark| bookmark.name == name).map(|index| self.bookmarks.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(name: &str, yaw: f32) -> CameraBookmark {
+        CameraBookmark {
+            name: name.to_owned(),
+            view_quaternion: glam::Quat::from_rotation_y(yaw),
+            view_zoom: 4.0+yaw,
+            target: glam::Vec3::new(yaw, 1.0, -2.0),
+        }
+    }
+
+    fn temporary_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("camera_bookmarks_{}_{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let mut camera_bookmarks = CameraBookmarks::default();
+        camera_bookmarks.insert(bookmark("2", 0.5));
+        camera_bookmarks.insert(bookmark("1", -1.0));
+        camera_bookmarks.last_view = Some(bookmark("", 2.0));
+        let path = temporary_path("round_trip");
+        camera_bookmarks.save(&path).unwrap();
+        let loaded = CameraBookmarks::load_or_default(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<&str> = loaded.bookmarks.iter().map(|bookmark| bookmark.name.as_str()).collect();
+        assert_eq!(names, vec!["1", "2"]);
+        for (a, b) in loaded.bookmarks.iter().chain(loaded.last_view.iter()).zip(camera_bookmarks.bookmarks.iter().chain(camera_bookmarks.last_view.iter())) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.view_quaternion, b.view_quaternion);
+            assert_eq!(a.view_zoom, b.view_zoom);
+            assert_eq!(a.target, b.target);
+        }
+        assert!(loaded.last_view.is_some());
+    }
+
+    #[test]
+    fn test_load_or_default() {
+        let path = temporary_path("missing");
+        assert!(CameraBookmarks::load_or_default(&path).unwrap().bookmarks.is_empty());
+        let path = temporary_path("malformed");
+        std::fs::write(&path, "{ \"bookmarks\": [").unwrap();
+        let result = CameraBookmarks::load_or_default(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
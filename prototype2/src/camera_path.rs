@@ -0,0 +1,341 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub position: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub fov_y_radians: f32,
+    pub near: f32,
+    /// None for an infinite far plane, as JSON has no infinity
+    pub far: Option<f32>,
+}
+
+impl CameraKeyframe {
+    pub fn from_camera(time: f32, camera: &crate::camera::Camera) -> Self {
+        let (_scale, rotation, position) = camera.get_world_matrix().to_scale_rotation_translation();
+        Self {
+            time,
+            position,
+            rotation: rotation.normalize(),
+            fov_y_radians: camera.get_fov_y_radians(),
+            near: camera.get_near(),
+            far: if camera.get_far().is_infinite() { None } else { Some(camera.get_far()) },
+        }
+    }
+
+    pub fn get_world_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_rotation_translation(self.rotation, self.position)
+    }
+
+    /// The aspect ratio is not recorded, as it depends on the viewport of the playback
+    pub fn apply_to_camera(&self, camera: &mut crate::camera::Camera, aspect_ratio: f32) {
+        camera.set_perspective(self.near, self.far.unwrap_or(std::f32::INFINITY), self.fov_y_radians, aspect_ratio);
+        camera.set_world_matrix(self.get_world_matrix());
+    }
+}
+
+fn quat_conjugate(q: glam::Quat) -> glam::Quat {
+    glam::Quat::from_xyzw(-q.x(), -q.y(), -q.z(), q.w())
+}
+
+/// Logarithm of a unit quaternion, the result is a pure quaternion
+fn quat_log(q: glam::Quat) -> glam::Vec3 {
+    let vector = glam::Vec3::new(q.x(), q.y(), q.z());
+    let sin_half_angle = vector.length();
+    if sin_half_angle < 0.000001 {
+        return vector;
+    }
+    vector*(sin_half_angle.atan2(q.w())/sin_half_angle)
+}
+
+/// Exponential of a pure quaternion, the result is a unit quaternion
+fn quat_exp(vector: glam::Vec3) -> glam::Quat {
+    let half_angle = vector.length();
+    if half_angle < 0.000001 {
+        return glam::Quat::from_xyzw(vector.x(), vector.y(), vector.z(), 1.0).normalize();
+    }
+    let vector = vector*(half_angle.sin()/half_angle);
+    glam::Quat::from_xyzw(vector.x(), vector.y(), vector.z(), half_angle.cos())
+}
+
+/// Inner control point of squad at q for its neighbors
+fn squad_control_point(previous: glam::Quat, q: glam::Quat, next: glam::Quat) -> glam::Quat {
+    let inverse = quat_conjugate(q);
+    let sum = quat_log(inverse*next)+quat_log(inverse*previous);
+    (q*quat_exp(sum*-0.25)).normalize()
+}
+
+fn squad(q1: glam::Quat, q2: glam::Quat, s1: glam::Quat, s2: glam::Quat, t: f32) -> glam::Quat {
+    let outer = q1.slerp(q2, t);
+    let inner = s1.slerp(s2, t);
+    outer.slerp(inner, 2.0*t*(1.0-t)).normalize()
+}
+
+/// Catmull-Rom tangents for non uniformly spaced keyframes
+fn catmull_rom_tangent(previous: &CameraKeyframe, next: &CameraKeyframe) -> glam::Vec3 {
+    let time_difference = next.time-previous.time;
+    if time_difference <= 0.0 {
+        return glam::Vec3::splat(0.0);
+    }
+    (next.position-previous.position)/time_difference
+}
+
+fn hermite(p1: glam::Vec3, m1: glam::Vec3, p2: glam::Vec3, m2: glam::Vec3, duration: f32, t: f32) -> glam::Vec3 {
+    let t2 = t*t;
+    let t3 = t2*t;
+    p1*(2.0*t3-3.0*t2+1.0)+m1*(duration*(t3-2.0*t2+t))+p2*(-2.0*t3+3.0*t2)+m2*(duration*(t3-t2))
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    pub fn load(path: &std::path::Path) -> Result<Self, crate::assets::AssetError> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.keyframes.last().map(|keyframe| keyframe.time).unwrap_or(0.0)
+    }
+
+    /// Keyframes are kept sorted by time, a keyframe at the same time replaces the old one.
+    /// Returns false and ignores the keyframe if its time is NaN or infinite.
+    pub fn insert(&mut self, keyframe: CameraKeyframe) -> bool {
+        if !keyframe.time.is_finite() {
+            return false;
+        }
+        match self.keyframes.binary_search_by(|other| other.time.partial_cmp(&keyframe.time).unwrap()) {
+            Ok(index) => self.keyframes[index] = keyframe,
+            Err(index) => self.keyframes.insert(index, keyframe),
+        }
+        true
+    }
+
+    pub fn record(&mut self, time: f32, camera: &crate::camera::Camera) -> bool {
+        self.insert(CameraKeyframe::from_camera(time, camera))
+    }
+
+    pub fn sample(&self, time: f32) -> Option<CameraKeyframe> {
+        let count = self.keyframes.len();
+        if count == 0 {
+            return None;
+        }
+        if count == 1 || time <= self.keyframes[0].time {
+            return Some(self.keyframes[0]);
+        }
+        if time >= self.keyframes[count-1].time {
+            return Some(self.keyframes[count-1]);
+        }
+        let index = self.keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap();
+        let k0 = &self.keyframes[index.max(1)-1];
+        let k1 = &self.keyframes[index];
+        let k2 = &self.keyframes[index+1];
+        let k3 = &self.keyframes[(index+2).min(count-1)];
+        let duration = k2.time-k1.time;
+        let t = if duration > 0.0 { (time-k1.time)/duration } else { 0.0 };
+        let position = hermite(k1.position, catmull_rom_tangent(k0, k2), k2.position, catmull_rom_tangent(k1, k3), duration, t);
+        // Keep all rotations in the hemisphere of q1, so that squad takes the short way
+        let q1 = k1.rotation;
+        let same_hemisphere = |q: glam::Quat| if q1.dot(q) < 0.0 { -q } else { q };
+        let q0 = same_hemisphere(k0.rotation);
+        let q2 = same_hemisphere(k2.rotation);
+        let q3 = if q2.dot(k3.rotation) < 0.0 { -k3.rotation } else { k3.rotation };
+        let rotation = squad(q1, q2, squad_control_point(q0, q1, q2), squad_control_point(q1, q2, q3), t);
+        Some(CameraKeyframe {
+            time,
+            position,
+            rotation,
+            fov_y_radians: k1.fov_y_radians+(k2.fov_y_radians-k1.fov_y_radians)*t,
+            near: k1.near+(k2.near-k1.near)*t,
+            far: match (k1.far, k2.far) {
+                (Some(far_1), Some(far_2)) => Some(far_1+(far_2-far_1)*t),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Plays a path back, with a fixed timestep the sampled frames are reproducible
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub looping: bool,
+    elapsed: f64,
+    /// Elapsed time at the first of the consecutive fixed steps and how many were taken since
+    step_origin: f64,
+    step_count: u64,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath, looping: bool) -> Self {
+        Self {
+            path,
+            looping,
+            elapsed: 0.0,
+            step_origin: 0.0,
+            step_count: 0,
+        }
+    }
+
+    pub fn get_time(&self) -> f32 {
+        let duration = self.path.get_duration() as f64;
+        if self.looping && duration > 0.0 {
+            self.elapsed.rem_euclid(duration) as f32
+        } else {
+            self.elapsed.min(duration) as f32
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.elapsed >= self.path.get_duration() as f64
+    }
+
+    pub fn advance(&mut self, delta_time: f32) -> Option<CameraKeyframe> {
+        self.elapsed += delta_time as f64;
+        self.step_origin = self.elapsed;
+        self.step_count = 0;
+        self.path.sample(self.get_time())
+    }
+
+    /// Continues from the time reached by the last advance. The time is derived from the number of
+    /// consecutive steps instead of accumulated, so no rounding errors build up.
+    pub fn step(&mut self, fixed_delta_time: f32) -> Option<CameraKeyframe> {
+        self.step_count += 1;
+        self.elapsed = self.step_origin+self.step_count as f64*fixed_delta_time as f64;
+        self.path.sample(self.get_time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn keyframe(time: f32, position: glam::Vec3, yaw: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            position,
+            rotation: glam::Quat::from_rotation_y(yaw),
+            fov_y_radians: 1.0+time*0.1,
+            near: 0.1,
+            far: None,
+        }
+    }
+
+    fn path() -> CameraPath {
+        let mut path = CameraPath::default();
+        for (time, position, yaw) in [
+            (0.0, glam::Vec3::new(0.0, 0.0, 0.0), 0.0),
+            (1.0, glam::Vec3::new(2.0, 1.0, 0.0), 0.5),
+            (3.5, glam::Vec3::new(3.0, -1.0, 2.0), 1.5),
+            (4.0, glam::Vec3::new(-1.0, 0.0, 4.0), 2.5),
+            (6.0, glam::Vec3::new(0.0, 2.0, 1.0), -2.5),
+        ].iter() {
+            assert!(path.insert(keyframe(*time, *position, *yaw)));
+        }
+        path
+    }
+
+    fn assert_rotation_eq(a: glam::Quat, b: glam::Quat) {
+        assert!(a.dot(b).abs() > 1.0-EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_insert_keeps_order_and_rejects_non_finite_times() {
+        let mut path = path();
+        assert!(path.insert(keyframe(2.0, glam::Vec3::splat(1.0), 0.0)));
+        assert!(path.insert(keyframe(2.0, glam::Vec3::splat(2.0), 0.0)));
+        assert!(!path.insert(keyframe(std::f32::NAN, glam::Vec3::splat(0.0), 0.0)));
+        assert!(!path.insert(keyframe(std::f32::INFINITY, glam::Vec3::splat(0.0), 0.0)));
+        let times: Vec<f32> = path.keyframes.iter().map(|keyframe| keyframe.time).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0, 3.5, 4.0, 6.0]);
+        assert_eq!(path.keyframes[2].position, glam::Vec3::splat(2.0));
+    }
+
+    #[test]
+    fn test_sample_at_keyframes() {
+        let path = path();
+        for keyframe in path.keyframes.iter() {
+            let sample = path.sample(keyframe.time).unwrap();
+            assert!((sample.position-keyframe.position).length() < EPSILON, "{:?} != {:?}", sample.position, keyframe.position);
+            assert_rotation_eq(sample.rotation, keyframe.rotation);
+            assert!((sample.fov_y_radians-keyframe.fov_y_radians).abs() < EPSILON);
+        }
+        assert_eq!(path.sample(-1.0).unwrap().position, path.keyframes[0].position);
+        assert_eq!(path.sample(10.0).unwrap().position, path.keyframes[4].position);
+        assert!(CameraPath::default().sample(0.0).is_none());
+    }
+
+    #[test]
+    fn test_catmull_rom_tangent_continuity() {
+        let path = path();
+        let h = 0.001;
+        let position = |time: f32| path.sample(time).unwrap().position;
+        for (index, keyframe) in path.keyframes.iter().enumerate().skip(1).take(path.keyframes.len()-2) {
+            let tangent = catmull_rom_tangent(&path.keyframes[index-1], &path.keyframes[index+1]);
+            // Second order one sided differences, as the curvature differs on both sides
+            let before = (keyframe.position*3.0-position(keyframe.time-h)*4.0+position(keyframe.time-2.0*h))/(2.0*h);
+            let after = (keyframe.position*-3.0+position(keyframe.time+h)*4.0-position(keyframe.time+2.0*h))/(2.0*h);
+            assert!((before-after).length() < 0.01, "tangents differ at {}: {:?} {:?}", keyframe.time, before, after);
+            assert!((after-tangent).length() < 0.01, "tangent at {} is {:?} instead of {:?}", keyframe.time, after, tangent);
+        }
+    }
+
+    #[test]
+    fn test_fixed_step_determinism() {
+        let fixed_delta_time = 1.0/60.0;
+        let mut player_a = CameraPathPlayer::new(path(), false);
+        let mut player_b = CameraPathPlayer::new(path(), false);
+        for frame_index in 1..=400u64 {
+            let a = player_a.step(fixed_delta_time).unwrap();
+            let b = player_b.step(fixed_delta_time).unwrap();
+            assert_eq!(a.time.to_bits(), b.time.to_bits());
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.rotation, b.rotation);
+            assert_eq!(a.time, (frame_index as f64*fixed_delta_time as f64).min(6.0) as f32);
+        }
+        assert!(player_a.is_finished());
+    }
+
+    #[test]
+    fn test_step_continues_after_advance() {
+        let fixed_delta_time = 1.0/60.0;
+        let mut player = CameraPathPlayer::new(path(), false);
+        player.step(fixed_delta_time);
+        player.advance(0.5);
+        let start_time = player.get_time() as f64;
+        for step_count in 1..=60u64 {
+            let keyframe = player.step(fixed_delta_time).unwrap();
+            assert!((keyframe.time-(start_time+step_count as f64*fixed_delta_time as f64) as f32).abs() < EPSILON);
+        }
+        player.advance(0.25);
+        assert!((player.get_time()-(start_time as f32+1.25)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut path = path();
+        path.keyframes[1].far = Some(1000.0);
+        let json = serde_json::to_string(&path).unwrap();
+        let loaded: CameraPath = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.keyframes.len(), path.keyframes.len());
+        for (a, b) in loaded.keyframes.iter().zip(path.keyframes.iter()) {
+            assert_eq!(a.time, b.time);
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.rotation, b.rotation);
+            assert_eq!(a.fov_y_radians, b.fov_y_radians);
+            assert_eq!(a.near, b.near);
+            assert_eq!(a.far, b.far);
+        }
+    }
+}
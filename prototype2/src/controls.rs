@@ -1,25 +1,58 @@
 use iced_wgpu::{Renderer, wgpu};
 use iced_winit::{
     Text, Align, Color, Column, Command, Element, Length, Program, Row,
+    button, Button,
     // slider, Slider,
     winit::dpi::{PhysicalPosition, PhysicalSize},
     winit::event::{VirtualKeyCode, KeyboardInput, ModifiersState, MouseScrollDelta, TouchPhase, ElementState, MouseButton},
 };
 
+const CAMERA_PATH_FILE: &str = "camera_path.json";
+const CAMERA_BOOKMARKS_FILE: &str = "camera_bookmarks.json";
+const CAMERA_BOOKMARK_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+];
+const CAMERA_PATH_KEYFRAME_INTERVAL: f32 = 2.0;
+
+/// Field which can be picked from the field list to fly to it
+pub struct FieldListEntry {
+    pub triangle_coordinate: crate::planet::TriangleCoordinate,
+    button: button::State,
+}
+
 pub struct Controls {
     pub render_options: crate::renderer::RenderOptions,
     pub viewport_size: PhysicalSize<u32>,
     pub pointer_state: ElementState,
     pub pointer_position: PhysicalPosition<f64>,
     pub normalized_pointer_position: glam::Vec2,
-    pub start_normalized_pointer_position: glam::Vec2,
+    pub previous_normalized_pointer_position: glam::Vec2,
     pub modifiers: ModifiersState,
-    pub view_quaternion: glam::Quat,
-    pub start_view_quaternion: glam::Quat,
+    pub orbit_camera: crate::orbit_camera::OrbitCamera,
     pub camera: crate::camera::Camera,
-    pub view_zoom: f32,
     pub planet: crate::planet::Planet,
     pub selection_matrix: glam::Mat4,
+    pub hovered_field: Option<crate::planet::TriangleCoordinate>,
+    pub focused_field: Option<crate::planet::TriangleCoordinate>,
+    /// Fields marked with a right click
+    pub field_list: Vec<FieldListEntry>,
+    pub camera_path: crate::camera_path::CameraPath,
+    pub camera_path_player: Option<crate::camera_path::CameraPathPlayer>,
+    pub camera_bookmarks: crate::camera_bookmark::CameraBookmarks,
+    /// False if the bookmarks file exists but could not be loaded, then it is not saved over
+    camera_bookmarks_savable: bool,
+    /// Taken and reset by the main loop in the next redraw
+    pub screenshot_request: Option<crate::screenshot::ScreenshotRequest>,
+    pub profiler_mode: crate::profiler::ProfilerMode,
+    /// Taken and reset by the main loop in the next redraw
+    pub trace_export_request: bool,
+    /// Last frame measured by the profiler, shown in the overlay
+    profiler_frame: Option<crate::profiler::ProfilerFrame>,
+    debug_view_button: button::State,
+    profiler_mode_button: button::State,
+    trace_export_button: button::State,
     // sliders: [slider::State; 3],
 }
 
@@ -33,43 +66,104 @@ pub enum Message {
     MouseWheel(MouseScrollDelta, TouchPhase),
     MouseInput(MouseButton, ElementState),
     Resized(PhysicalSize<u32>),
+    Tick(f32),
+    FlyToField(crate::planet::TriangleCoordinate),
+    ScreenshotSaved,
+    CycleDebugView,
+    CycleProfilerMode,
+    ExportTrace,
+    TraceExported,
+    ProfilerFrame(crate::profiler::ProfilerFrame),
     // SlidersChanged(glam::Vec3),
 }
 
 impl Controls {
     pub fn new(device: &wgpu::Device) -> Self {
-        let mut controls = Self {
-            render_options: crate::renderer::RenderOptions {
-                scale_factor: 1.0,
-                enable_frustum_culling: false,
-                enable_occulsion_culling: false,
-                enable_shadow_mapping: false,
+        let (camera_bookmarks, camera_bookmarks_savable) = match crate::camera_bookmark::CameraBookmarks::load_or_default(std::path::Path::new(CAMERA_BOOKMARKS_FILE)) {
+            Ok(camera_bookmarks) => (camera_bookmarks, true),
+            Err(error) => {
+                eprintln!("Could not load camera bookmarks, they will not be saved: {:?}", error);
+                (crate::camera_bookmark::CameraBookmarks::default(), false)
             },
+        };
+        let mut controls = Self {
+            render_options: crate::renderer::RenderOptions::default(),
             viewport_size: PhysicalSize::new(0, 0),
             pointer_state: ElementState::Released,
             pointer_position: PhysicalPosition::new(0.0, 0.0),
             normalized_pointer_position: glam::Vec2::splat(0.0),
-            start_normalized_pointer_position: glam::Vec2::splat(0.0),
+            previous_normalized_pointer_position: glam::Vec2::splat(0.0),
             modifiers: ModifiersState::default(),
-            view_quaternion: glam::Quat::identity(),
-            start_view_quaternion: glam::Quat::identity(),
+            orbit_camera: crate::orbit_camera::OrbitCamera::default(),
             camera: crate::camera::Camera::default(),
-            view_zoom: 3.0,
             planet: crate::planet::Planet::new(device, 5),
             selection_matrix: glam::Mat4::from_scale(glam::Vec3::splat(0.0)),
+            hovered_field: None,
+            focused_field: None,
+            field_list: Vec::new(),
+            camera_path: crate::camera_path::CameraPath::load(std::path::Path::new(CAMERA_PATH_FILE)).unwrap_or_default(),
+            camera_path_player: None,
+            camera_bookmarks,
+            camera_bookmarks_savable,
+            screenshot_request: None,
+            profiler_mode: crate::profiler::ProfilerMode::Disabled,
+            trace_export_request: false,
+            profiler_frame: None,
+            debug_view_button: button::State::new(),
+            profiler_mode_button: button::State::new(),
+            trace_export_button: button::State::new(),
             // sliders: Default::default(),
         };
+        if let Some(last_view) = &controls.camera_bookmarks.last_view {
+            last_view.apply_to_orbit_camera(&mut controls.orbit_camera);
+        }
         controls.update_camera_projection();
         controls
     }
 
+    /// Stores the current view as last_view, so that it is restored on the next startup
+    pub fn save_camera_bookmarks(&self) {
+        if !self.camera_bookmarks_savable {
+            return;
+        }
+        let mut camera_bookmarks = self.camera_bookmarks.clone();
+        camera_bookmarks.last_view = Some(crate::camera_bookmark::CameraBookmark::from_orbit_camera(String::new(), &self.orbit_camera));
+        if let Err(error) = camera_bookmarks.save(std::path::Path::new(CAMERA_BOOKMARKS_FILE)) {
+            eprintln!("Could not save camera bookmarks: {:?}", error);
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        // The profiler measures continuously rendered frames
+        self.orbit_camera.is_animating() || self.camera_path_player.is_some() || self.profiler_mode != crate::profiler::ProfilerMode::Disabled
+    }
+
+    pub fn fly_to_field(&mut self, triangle_coordinate: crate::planet::TriangleCoordinate, on_completion: Option<crate::orbit_camera::AnimationCallback>) {
+        let surface_matrix = self.planet.surface_matrix(&triangle_coordinate);
+        let view_quaternion = crate::orbit_camera::OrbitCamera::view_quaternion_from_direction(surface_matrix.y_axis().truncate());
+        let angle = self.orbit_camera.get_view_quaternion().dot(view_quaternion).abs().min(1.0).acos()*2.0;
+        let view_zoom = (self.planet.surface_radius()*1.5).ln();
+        self.orbit_camera.animate_to(view_quaternion, view_zoom, 0.5+angle*0.4, on_completion);
+    }
+
+    /// Adds the field to the field list or removes it if it is listed already
+    pub fn toggle_field_list_entry(&mut self, triangle_coordinate: crate::planet::TriangleCoordinate) {
+        if let Some(index) = self.field_list.iter().position(|entry| entry.triangle_coordinate == triangle_coordinate) {
+            self.field_list.remove(index);
+        } else {
+            self.field_list.push(FieldListEntry {
+                triangle_coordinate,
+                button: button::State::new(),
+            });
+        }
+    }
+
     fn update_camera(&mut self) {
-        self.camera.set_world_matrix(glam::Mat4::from_quat(self.view_quaternion).inverse()*glam::Mat4::from_translation(glam::Vec3::new(0.0, 0.0, self.view_zoom.exp())));
+        self.camera.set_world_matrix(self.orbit_camera.get_world_matrix());
     }
 
     fn update_camera_projection(&mut self) {
-        self.view_zoom = self.view_zoom.max(3.0).min(10.0);
-        self.camera.set_perspective(self.view_zoom.exp()*0.25, 100000.0, std::f32::consts::PI*0.3, self.viewport_size.width as f32/self.viewport_size.height as f32);
+        self.camera.set_perspective(self.orbit_camera.view_zoom.exp()*0.25, std::f32::INFINITY, std::f32::consts::PI*0.3, self.viewport_size.width as f32/self.viewport_size.height as f32);
         self.update_camera();
     }
 }
@@ -80,39 +174,63 @@ impl Program for Controls {
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
-            Message::KeyboardInput(input) if input.state == ElementState::Pressed => {
+            Message::KeyboardInput(input) => {
+                let pressed = input.state == ElementState::Pressed;
+                let input_value = if pressed { 1.0 } else { 0.0 };
                 match input.virtual_keycode {
-                    Some(VirtualKeyCode::Escape) => {
+                    Some(VirtualKeyCode::Escape) if pressed => {
                         // TODO
                     },
                     Some(VirtualKeyCode::A) => {
-                        self.view_quaternion = (self.view_quaternion*glam::Quat::from_rotation_y(0.1)).normalize();
-                        self.update_camera();
+                        *self.orbit_camera.rotation_input.x_mut() = input_value;
                     },
                     Some(VirtualKeyCode::D) => {
-                        self.view_quaternion = (self.view_quaternion*glam::Quat::from_rotation_y(-0.1)).normalize();
-                        self.update_camera();
+                        *self.orbit_camera.rotation_input.x_mut() = -input_value;
                     },
                     Some(VirtualKeyCode::W) => {
-                        self.view_quaternion = (glam::Quat::from_rotation_x(0.1)*self.view_quaternion).normalize();
-                        self.update_camera();
+                        *self.orbit_camera.rotation_input.y_mut() = input_value;
                     },
                     Some(VirtualKeyCode::S) => {
-                        self.view_quaternion = (glam::Quat::from_rotation_x(-0.1)*self.view_quaternion).normalize();
-                        self.update_camera();
+                        *self.orbit_camera.rotation_input.y_mut() = -input_value;
                     },
-                    Some(VirtualKeyCode::X) => {
-                        self.view_zoom += 0.05;
-                        self.update_camera_projection();
+                    Some(VirtualKeyCode::X) if pressed => {
+                        self.orbit_camera.zoom(0.05);
+                    },
+                    Some(VirtualKeyCode::Y) if pressed => {
+                        self.orbit_camera.zoom(-0.05);
+                    },
+                    Some(VirtualKeyCode::K) if pressed => {
+                        let time = if self.camera_path.keyframes.is_empty() { 0.0 } else { self.camera_path.get_duration()+CAMERA_PATH_KEYFRAME_INTERVAL };
+                        self.camera_path.record(time, &self.camera);
+                        if let Err(error) = self.camera_path.save(std::path::Path::new(CAMERA_PATH_FILE)) {
+                            eprintln!("Could not save camera path: {:?}", error);
+                        }
+                    },
+                    Some(key_code) if pressed && CAMERA_BOOKMARK_KEYS.contains(&key_code) => {
+                        let name = (CAMERA_BOOKMARK_KEYS.iter().position(|other| *other == key_code).unwrap()+1).to_string();
+                        if self.modifiers.ctrl() {
+                            self.camera_bookmarks.insert(crate::camera_bookmark::CameraBookmark::from_orbit_camera(name, &self.orbit_camera));
+                            self.save_camera_bookmarks();
+                        } else if let Some(bookmark) = self.camera_bookmarks.get(&name) {
+                            bookmark.animate_orbit_camera(&mut self.orbit_camera, 1.0);
+                        }
+                    },
+                    Some(VirtualKeyCode::F12) if pressed => {
+                        self.screenshot_request = Some(crate::screenshot::ScreenshotRequest {
+                            include_gbuffer: self.modifiers.shift(),
+                        });
                     },
-                    Some(VirtualKeyCode::Y) => {
-                        self.view_zoom -= 0.05;
+                    Some(VirtualKeyCode::P) if pressed => {
+                        self.camera_path_player = if self.camera_path_player.is_none() && !self.camera_path.keyframes.is_empty() {
+                            Some(crate::camera_path::CameraPathPlayer::new(self.camera_path.clone(), false))
+                        } else {
+                            None
+                        };
                         self.update_camera_projection();
                     },
                     _ => {}
                 }
             },
-            Message::KeyboardInput(_input) => {},
             Message::ModifiersChanged(modifiers) => {
                 self.modifiers = modifiers;
             },
@@ -121,25 +239,36 @@ impl Program for Controls {
                 self.normalized_pointer_position[0] = (position.x as f32/self.viewport_size.width as f32)*2.0-1.0;
                 self.normalized_pointer_position[1] = 1.0-(position.y as f32/self.viewport_size.height as f32)*2.0;
                 if self.pointer_state == ElementState::Pressed {
-                    let diff = self.normalized_pointer_position-self.start_normalized_pointer_position;
-                    self.view_quaternion = (glam::Quat::from_rotation_y(diff[0])*glam::Quat::from_rotation_x(-diff[1])*self.start_view_quaternion).normalize();
-                    self.update_camera();
+                    let diff = self.normalized_pointer_position-self.previous_normalized_pointer_position;
+                    self.orbit_camera.drag(glam::Vec2::new(diff[0], -diff[1]));
+                    self.previous_normalized_pointer_position = self.normalized_pointer_position;
                 }
             },
             Message::CursorEntered => {},
             Message::CursorLeft => {},
             Message::MouseWheel(delta, _phase) => {
-                self.view_zoom += match delta {
+                self.orbit_camera.zoom(match delta {
                     MouseScrollDelta::LineDelta(_x, y) => y as f32*0.05,
                     MouseScrollDelta::PixelDelta(delta) => delta.y as f32*0.005,
-                };
-                self.update_camera_projection();
+                });
             },
             Message::MouseInput(button, state) => {
+                if button == MouseButton::Left && state == ElementState::Pressed {
+                    if let Some(triangle_coordinate) = self.hovered_field {
+                        return self.update(Message::FlyToField(triangle_coordinate));
+                    }
+                }
+                if button == MouseButton::Right && state == ElementState::Pressed {
+                    if let Some(triangle_coordinate) = self.hovered_field {
+                        self.toggle_field_list_entry(triangle_coordinate);
+                    }
+                }
                 if button == MouseButton::Middle {
                     if self.pointer_state == ElementState::Released && state == ElementState::Pressed {
-                        self.start_normalized_pointer_position = self.normalized_pointer_position;
-                        self.start_view_quaternion = self.view_quaternion;
+                        self.previous_normalized_pointer_position = self.normalized_pointer_position;
+                        self.orbit_camera.begin_drag();
+                    } else if self.pointer_state == ElementState::Pressed && state == ElementState::Released {
+                        self.orbit_camera.end_drag();
                     }
                     self.pointer_state = state;
                 }
@@ -148,6 +277,50 @@ impl Program for Controls {
                 self.viewport_size = viewport_size;
                 self.update_camera_projection();
             },
+            Message::Tick(delta_time) => {
+                if let Some(camera_path_player) = &mut self.camera_path_player {
+                    if let Some(keyframe) = camera_path_player.advance(delta_time) {
+                        keyframe.apply_to_camera(&mut self.camera, self.viewport_size.width as f32/self.viewport_size.height as f32);
+                    }
+                    if camera_path_player.is_finished() {
+                        self.camera_path_player = None;
+                        self.update_camera_projection();
+                    }
+                    return Command::none();
+                }
+                let on_completion = self.orbit_camera.update(delta_time);
+                self.update_camera_projection();
+                if let Some(on_completion) = on_completion {
+                    on_completion(self);
+                }
+            },
+            Message::FlyToField(triangle_coordinate) => {
+                self.focused_field = None;
+                self.fly_to_field(triangle_coordinate, Some(Box::new(move |controls: &mut Controls| {
+                    controls.focused_field = Some(triangle_coordinate);
+                })));
+            },
+            Message::ScreenshotSaved => {
+                self.screenshot_request = None;
+            },
+            Message::CycleDebugView => {
+                self.render_options.debug_view = self.render_options.debug_view.next();
+            },
+            Message::CycleProfilerMode => {
+                self.profiler_mode = self.profiler_mode.next();
+                if self.profiler_mode == crate::profiler::ProfilerMode::Disabled {
+                    self.profiler_frame = None;
+                }
+            },
+            Message::ExportTrace => {
+                self.trace_export_request = true;
+            },
+            Message::TraceExported => {
+                self.trace_export_request = false;
+            },
+            Message::ProfilerFrame(profiler_frame) => {
+                self.profiler_frame = Some(profiler_frame);
+            },
             /*Message::SlidersChanged(vector) => {
                 self.vector = vector;
             }*/
@@ -181,6 +354,7 @@ impl Program for Controls {
             );*/
 
         self.selection_matrix = glam::Mat4::from_scale(glam::Vec3::splat(0.0));
+        self.hovered_field = None;
         let view_ray = self.camera.get_view_ray(self.normalized_pointer_position[0], self.normalized_pointer_position[1]);
         let triangle_coordinate = crate::camera::ray_sphere_intersection(&view_ray, self.planet.surface_radius()).and_then(|(_distance, first_hit, _second_hit)| {
             let direction = first_hit.normalize(); // (first_hit-center).normalize();
@@ -188,16 +362,38 @@ impl Program for Controls {
             // let prev = (triangle_coordinate.cube_coord, triangle_coordinate.triangle_latitude, triangle_coordinate.triangle_longitude);
             // triangle_coordinate.navigate(self.direction);
             self.selection_matrix = self.planet.surface_matrix(&triangle_coordinate);
+            self.hovered_field = Some(triangle_coordinate);
             // let parallelogram_coord = crate::planet::ParallelogramCoordinate::from_triangle_coordinate(triangle_coordinate);
             // let triangle_coordinate = crate::planet::TriangleCoordinate::from_parallelogram_coordinate(parallelogram_coord);
             let next = (triangle_coordinate.cube_coord, triangle_coordinate.triangle_latitude, triangle_coordinate.triangle_longitude);
             Some(next)
         });
 
+        let field_list = self.field_list.iter_mut().fold(Column::new().spacing(5), |column, entry| {
+            let triangle_coordinate = entry.triangle_coordinate;
+            column.push(
+                Button::new(&mut entry.button, Text::new(format!("{:?}", (triangle_coordinate.cube_coord, triangle_coordinate.triangle_latitude, triangle_coordinate.triangle_longitude))).size(14))
+                    .on_press(Message::FlyToField(triangle_coordinate)),
+            )
+        });
+
+        let milliseconds = |time: &std::ops::Range<std::time::Duration>| (time.end-time.start).as_secs_f64()*1000.0;
+        let profiler_overlay = self.profiler_frame.iter().fold(Column::new().padding(10).spacing(2), |column, profiler_frame| {
+            let column = column.push(
+                Text::new(format!("Frame {:.2} ms", milliseconds(&profiler_frame.time))).size(14).color(Color::WHITE),
+            );
+            profiler_frame.scopes.iter().fold(column, |column, scope| {
+                column.push(
+                    Text::new(format!("{} recording {:.2} ms", scope.name, milliseconds(&scope.cpu))).size(14).color(Color::WHITE),
+                )
+            })
+        });
+
         Row::new()
             .width(Length::Fill)
             .height(Length::Fill)
             .align_items(Align::End)
+            .push(profiler_overlay)
             .push(
                 Column::new()
                     .width(Length::Fill)
@@ -212,6 +408,28 @@ impl Program for Controls {
                             // .push(sliders)
                             .push(
                                 Text::new(format!("{:?}", triangle_coordinate)).size(14).color(Color::WHITE),
+                            )
+                            .push(
+                                Text::new(format!("Bookmarks {}", self.camera_bookmarks.bookmarks.iter().map(|bookmark| bookmark.name.as_str()).collect::<Vec<&str>>().join(" "))).size(14).color(Color::WHITE),
+                            )
+                            .push(
+                                Text::new(format!("Focused {:?}", self.focused_field.map(|focused_field| (focused_field.cube_coord, focused_field.triangle_latitude, focused_field.triangle_longitude)))).size(14).color(Color::WHITE),
+                            )
+                            .push(
+                                Text::new("Field List").color(Color::WHITE),
+                            )
+                            .push(field_list)
+                            .push(
+                                Button::new(&mut self.debug_view_button, Text::new(format!("Debug View {:?}", self.render_options.debug_view)).size(14))
+                                    .on_press(Message::CycleDebugView),
+                            )
+                            .push(
+                                Button::new(&mut self.profiler_mode_button, Text::new(format!("Profiler {:?}", self.profiler_mode)).size(14))
+                                    .on_press(Message::CycleProfilerMode),
+                            )
+                            .push(
+                                Button::new(&mut self.trace_export_button, Text::new("Export Trace").size(14))
+                                    .on_press(Message::ExportTrace),
                             ),
                     ),
             )
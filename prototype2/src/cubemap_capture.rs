@@ -0,0 +1,124 @@
+#![allow(dead_code)]
+
+use iced_wgpu::wgpu;
+
+/// Forward and up direction of each face in the order +X, -X, +Y, -Y, +Z, -Z.
+/// A right handed camera can not produce the left handed cubemap faces without mirroring the triangle winding,
+/// so the captured cubemap is mirrored along z instead and has to be sampled with vec3(d.x, d.y, -d.z).
+fn face_orientations() -> [(glam::Vec3, glam::Vec3); 6] {
+    [
+        (glam::Vec3::new(1.0, 0.0, 0.0), glam::Vec3::new(0.0, 1.0, 0.0)),
+        (glam::Vec3::new(-1.0, 0.0, 0.0), glam::Vec3::new(0.0, 1.0, 0.0)),
+        (glam::Vec3::new(0.0, 1.0, 0.0), glam::Vec3::new(0.0, 0.0, 1.0)),
+        (glam::Vec3::new(0.0, -1.0, 0.0), glam::Vec3::new(0.0, 0.0, -1.0)),
+        (glam::Vec3::new(0.0, 0.0, -1.0), glam::Vec3::new(0.0, 1.0, 0.0)),
+        (glam::Vec3::new(0.0, 0.0, 1.0), glam::Vec3::new(0.0, 1.0, 0.0)),
+    ]
+}
+
+pub fn cubemap_face_cameras(position: glam::Vec3, near: f32) -> [crate::camera::Camera; 6] {
+    let mut cameras: [crate::camera::Camera; 6] = Default::default();
+    for (camera, (forward, up)) in cameras.iter_mut().zip(face_orientations().iter()) {
+        camera.set_perspective(near, std::f32::INFINITY, std::f32::consts::PI*0.5, 1.0);
+        camera.set_world_matrix(glam::Mat4::from_cols(
+            forward.cross(*up).extend(0.0),
+            up.extend(0.0),
+            (-*forward).extend(0.0),
+            position.extend(1.0),
+        ));
+    }
+    cameras
+}
+
+/// Renders the scene from a point into a HDR cubemap, e.g. for reflections and image based lighting
+pub struct CubemapCapture {
+    pub renderer: crate::renderer::Renderer,
+    pub texture: crate::assets::Texture,
+}
+
+impl CubemapCapture {
+    pub fn new(device: &wgpu::Device, size: u32, render_options: &crate::renderer::RenderOptions, asset_pack: &crate::assets::AssetPack) -> Self {
+        let swap_chain_descriptor = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba16Float,
+            width: size,
+            height: size,
+            present_mode: wgpu::PresentMode::Mailbox,
+        };
+        let renderer = crate::renderer::Renderer::new(device, &swap_chain_descriptor, render_options, asset_pack);
+        let mut texture = crate::assets::Texture::new(device, wgpu::Extent3d { width: size, height: size, depth: 6 }, true, false, wgpu::TextureDimension::D2, wgpu::TextureFormat::Rgba16Float);
+        texture.view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+        Self {
+            renderer,
+            texture,
+        }
+    }
+
+    /// render_scene records the surface and volumetric passes, like the main frame does.
+    /// render_shadow_casters records the draw calls of one shadow pass (e.g. Mesh::render_shadow and Renderer::render_sphere_shadows),
+    /// the shadow cascades are fit to each face.
+    /// Each face is submitted separately, because the camera uniforms are written through the queue.
+    pub fn capture<F, G>(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, mipmap_generator: &crate::assets::MipmapGenerator, world_matrices: &[glam::Mat4], lights: &crate::light::Lights, position: glam::Vec3, near: f32, mut render_scene: F, mut render_shadow_casters: G)
+        where F: FnMut(&crate::renderer::Renderer, &mut wgpu::CommandEncoder), G: for<'a> FnMut(&'a crate::renderer::Renderer, &mut wgpu::RenderPass<'a>) {
+        self.renderer.reserve_instances(device, world_matrices.len());
+        for (face_index, camera) in cubemap_face_cameras(position, near).iter().enumerate() {
+            let mut encoder = device.create_command_encoder(
+                &wgpu::CommandEncoderDescriptor { label: None },
+            );
+            if face_index == 0 {
+                self.renderer.update_instances(&mut encoder, queue, world_matrices);
+            }
+            self.renderer.update_lights(device, queue, lights, camera);
+            self.renderer.update_shadow_maps(&mut encoder, world_matrices.len());
+            for shadow_map_index in 0..self.renderer.get_shadow_map_count() {
+                let mut shadow_pass = self.renderer.render_shadow_pass(&mut encoder, shadow_map_index);
+                render_shadow_casters(&self.renderer, &mut shadow_pass);
+            }
+            self.renderer.update_camera(&mut encoder, queue, world_matrices.len(), camera);
+            render_scene(&self.renderer, &mut encoder);
+            self.renderer.render_light_pass(&mut encoder);
+            self.renderer.copy_color_to_texture(&mut encoder, &self.texture.texture, face_index as u32);
+            queue.submit(Some(encoder.finish()));
+        }
+        let mut encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor { label: None },
+        );
+        for face_index in 0..6 {
+            mipmap_generator.generate(device, &mut encoder, &self.texture, Some(face_index));
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    #[test]
+    fn test_face_cameras_match_face_orientations() {
+        let position = glam::Vec3::new(1.0, -2.0, 3.0);
+        // Sampling direction of each layer in a cubemap, the captured faces are mirrored along z
+        let layer_directions = [
+            glam::Vec3::new(1.0, 0.0, 0.0),
+            glam::Vec3::new(-1.0, 0.0, 0.0),
+            glam::Vec3::new(0.0, 1.0, 0.0),
+            glam::Vec3::new(0.0, -1.0, 0.0),
+            glam::Vec3::new(0.0, 0.0, 1.0),
+            glam::Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for ((camera, (forward, up)), layer_direction) in cubemap_face_cameras(position, 0.1).iter().zip(face_orientations().iter()).zip(layer_directions.iter()) {
+            let world_matrix = camera.get_world_matrix();
+            let camera_forward = -glam::Vec3::from(world_matrix.z_axis().truncate());
+            let camera_up = glam::Vec3::from(world_matrix.y_axis().truncate());
+            assert!((camera_forward-*forward).length() < EPSILON, "forward {:?} != {:?}", camera_forward, forward);
+            assert!((camera_up-*up).length() < EPSILON, "up {:?} != {:?}", camera_up, up);
+            assert!((glam::Vec3::from(world_matrix.w_axis().truncate())-position).length() < EPSILON);
+            assert!((*forward*glam::Vec3::new(1.0, 1.0, -1.0)-*layer_direction).length() < EPSILON, "{:?} is not the mirrored {:?}", forward, layer_direction);
+        }
+    }
+}
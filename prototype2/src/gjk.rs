@@ -0,0 +1,538 @@
+#![allow(dead_code)]
+
+use crate::bounding_volume::{BoundingBox, BoundingPyramid, BoundingSphere, BoundingCone, BoundingVolume, radius_at_near};
+
+const MAX_GJK_ITERATIONS: usize = 64;
+const MAX_EPA_ITERATIONS: usize = 64;
+const EPA_TOLERANCE: f32 = 0.0001;
+
+/// Convex shapes which can be queried for their furthest point in a direction (in local space)
+pub trait SupportMapping {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A;
+}
+
+/// The points must not be empty
+fn support_of_points(points: &[glam::Vec3A], direction: glam::Vec3A) -> glam::Vec3A {
+    let mut best_point = points[0];
+    let mut best_distance = best_point.dot(direction);
+    for point in &points[1..] {
+        let distance = point.dot(direction);
+        if distance > best_distance {
+            best_distance = distance;
+            best_point = *point;
+        }
+    }
+    best_point
+}
+
+fn support_of_disc(z: f32, radius: f32, direction: glam::Vec3A) -> glam::Vec3A {
+    let planar = glam::Vec3A::new(direction[0], direction[1], 0.0);
+    let planar_length = planar.length();
+    let center = glam::Vec3A::new(0.0, 0.0, z);
+    if planar_length > 0.0 {
+        center+planar*(radius/planar_length)
+    } else {
+        center
+    }
+}
+
+impl SupportMapping for BoundingBox {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        glam::Vec3A::new(
+            if direction[0] < 0.0 { self.min[0] } else { self.max[0] },
+            if direction[1] < 0.0 { self.min[1] } else { self.max[1] },
+            if direction[2] < 0.0 { self.min[2] } else { self.max[2] },
+        )
+    }
+}
+
+impl SupportMapping for BoundingPyramid {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        let width_at_near = radius_at_near(self.near, self.far, self.width_at_far);
+        let height_at_near = radius_at_near(self.near, self.far, self.height_at_far);
+        support_of_points(&[
+            glam::Vec3A::new(-width_at_near, -height_at_near, self.near),
+            glam::Vec3A::new(width_at_near, -height_at_near, self.near),
+            glam::Vec3A::new(-width_at_near, height_at_near, self.near),
+            glam::Vec3A::new(width_at_near, height_at_near, self.near),
+            glam::Vec3A::new(-self.width_at_far, -self.height_at_far, self.far),
+            glam::Vec3A::new(self.width_at_far, -self.height_at_far, self.far),
+            glam::Vec3A::new(-self.width_at_far, self.height_at_far, self.far),
+            glam::Vec3A::new(self.width_at_far, self.height_at_far, self.far),
+        ], direction)
+    }
+}
+
+impl SupportMapping for BoundingSphere {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        let length = direction.length();
+        if length > 0.0 {
+            direction*(self.radius/length)
+        } else {
+            glam::Vec3A::splat(0.0)
+        }
+    }
+}
+
+impl SupportMapping for BoundingCone {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        let near_point = support_of_disc(self.near, radius_at_near(self.near, self.far, self.radius_at_far), direction);
+        let far_point = support_of_disc(self.far, self.radius_at_far, direction);
+        if near_point.dot(direction) > far_point.dot(direction) { near_point } else { far_point }
+    }
+}
+
+impl SupportMapping for BoundingVolume {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        match self {
+            BoundingVolume::Box(bb) => bb.support(direction),
+            BoundingVolume::Pyramid(pyramid) => pyramid.support(direction),
+            BoundingVolume::Sphere(sphere) => sphere.support(direction),
+            BoundingVolume::Cone(cone) => cone.support(direction),
+        }
+    }
+}
+
+/// Convex hull of an arbitrary point cloud, e.g. the vertices of a collision mesh
+#[derive(Debug, Clone)]
+pub struct ConvexHull {
+    points: Vec<glam::Vec3A>,
+}
+
+impl ConvexHull {
+    /// Returns None for an empty point cloud, as it has no support point
+    pub fn new(points: Vec<glam::Vec3A>) -> Option<Self> {
+        if points.is_empty() {
+            None
+        } else {
+            Some(Self { points })
+        }
+    }
+
+    pub fn points(&self) -> &[glam::Vec3A] {
+        &self.points
+    }
+}
+
+impl SupportMapping for ConvexHull {
+    fn support(&self, direction: glam::Vec3A) -> glam::Vec3A {
+        support_of_points(&self.points, direction)
+    }
+}
+
+fn world_support<S: SupportMapping>(shape: &S, world_matrix: &glam::Mat4, direction: glam::Vec3A) -> glam::Vec3A {
+    let local_direction = glam::Vec3A::new(
+        world_matrix.x_axis().truncate().dot(direction),
+        world_matrix.y_axis().truncate().dot(direction),
+        world_matrix.z_axis().truncate().dot(direction),
+    );
+    world_matrix.mul_vec4(shape.support(local_direction).extend(1.0)).truncate()
+}
+
+fn minkowski_support<A: SupportMapping, B: SupportMapping>(a: &A, b: &B, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4, direction: glam::Vec3A) -> glam::Vec3A {
+    world_support(a, world_matrix_a, direction)-world_support(b, world_matrix_b, -direction)
+}
+
+fn triple_cross(a: glam::Vec3A, b: glam::Vec3A, c: glam::Vec3A) -> glam::Vec3A {
+    a.cross(b).cross(c)
+}
+
+fn any_orthogonal(v: glam::Vec3A) -> glam::Vec3A {
+    let axis = if v[0].abs() < 0.5 { glam::Vec3A::new(1.0, 0.0, 0.0) } else { glam::Vec3A::new(0.0, 1.0, 0.0) };
+    v.cross(axis)
+}
+
+/// Points are ordered from oldest to newest
+struct Simplex {
+    points: [glam::Vec3A; 4],
+    count: usize,
+}
+
+impl Simplex {
+    fn line(&mut self, direction: &mut glam::Vec3A) -> bool {
+        let [b, a, _, _] = self.points;
+        let ab = b-a;
+        let ao = -a;
+        if ab.dot(ao) > 0.0 {
+            *direction = triple_cross(ab, ao, ab);
+            if direction.length_squared() == 0.0 {
+                *direction = any_orthogonal(ab);
+            }
+        } else {
+            self.points[0] = a;
+            self.count = 1;
+            *direction = ao;
+        }
+        false
+    }
+
+    fn triangle(&mut self, direction: &mut glam::Vec3A) -> bool {
+        let [c, b, a, _] = self.points;
+        let ab = b-a;
+        let ac = c-a;
+        let ao = -a;
+        let abc = ab.cross(ac);
+        if abc.cross(ac).dot(ao) > 0.0 {
+            if ac.dot(ao) > 0.0 {
+                self.points[0] = c;
+                self.points[1] = a;
+                self.count = 2;
+                *direction = triple_cross(ac, ao, ac);
+                return false;
+            }
+            self.points[0] = b;
+            self.points[1] = a;
+            self.count = 2;
+            return self.line(direction);
+        }
+        if ab.cross(abc).dot(ao) > 0.0 {
+            self.points[0] = b;
+            self.points[1] = a;
+            self.count = 2;
+            return self.line(direction);
+        }
+        if abc.dot(ao) >= 0.0 {
+            *direction = abc;
+        } else {
+            self.points[0] = b;
+            self.points[1] = c;
+            *direction = -abc;
+        }
+        false
+    }
+
+    fn tetrahedron(&mut self, direction: &mut glam::Vec3A) -> bool {
+        let [d, c, b, a] = self.points;
+        let ab = b-a;
+        let ac = c-a;
+        let ad = d-a;
+        let ao = -a;
+        let abc = ab.cross(ac);
+        let acd = ac.cross(ad);
+        let adb = ad.cross(ab);
+        let (face, is_outside) = if abc.dot(ao) > 0.0 {
+            ([c, b, a], true)
+        } else if acd.dot(ao) > 0.0 {
+            ([d, c, a], true)
+        } else if adb.dot(ao) > 0.0 {
+            ([b, d, a], true)
+        } else {
+            ([c, b, a], false)
+        };
+        if !is_outside {
+            return true;
+        }
+        self.points[0] = face[0];
+        self.points[1] = face[1];
+        self.points[2] = face[2];
+        self.count = 3;
+        self.triangle(direction)
+    }
+
+    fn contains_origin(&mut self, direction: &mut glam::Vec3A) -> bool {
+        match self.count {
+            2 => self.line(direction),
+            3 => self.triangle(direction),
+            4 => self.tetrahedron(direction),
+            _ => unreachable!()
+        }
+    }
+}
+
+/// Returns a tetrahedron of the Minkowski difference which encloses the origin if the shapes intersect
+pub fn gjk<A: SupportMapping, B: SupportMapping>(a: &A, b: &B, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> Option<[glam::Vec3A; 4]> {
+    let mut direction = world_matrix_a.w_axis().truncate()-world_matrix_b.w_axis().truncate();
+    if direction.length_squared() == 0.0 {
+        direction = glam::Vec3A::new(1.0, 0.0, 0.0);
+    }
+    let mut simplex = Simplex {
+        points: [minkowski_support(a, b, world_matrix_a, world_matrix_b, direction), glam::Vec3A::splat(0.0), glam::Vec3A::splat(0.0), glam::Vec3A::splat(0.0)],
+        count: 1,
+    };
+    direction = -simplex.points[0];
+    for _ in 0..MAX_GJK_ITERATIONS {
+        if direction.length_squared() == 0.0 {
+            direction = any_orthogonal(simplex.points[0]);
+        }
+        let point = minkowski_support(a, b, world_matrix_a, world_matrix_b, direction);
+        if point.dot(direction) < 0.0 {
+            return None;
+        }
+        simplex.points[simplex.count] = point;
+        simplex.count += 1;
+        if simplex.contains_origin(&mut direction) {
+            return Some(simplex.points);
+        }
+    }
+    None
+}
+
+pub fn gjk_intersection<A: SupportMapping, B: SupportMapping>(a: &A, b: &B, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
+    gjk(a, b, world_matrix_a, world_matrix_b).is_some()
+}
+
+fn epa_face(vertices: &[glam::Vec3A], indices: [usize; 3]) -> ([usize; 3], glam::Vec3A, f32) {
+    let a = vertices[indices[0]];
+    let normal = (vertices[indices[1]]-a).cross(vertices[indices[2]]-a);
+    let length = normal.length();
+    if length == 0.0 {
+        return (indices, normal, std::f32::INFINITY);
+    }
+    let normal = normal/length;
+    (indices, normal, normal.dot(a))
+}
+
+fn add_horizon_edge(edges: &mut Vec<(usize, usize)>, a: usize, b: usize) {
+    if let Some(index) = edges.iter().position(|edge| *edge == (b, a)) {
+        edges.swap_remove(index);
+    } else {
+        edges.push((a, b));
+    }
+}
+
+/// Returns the penetration depth and the normal pointing from a towards b.
+/// Translating b by depth*normal separates the shapes.
+pub fn epa_penetration<A: SupportMapping, B: SupportMapping>(a: &A, b: &B, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> Option<(f32, glam::Vec3A)> {
+    let tetrahedron = gjk(a, b, world_matrix_a, world_matrix_b)?;
+    let mut vertices: Vec<glam::Vec3A> = tetrahedron.to_vec();
+    let centroid = (vertices[0]+vertices[1]+vertices[2]+vertices[3])*0.25;
+    let mut faces: Vec<([usize; 3], glam::Vec3A, f32)> = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]].iter().map(|indices| {
+        let face = epa_face(&vertices, *indices);
+        if face.1.dot(vertices[indices[0]]-centroid) < 0.0 {
+            epa_face(&vertices, [indices[0], indices[2], indices[1]])
+        } else {
+            face
+        }
+    }).collect();
+    let mut closest = (0.0, glam::Vec3A::new(1.0, 0.0, 0.0));
+    for _ in 0..MAX_EPA_ITERATIONS {
+        let (closest_index, _) = faces.iter().enumerate().fold((0, std::f32::INFINITY), |best, (index, face)| {
+            if face.2 < best.1 { (index, face.2) } else { best }
+        });
+        let (_, normal, distance) = faces[closest_index];
+        if distance == std::f32::INFINITY {
+            break;
+        }
+        closest = (distance, normal);
+        let point = minkowski_support(a, b, world_matrix_a, world_matrix_b, normal);
+        if point.dot(normal)-distance < EPA_TOLERANCE {
+            break;
+        }
+        let point_index = vertices.len();
+        vertices.push(point);
+        let mut horizon_edges: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|(indices, normal, distance)| {
+            if normal.dot(point)-distance > 0.0 {
+                add_horizon_edge(&mut horizon_edges, indices[0], indices[1]);
+                add_horizon_edge(&mut horizon_edges, indices[1], indices[2]);
+                add_horizon_edge(&mut horizon_edges, indices[2], indices[0]);
+                false
+            } else {
+                true
+            }
+        });
+        for (edge_a, edge_b) in horizon_edges {
+            faces.push(epa_face(&vertices, [edge_a, edge_b, point_index]));
+        }
+    }
+    Some(closest)
+}
+
+pub fn bounding_volume_gjk_intersection(a: &BoundingVolume, b: &BoundingVolume, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> bool {
+    gjk_intersection(a, b, world_matrix_a, world_matrix_b)
+}
+
+pub fn bounding_volume_penetration(a: &BoundingVolume, b: &BoundingVolume, world_matrix_a: &glam::Mat4, world_matrix_b: &glam::Mat4) -> Option<(f32, glam::Vec3A)> {
+    epa_penetration(a, b, world_matrix_a, world_matrix_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounding_volume::{box_box_intersection, box_cone_intersection, box_pyramid_intersection, box_sphere_intersection, cone_cone_intersection, pyramid_cone_intersection, pyramid_sphere_intersection, sphere_cone_intersection, sphere_sphere_intersection};
+
+    fn world_matrix(translation: glam::Vec3, yaw: f32, pitch: f32, roll: f32) -> glam::Mat4 {
+        glam::Mat4::from_rotation_translation(glam::Quat::from_rotation_ypr(yaw, pitch, roll), translation)
+    }
+
+    fn unit_box() -> BoundingBox {
+        BoundingBox {
+            min: glam::Vec3A::splat(-1.0),
+            max: glam::Vec3A::splat(1.0),
+        }
+    }
+
+    fn unit_pyramid() -> BoundingPyramid {
+        BoundingPyramid {
+            near: 0.25,
+            far: 1.5,
+            width_at_far: 1.0,
+            height_at_far: 0.75,
+        }
+    }
+
+    fn unit_cone() -> BoundingCone {
+        BoundingCone {
+            near: 0.25,
+            far: 1.5,
+            radius_at_far: 1.0,
+        }
+    }
+
+    /// Pairs of world matrices, the first ones are clearly intersecting and the last ones clearly separated
+    fn placements() -> Vec<(glam::Mat4, glam::Mat4)> {
+        vec![
+            (glam::Mat4::identity(), world_matrix(glam::Vec3::new(1.5, 0.0, 0.0), 0.0, 0.0, 0.0)),
+            (world_matrix(glam::Vec3::new(3.0, -2.0, 1.0), 0.4, 0.0, 0.0), world_matrix(glam::Vec3::new(3.5, -1.5, 1.2), 0.0, 0.3, 0.2)),
+            (world_matrix(glam::Vec3::new(0.0, 0.0, -4.0), 1.0, 0.5, -0.3), world_matrix(glam::Vec3::new(0.2, 0.1, -4.3), -0.2, 0.0, 0.9)),
+            (glam::Mat4::identity(), world_matrix(glam::Vec3::new(4.0, 0.0, 0.0), 0.0, 0.0, 0.0)),
+            (world_matrix(glam::Vec3::new(3.0, -2.0, 1.0), 0.4, 0.0, 0.0), world_matrix(glam::Vec3::new(3.0, 2.0, 1.0), 0.0, 0.3, 0.2)),
+            (world_matrix(glam::Vec3::new(0.0, 0.0, -4.0), 1.0, 0.5, -0.3), world_matrix(glam::Vec3::new(-3.0, 3.0, -6.0), -0.2, 0.0, 0.9)),
+        ]
+    }
+
+    #[test]
+    fn test_gjk_matches_sphere_sphere() {
+        let a = BoundingSphere { radius: 1.0 };
+        let b = BoundingSphere { radius: 0.8 };
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), sphere_sphere_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_gjk_matches_box_box() {
+        let a = unit_box();
+        let b = BoundingBox {
+            min: glam::Vec3A::new(-0.5, -1.0, -0.25),
+            max: glam::Vec3A::new(0.5, 1.0, 0.25),
+        };
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), box_box_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_gjk_matches_box_sphere() {
+        let a = unit_box();
+        let b = BoundingSphere { radius: 0.75 };
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), box_sphere_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_gjk_matches_box_pyramid() {
+        let a = unit_box();
+        let b = unit_pyramid();
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), box_pyramid_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_gjk_matches_pyramid_sphere() {
+        let a = unit_pyramid();
+        let b = BoundingSphere { radius: 0.75 };
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), pyramid_sphere_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_gjk_matches_sphere_cone() {
+        let a = BoundingSphere { radius: 0.75 };
+        let b = unit_cone();
+        for (world_matrix_a, world_matrix_b) in placements() {
+            assert_eq!(gjk_intersection(&a, &b, &world_matrix_a, &world_matrix_b), sphere_cone_intersection(&a, &b, &world_matrix_a, &world_matrix_b));
+        }
+    }
+
+    #[test]
+    fn test_pairs_routed_through_gjk() {
+        let expected = vec![true, true, true, false, false, false];
+        let results: Vec<bool> = placements().iter().map(|(world_matrix_a, world_matrix_b)| box_cone_intersection(&unit_box(), &unit_cone(), world_matrix_a, world_matrix_b)).collect();
+        assert_eq!(results, expected);
+        let results: Vec<bool> = placements().iter().map(|(world_matrix_a, world_matrix_b)| pyramid_cone_intersection(&unit_pyramid(), &unit_cone(), world_matrix_a, world_matrix_b)).collect();
+        assert_eq!(results, expected);
+        let results: Vec<bool> = placements().iter().map(|(world_matrix_a, world_matrix_b)| cone_cone_intersection(&unit_cone(), &unit_cone(), world_matrix_a, world_matrix_b)).collect();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_box_cone_along_side() {
+        // The cone has a radius of 0.933 at the top of the box (z = 1.4)
+        let cone = unit_cone();
+        let touching = world_matrix(glam::Vec3::new(1.9, 0.0, 0.4), 0.0, 0.0, 0.0);
+        let separated = world_matrix(glam::Vec3::new(2.0, 0.0, 0.4), 0.0, 0.0, 0.0);
+        assert!(box_cone_intersection(&unit_box(), &cone, &touching, &glam::Mat4::identity()));
+        assert!(!box_cone_intersection(&unit_box(), &cone, &separated, &glam::Mat4::identity()));
+    }
+
+    #[test]
+    fn test_degenerate_far_plane() {
+        // With far == 0 the cone is a disc and the pyramid a rectangle
+        let disc = BoundingCone { near: 0.0, far: 0.0, radius_at_far: 1.0 };
+        let rectangle = BoundingPyramid { near: 0.0, far: 0.0, width_at_far: 1.0, height_at_far: 1.0 };
+        let sphere = BoundingSphere { radius: 0.6 };
+        let above = world_matrix(glam::Vec3::new(0.0, 0.0, 0.5), 0.0, 0.0, 0.0);
+        let further_above = world_matrix(glam::Vec3::new(0.0, 0.0, 0.7), 0.0, 0.0, 0.0);
+        assert!(gjk_intersection(&disc, &sphere, &glam::Mat4::identity(), &above));
+        assert!(!gjk_intersection(&disc, &sphere, &glam::Mat4::identity(), &further_above));
+        assert!(gjk_intersection(&rectangle, &sphere, &glam::Mat4::identity(), &above));
+        assert!(!gjk_intersection(&rectangle, &sphere, &glam::Mat4::identity(), &further_above));
+    }
+
+    #[test]
+    fn test_placements_cover_both_outcomes() {
+        let a = BoundingSphere { radius: 1.0 };
+        let results: Vec<bool> = placements().iter().map(|(world_matrix_a, world_matrix_b)| gjk_intersection(&a, &a, world_matrix_a, world_matrix_b)).collect();
+        assert_eq!(results, vec![true, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_epa_box_box_depth() {
+        let a = unit_box();
+        let world_matrix_b = world_matrix(glam::Vec3::new(1.5, 0.25, -0.5), 0.0, 0.0, 0.0);
+        let (depth, normal) = epa_penetration(&a, &a, &glam::Mat4::identity(), &world_matrix_b).unwrap();
+        assert!((depth-0.5).abs() < 0.001, "depth {}", depth);
+        assert!((normal-glam::Vec3A::new(1.0, 0.0, 0.0)).length() < 0.001, "normal {:?}", normal);
+    }
+
+    #[test]
+    fn test_epa_sphere_sphere_depth() {
+        let a = BoundingSphere { radius: 1.0 };
+        let b = BoundingSphere { radius: 0.5 };
+        let world_matrix_b = world_matrix(glam::Vec3::new(0.0, 1.2, 0.0), 0.0, 0.0, 0.0);
+        let (depth, normal) = epa_penetration(&a, &b, &glam::Mat4::identity(), &world_matrix_b).unwrap();
+        assert!((depth-0.3).abs() < 0.01, "depth {}", depth);
+        assert!((normal-glam::Vec3A::new(0.0, 1.0, 0.0)).length() < 0.05, "normal {:?}", normal);
+    }
+
+    #[test]
+    fn test_epa_convex_hull_depth() {
+        let hull = ConvexHull::new(vec![
+            glam::Vec3A::new(0.0, 0.0, 0.0),
+            glam::Vec3A::new(2.0, 0.0, 0.0),
+            glam::Vec3A::new(0.0, 2.0, 0.0),
+            glam::Vec3A::new(0.0, 0.0, 2.0),
+        ]).unwrap();
+        let sphere = BoundingSphere { radius: 0.5 };
+        let world_matrix_b = world_matrix(glam::Vec3::new(0.5, 0.5, -0.25), 0.0, 0.0, 0.0);
+        let (depth, normal) = epa_penetration(&hull, &sphere, &glam::Mat4::identity(), &world_matrix_b).unwrap();
+        assert!((depth-0.25).abs() < 0.01, "depth {}", depth);
+        assert!((normal-glam::Vec3A::new(0.0, 0.0, -1.0)).length() < 0.05, "normal {:?}", normal);
+    }
+
+    #[test]
+    fn test_epa_separated() {
+        let a = unit_box();
+        let world_matrix_b = world_matrix(glam::Vec3::new(2.5, 0.0, 0.0), 0.0, 0.0, 0.0);
+        assert!(epa_penetration(&a, &a, &glam::Mat4::identity(), &world_matrix_b).is_none());
+    }
+
+    #[test]
+    fn test_empty_convex_hull() {
+        assert!(ConvexHull::new(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use iced_wgpu::wgpu;
+use serde::{Serialize, Deserialize};
+
+/// Scene and output of a headless render, loaded from a JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadlessConfig {
+    pub width: u32,
+    pub height: u32,
+    pub output_path: std::path::PathBuf,
+    pub camera: crate::camera_path::CameraKeyframe,
+    pub planet_gp_index: usize,
+    pub star_position: glam::Vec3,
+    pub star_radius: f32,
+    /// Frames rendered before the last one is saved, so that auto exposure and temporal anti aliasing can settle
+    pub frame_count: usize,
+    /// Fixed time step between the frames, to keep the output reproducible
+    pub delta_time: f32,
+    /// Measures the CPU recording time of the passes with the profiler and writes them as a Chrome trace
+    pub trace_path: Option<std::path::PathBuf>,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            output_path: std::path::PathBuf::from("headless.png"),
+            camera: crate::camera_path::CameraKeyframe {
+                time: 0.0,
+                position: glam::Vec3::new(0.0, 0.0, 45.0),
+                rotation: glam::Quat::identity(),
+                fov_y_radians: std::f32::consts::PI*0.3,
+                near: 0.1,
+                far: None,
+            },
+            planet_gp_index: 5,
+            star_position: glam::Vec3::new(3000.0, 1500.0, -5000.0),
+            star_radius: 500.0,
+            frame_count: 16,
+            delta_time: 1.0/60.0,
+            trace_path: None,
+        }
+    }
+}
+
+impl HeadlessConfig {
+    pub fn load(path: &std::path::Path) -> Result<Self, crate::assets::AssetError> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+}
+
+/// Renders the configured scene without a window or swap chain and saves the last frame.
+/// CI renders headless.json on Mesa's software Vulkan driver lavapipe with headless_lavapipe.sh.
+pub fn run(config_path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+    let config = HeadlessConfig::load(config_path)?;
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let (device, queue) = crate::request_device(&instance, None)?;
+    let render_options = crate::renderer::RenderOptions::default();
+    // The renderer only uses the size of the swap chain descriptor
+    let swap_chain_descriptor = wgpu::SwapChainDescriptor {
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width: config.width,
+        height: config.height,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+    let size = wgpu::Extent3d { width: config.width, height: config.height, depth: 1 };
+    let output_texture = device.create_texture(&wgpu::TextureDescriptor {
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT|wgpu::TextureUsage::COPY_SRC,
+        label: None,
+    });
+    let output_view = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut planet = crate::planet::Planet::new(&device, config.planet_gp_index);
+    let mut scene = crate::scene::Scene::default();
+    let mut profiler = crate::profiler::Profiler::default();
+    if config.trace_path.is_some() {
+        profiler.mode = crate::profiler::ProfilerMode::Cpu;
+    }
+    profiler.begin_frame();
+    let (mut renderer, planetary_system) = {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let (asset_pack, _mipmap_generator, renderer) = crate::planetary_system::load_renderer(&device, &queue, &mut encoder, &swap_chain_descriptor, &render_options, &["assets/textures/"], &mut profiler)?;
+        let planetary_system = crate::planetary_system::PlanetarySystem::new(&device, &queue, &mut encoder, &renderer, &asset_pack, &mut scene, &mut planet, config.star_position, config.star_radius, &mut profiler);
+        queue.submit(Some(encoder.finish()));
+        profiler.end_frame();
+        (renderer, planetary_system)
+    };
+
+    let mut lights = crate::light::Lights::default();
+    lights.ambient_lights.add(&mut scene, crate::light::AmbientLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 0.1,
+    });
+    // Shines from the direction of the star through the entire atmosphere of the planet
+    let atmosphere_radius = planet.atmosphere_radius();
+    let light_origin = config.star_position.normalize()*atmosphere_radius;
+    lights.parallel_lights.add(&mut scene, crate::light::ParallelLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 1.0,
+        world_matrix: glam::Mat4::look_at_rh(light_origin, glam::Vec3::zero(), glam::Vec3::unit_y()).inverse()*glam::Mat4::from_scale(glam::Vec3::new(atmosphere_radius, atmosphere_radius, atmosphere_radius*2.0)),
+        casts_shadow: false,
+        shadow_cascade_count: 0,
+        shadow_distance: 0.0,
+    });
+    lights.update(&mut scene);
+
+    let mut camera = crate::camera::Camera::default();
+    config.camera.apply_to_camera(&mut camera, config.width as f32/config.height as f32);
+
+    for frame_index in 0..config.frame_count.max(1) {
+        profiler.begin_frame();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        planetary_system.render_scene(&device, &queue, &mut encoder, &mut renderer, &scene, &lights, &camera, &crate::planetary_system::AdditionalDrawCalls::default(), &mut profiler);
+        renderer.render_frame(&mut encoder, &output_view, if frame_index == 0 { 0.0 } else { config.delta_time }, &mut profiler);
+        queue.submit(Some(encoder.finish()));
+        profiler.end_frame();
+    }
+    if let Some(trace_path) = &config.trace_path {
+        profiler.write_chrome_trace(trace_path)?;
+    }
+
+    crate::screenshot::save_frame(&device, &queue, &output_texture, size, &config.output_path)
+}
@@ -0,0 +1,322 @@
+/// Resolution of the cone mesh of spot lights, its polygon has to enclose the circle of the cone
+pub const SPOT_LIGHT_CONE_RESOLUTION: usize = 8;
+
+/// Cascade splits blend between the logarithmic (1.0) and the uniform (0.0) split scheme
+const CASCADE_SPLIT_LAMBDA: f32 = 0.75;
+
+/// Fraction of each cascade which is blended with the next cascade
+const CASCADE_BLEND_FRACTION: f32 = 0.1;
+
+/// A shadow map and the view depth range in which it is used
+pub struct ShadowCascade {
+    pub camera: crate::camera::Camera,
+    pub split_far: f32,
+    pub blend_distance: f32,
+}
+
+impl ShadowCascade {
+    pub fn new(camera: crate::camera::Camera) -> Self {
+        Self {
+            camera,
+            split_far: std::f32::INFINITY,
+            blend_distance: 0.0,
+        }
+    }
+}
+
+pub trait Light {
+    /// Whether the light is drawn as a volume and needs an instance slot
+    const HAS_VOLUME: bool;
+
+    fn get_world_matrix(&self) -> glam::Mat4;
+}
+
+/// Lights every surface evenly, all ambient lights are summed up and applied in one pass
+#[derive(Clone, Copy, Debug)]
+pub struct AmbientLight {
+    pub color: glam::Vec3,
+    pub intensity: f32,
+}
+
+impl Light for AmbientLight {
+    const HAS_VOLUME: bool = false;
+
+    fn get_world_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::identity()
+    }
+}
+
+/// Shines along -z of the world matrix, inside the box from (-1, -1, -1) to (1, 1, 0) in its local space
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelLight {
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub world_matrix: glam::Mat4,
+    pub casts_shadow: bool,
+    /// 0 uses one shadow map for the entire light volume, otherwise the cascades are fit to the view camera
+    pub shadow_cascade_count: usize,
+    /// Cascades end here if the far plane of the view camera is further away
+    pub shadow_distance: f32,
+}
+
+impl ParallelLight {
+    /// Orthographic projection of the light volume
+    pub fn get_shadow_camera(&self) -> crate::camera::Camera {
+        let (scale, rotation, translation) = self.world_matrix.to_scale_rotation_translation();
+        let mut camera = crate::camera::Camera::default();
+        camera.set_orthographic(0.0, scale.z(), scale.x()*2.0, scale.y()*2.0);
+        camera.set_world_matrix(glam::Mat4::from_rotation_translation(rotation, translation));
+        camera
+    }
+
+    /// Casters in the light volume in front of a cascade are included, up to the depth of the light volume.
+    /// There are no cascades if the near plane of the view camera is beyond the shadow distance.
+    pub fn get_shadow_cascades(&self, camera: &crate::camera::Camera, shadow_map_size: u32) -> Vec<ShadowCascade> {
+        let camera_pyramid = match camera.get_bounding_volume() {
+            crate::bounding_volume::BoundingVolume::Pyramid(pyramid) if self.shadow_cascade_count > 0 => *pyramid,
+            _ => return vec![ShadowCascade::new(self.get_shadow_camera())],
+        };
+        let (scale, rotation, _translation) = self.world_matrix.to_scale_rotation_translation();
+        let inverse_rotation = rotation.conjugate();
+        let camera_world_matrix = camera.get_bounding_volume_world_matrix();
+        let near = camera.get_near();
+        if near >= self.shadow_distance {
+            return Vec::new();
+        }
+        let far = camera.get_far().min(self.shadow_distance);
+        let mut cascades = Vec::with_capacity(self.shadow_cascade_count);
+        let mut split_near = near;
+        for cascade_index in 1..=self.shadow_cascade_count {
+            let t = cascade_index as f32/self.shadow_cascade_count as f32;
+            let split_far = CASCADE_SPLIT_LAMBDA*near*(far/near).powf(t)+(1.0-CASCADE_SPLIT_LAMBDA)*(near+(far-near)*t);
+            let pyramid = crate::bounding_volume::BoundingPyramid {
+                near: split_near,
+                far: split_far,
+                width_at_far: camera_pyramid.width_at_far*split_far/camera_pyramid.far,
+                height_at_far: camera_pyramid.height_at_far*split_far/camera_pyramid.far,
+            };
+            let points = crate::bounding_volume::points_of_bounding_pyramid(&pyramid, &camera_world_matrix);
+            let center = points.iter().fold(glam::Vec3A::zero(), |sum, point| sum+*point)/points.len() as f32;
+            // A bounding sphere keeps the size of the cascade independent of the camera rotation
+            let radius = points.iter().map(|point| (*point-center).length()).fold(0.0, f32::max);
+            let radius = (radius*16.0).ceil()/16.0;
+            // Move the cascade in whole texels only, so that the shadow edges do not shimmer
+            let texel_size = radius*2.0/shadow_map_size as f32;
+            let light_space_center = inverse_rotation*glam::Vec3::from(center);
+            let light_space_center = glam::Vec3::new(
+                (light_space_center.x()/texel_size).floor()*texel_size,
+                (light_space_center.y()/texel_size).floor()*texel_size,
+                light_space_center.z()+radius+scale.z(),
+            );
+            let mut shadow_camera = crate::camera::Camera::default();
+            shadow_camera.set_orthographic(0.0, radius*2.0+scale.z(), radius*2.0, radius*2.0);
+            shadow_camera.set_world_matrix(glam::Mat4::from_rotation_translation(rotation, rotation*light_space_center));
+            let blend_distance = (split_far-split_near)*CASCADE_BLEND_FRACTION;
+            cascades.push(ShadowCascade {
+                camera: shadow_camera,
+                split_far,
+                blend_distance,
+            });
+            // The next cascade also covers the blend region of this one
+            split_near = split_far-blend_distance;
+        }
+        cascades
+    }
+}
+
+impl Light for ParallelLight {
+    const HAS_VOLUME: bool = true;
+
+    fn get_world_matrix(&self) -> glam::Mat4 {
+        self.world_matrix
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub position: glam::Vec3,
+    pub radius: f32,
+}
+
+impl Light for PointLight {
+    const HAS_VOLUME: bool = true;
+
+    fn get_world_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(self.radius), glam::Quat::identity(), self.position)
+    }
+}
+
+/// Shines along -z of its rotation, angles are measured from the axis of the cone
+#[derive(Clone, Copy, Debug)]
+pub struct SpotLight {
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub position: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub radius: f32,
+    pub outer_angle: f32,
+    pub inner_angle: f32,
+    pub casts_shadow: bool,
+}
+
+impl SpotLight {
+    /// Perspective projection which encloses the cone of the light
+    pub fn get_shadow_camera(&self) -> crate::camera::Camera {
+        let mut camera = crate::camera::Camera::default();
+        camera.set_perspective(self.radius*0.01, self.radius, self.outer_angle*2.0, 1.0);
+        camera.set_world_matrix(glam::Mat4::from_rotation_translation(self.rotation, self.position));
+        camera
+    }
+}
+
+impl Light for SpotLight {
+    const HAS_VOLUME: bool = true;
+
+    fn get_world_matrix(&self) -> glam::Mat4 {
+        let polygon_factor = 1.0/(std::f32::consts::PI/SPOT_LIGHT_CONE_RESOLUTION as f32).cos();
+        let radius_at_far = self.outer_angle.tan()*self.radius*polygon_factor;
+        glam::Mat4::from_scale_rotation_translation(glam::Vec3::new(radius_at_far, radius_at_far, self.radius), self.rotation, self.position)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LightHandle(usize);
+
+/// Lights of one type, they occupy a contiguous range of instance slots so that they can be drawn in one call
+pub struct LightList<T: Light> {
+    lights: Vec<T>,
+    handles: Vec<LightHandle>,
+    next_handle: usize,
+    instances: std::ops::Range<u32>,
+}
+
+impl<T: Light> Default for LightList<T> {
+    fn default() -> Self {
+        Self {
+            lights: Vec::new(),
+            handles: Vec::new(),
+            next_handle: 0,
+            instances: 0..0,
+        }
+    }
+}
+
+impl<T: Light> LightList<T> {
+    pub fn add(&mut self, scene: &mut crate::scene::Scene, light: T) -> LightHandle {
+        if T::HAS_VOLUME && self.lights.len() as u32 == self.instances.end-self.instances.start {
+            let capacity = (self.instances.end-self.instances.start).max(1)*2;
+            scene.free_instances(self.instances.clone());
+            self.instances = scene.allocate_instances(capacity);
+        }
+        let handle = LightHandle(self.next_handle);
+        self.next_handle += 1;
+        self.lights.push(light);
+        self.handles.push(handle);
+        handle
+    }
+
+    pub fn remove(&mut self, handle: LightHandle) -> Option<T> {
+        let index = self.handles.iter().position(|other| *other == handle)?;
+        self.handles.swap_remove(index);
+        Some(self.lights.swap_remove(index))
+    }
+
+    pub fn get(&self, handle: LightHandle) -> Option<&T> {
+        self.handles.iter().position(|other| *other == handle).map(move |index| &self.lights[index])
+    }
+
+    pub fn get_mut(&mut self, handle: LightHandle) -> Option<&mut T> {
+        let lights = &mut self.lights;
+        self.handles.iter().position(|other| *other == handle).map(move |index| &mut lights[index])
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<T> {
+        self.lights.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Instance slots of the lights which are currently in use
+    pub fn get_instances(&self) -> std::ops::Range<u32> {
+        self.instances.start..self.instances.start+self.lights.len() as u32
+    }
+
+    fn update(&self, scene: &mut crate::scene::Scene) {
+        if !T::HAS_VOLUME {
+            return;
+        }
+        for (instance_index, light) in self.get_instances().zip(self.lights.iter()) {
+            scene.set_world_matrix(instance_index, light.get_world_matrix());
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Lights {
+    pub ambient_lights: LightList<AmbientLight>,
+    pub parallel_lights: LightList<ParallelLight>,
+    pub point_lights: LightList<PointLight>,
+    pub spot_lights: LightList<SpotLight>,
+}
+
+impl Lights {
+    /// Writes the world matrices of the light volumes into their instance slots
+    pub fn update(&self, scene: &mut crate::scene::Scene) {
+        self.parallel_lights.update(scene);
+        self.point_lights.update(scene);
+        self.spot_lights.update(scene);
+    }
+
+    pub fn get_ambient_color(&self) -> glam::Vec3 {
+        self.ambient_lights.iter().fold(glam::Vec3::zero(), |sum, light| sum+light.color*light.intensity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parallel_light(shadow_distance: f32) -> ParallelLight {
+        ParallelLight {
+            color: glam::Vec3::one(),
+            intensity: 1.0,
+            world_matrix: glam::Mat4::from_scale(glam::Vec3::new(100.0, 100.0, 100.0)),
+            casts_shadow: true,
+            shadow_cascade_count: 4,
+            shadow_distance,
+        }
+    }
+
+    fn view_camera(near: f32, far: f32) -> crate::camera::Camera {
+        let mut camera = crate::camera::Camera::default();
+        camera.set_perspective(near, far, std::f32::consts::PI*0.5, 1.0);
+        camera
+    }
+
+    #[test]
+    fn test_cascades_end_at_shadow_distance() {
+        let cascades = parallel_light(50.0).get_shadow_cascades(&view_camera(0.1, 1000.0), 1024);
+        assert_eq!(cascades.len(), 4);
+        let mut split_near = 0.1;
+        for cascade in &cascades {
+            assert!(cascade.split_far > split_near);
+            assert!(cascade.blend_distance > 0.0);
+            split_near = cascade.split_far;
+        }
+        assert!((split_near-50.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_no_cascades_behind_shadow_distance() {
+        assert!(parallel_light(5.0).get_shadow_cascades(&view_camera(10.0, 1000.0), 1024).is_empty());
+        assert!(parallel_light(10.0).get_shadow_cascades(&view_camera(10.0, 1000.0), 1024).is_empty());
+    }
+}
@@ -1,12 +1,30 @@
 mod controls;
 #[macro_use]
 mod renderer;
+mod render_graph;
+mod pipeline_layout;
 mod bounding_volume;
+mod gjk;
 mod camera;
+mod camera_path;
+mod camera_bookmark;
+mod orbit_camera;
 mod assets;
 mod particle;
+mod cubemap_capture;
+mod scene;
+mod light;
 mod planet;
 mod star;
+mod planetary_system;
+mod headless;
+mod screenshot;
+mod shader_permutation;
+#[cfg(feature = "shader-reload")]
+mod shader_compiler;
+#[cfg(feature = "shader-reload")]
+mod shader_reload;
+mod profiler;
 
 use iced_wgpu::{wgpu, Backend, Renderer, Settings, Viewport};
 use iced_winit::{
@@ -45,34 +63,26 @@ fn generate_swap_chain_descriptor(window: &winit::window::Window, scale_factor:
     }
 }
 
-pub fn main() {
-    // env_logger::init();
-
-    let event_loop = winit::event_loop::EventLoop::new();
-    let window = winit::window::Window::new(&event_loop).unwrap();
-
-    let physical_size = window.inner_size();
-    let mut viewport = Viewport::with_physical_size(
-        Size::new(physical_size.width, physical_size.height),
-        window.scale_factor(),
-    );
-
-    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
-    let surface = unsafe { instance.create_surface(&window) };
-
-    let (mut device, queue) = futures::executor::block_on(async {
+/// Without a compatible surface any adapter is accepted, including software Vulkan implementations like lavapipe
+fn request_device(instance: &wgpu::Instance, compatible_surface: Option<&wgpu::Surface>) -> Result<(wgpu::Device, wgpu::Queue), assets::AssetError> {
+    futures::executor::block_on(async {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::Default,
-                compatible_surface: Some(&surface),
+                compatible_surface,
             })
             .await
-            .unwrap();
-
+            .ok_or(assets::AssetError::NoAdapter)?;
+        let features = wgpu::Features::PUSH_CONSTANTS|wgpu::Features::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING;
+        // Checked up front so that drivers without non uniform indexing, e.g. older lavapipe versions, get a clear error
+        let missing_features = features-adapter.features();
+        if !missing_features.is_empty() {
+            return Err(assets::AssetError::MissingFeatures(missing_features));
+        }
         adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::PUSH_CONSTANTS|wgpu::Features::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+                    features,
                     limits: wgpu::Limits {
                         max_push_constant_size: 128,
                         ..Default::default()
@@ -82,30 +92,66 @@ pub fn main() {
                 None,
             )
             .await
-            .unwrap()
-    });
+            .map_err(assets::AssetError::RequestDeviceError)
+    })
+}
+
+pub fn main() {
+    // env_logger::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "--headless" {
+        if let Err(error) = headless::run(std::path::Path::new(&args[2])) {
+            eprintln!("Headless rendering failed: {:?}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(feature = "shader-reload")]
+    let mut shader_watcher = if args.iter().any(|arg| arg == "--watch-shaders") {
+        Some(shader_reload::ShaderWatcher::new())
+    } else {
+        None
+    };
+    #[cfg(not(feature = "shader-reload"))]
+    if args.iter().any(|arg| arg == "--watch-shaders") {
+        eprintln!("--watch-shaders needs the shader-reload feature, the embedded shaders are used");
+    }
+
+    let event_loop = winit::event_loop::EventLoop::new();
+    let window = winit::window::Window::new(&event_loop).unwrap();
+
+    let physical_size = window.inner_size();
+    let mut viewport = Viewport::with_physical_size(
+        Size::new(physical_size.width, physical_size.height),
+        window.scale_factor(),
+    );
+
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let surface = unsafe { instance.create_surface(&window) };
+
+    let (mut device, queue) = match request_device(&instance, Some(&surface)) {
+        Ok(device_and_queue) => device_and_queue,
+        Err(error) => {
+            eprintln!("Requesting a device failed: {:?}", error);
+            std::process::exit(1);
+        }
+    };
 
     let mut resized = false;
     let mut staging_belt = wgpu::util::StagingBelt::new(5 * 1024);
     let mut local_pool = futures::executor::LocalPool::new();
     let mut controls = controls::Controls::new(&device);
 
-    let (mut swap_chain, mut renderer, asset_pack, particle_renderer, particle_system, planet_renderer, star_renderer) = {
+    let mut scene = scene::Scene::default();
+    let mut profiler = profiler::Profiler::default();
+    let (mut swap_chain, mut renderer, mut asset_pack, mut mipmap_generator, mut particle_renderer, particle_system, mut planetary_system) = {
         let mut encoder = device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor { label: None },
         );
         let swap_chain_descriptor = generate_swap_chain_descriptor(&window, controls.render_options.scale_factor);
         let swap_chain = device.create_swap_chain(&surface, &swap_chain_descriptor);
-        let mut path_pool = assets::AssetPack::create_path_pool();
-        assets::AssetPack::collect_paths(&mut path_pool, &std::path::PathBuf::from("assets/shader_modules/"));
-        let mut asset_pack = assets::AssetPack::default();
-        asset_pack.load(&device, &queue, &mut encoder, None, None, None, &path_pool).unwrap();
-        let mipmap_generator = assets::MipmapGenerator::new(&device, &asset_pack);
-        let renderer = renderer::Renderer::new(&mut device, &swap_chain_descriptor, &controls.render_options, &asset_pack);
-        let mut path_pool = assets::AssetPack::create_path_pool();
-        assets::AssetPack::collect_paths(&mut path_pool, &std::path::PathBuf::from("assets/example/meshes/hex/"));
-        assets::AssetPack::collect_paths(&mut path_pool, &std::path::PathBuf::from("assets/textures/"));
-        asset_pack.load(&device, &queue, &mut encoder, Some(&renderer.bind_group_layouts.surface_pass_bind_group_layout), Some(&renderer.sampler), Some(&mipmap_generator), &path_pool).unwrap();
+        let (asset_pack, mipmap_generator, renderer) = planetary_system::load_renderer(&device, &queue, &mut encoder, &swap_chain_descriptor, &controls.render_options, &["assets/example/meshes/hex/", "assets/textures/"], &mut profiler).unwrap();
         let particle_renderer = particle::ParticleRenderer::new(&device, &renderer, &asset_pack);
         let particle_system = crate::particle::ParticleSystem::new(&device, &particle_renderer, 512);
         {
@@ -133,16 +179,44 @@ pub fn main() {
             ];
             particle_system.generate_clouds(&queue, &matrices);
         }
-        let mut planet_renderer = planet::PlanetRenderer::new(&device, &renderer, &asset_pack);
-        controls.planet.generate_terrain_and_selection_texture(&queue);
-        controls.planet.generate_atmosphere(&device, &mut encoder, &planet_renderer);
-        planet_renderer.generate_bind_group(&device, &renderer, &asset_pack, &controls.planet);
-        let star_renderer = star::StarRenderer::new(&device, &renderer, &asset_pack);
+        let planetary_system = planetary_system::PlanetarySystem::new(&device, &queue, &mut encoder, &renderer, &asset_pack, &mut scene, &mut controls.planet, glam::Vec3::new(0.0, 0.0, 5000.0), 500.0, &mut profiler);
         staging_belt.finish();
         queue.submit(Some(encoder.finish()));
-        (swap_chain, renderer, asset_pack, particle_renderer, particle_system, planet_renderer, star_renderer)
+        (swap_chain, renderer, asset_pack, mipmap_generator, particle_renderer, particle_system, planetary_system)
     };
 
+    let mut lights = light::Lights::default();
+    lights.ambient_lights.add(&mut scene, light::AmbientLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 0.1,
+    });
+    lights.spot_lights.add(&mut scene, light::SpotLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 1.0,
+        position: glam::Vec3::new(0.0, 14.5, 0.0),
+        rotation: glam::Quat::from_rotation_x(-0.1*std::f32::consts::PI),
+        radius: 2.0,
+        outer_angle: std::f32::consts::PI*0.25,
+        inner_angle: std::f32::consts::PI*0.2,
+        casts_shadow: true,
+    });
+    lights.point_lights.add(&mut scene, light::PointLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 1.0,
+        position: glam::Vec3::new(14.5, 0.0, 0.0),
+        radius: 2.0,
+    });
+    lights.parallel_lights.add(&mut scene, light::ParallelLight {
+        color: glam::Vec3::splat(1.0),
+        intensity: 0.8,
+        world_matrix: glam::Mat4::from_scale_rotation_translation(glam::Vec3::new(20.0, 20.0, 40.0), glam::Quat::identity(), glam::Vec3::new(0.0, 0.0, 20.0)),
+        casts_shadow: true,
+        shadow_cascade_count: 4,
+        shadow_distance: 200.0,
+    });
+    let selection_instances = scene.allocate_instances(1);
+    let particle_instances = scene.allocate_instances(1);
+
     let cursor_position = conversion::cursor_position(controls.pointer_position, viewport.scale_factor());
     let mut gui_debug = Debug::new();
     let mut gui_renderer = Renderer::new(Backend::new(&mut device, Settings::default()));
@@ -154,9 +228,20 @@ pub fn main() {
         &mut gui_debug,
     );
     gui_state.queue_message(controls::Message::Resized(physical_size));
+    let mut last_update_time: Option<std::time::Instant> = None;
+    let mut last_redraw_time: Option<std::time::Instant> = None;
+    let mut shaders_reloaded = false;
 
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = winit::event_loop::ControlFlow::Wait;
+        *control_flow = if last_update_time.is_some() {
+            winit::event_loop::ControlFlow::Poll
+        } else {
+            winit::event_loop::ControlFlow::Wait
+        };
+        #[cfg(feature = "shader-reload")]
+        if let (winit::event_loop::ControlFlow::Wait, Some(shader_watcher)) = (*control_flow, &shader_watcher) {
+            *control_flow = winit::event_loop::ControlFlow::WaitUntil(shader_watcher.next_poll_time());
+        }
 
         match event {
             Event::WindowEvent { event, .. } => {
@@ -191,6 +276,7 @@ pub fn main() {
                         resized = true;
                     },
                     WindowEvent::CloseRequested => {
+                        gui_state.program().save_camera_bookmarks();
                         *control_flow = winit::event_loop::ControlFlow::Exit;
                     },
                     _ => {}
@@ -206,6 +292,12 @@ pub fn main() {
                 }
             }
             Event::MainEventsCleared => {
+                let now = std::time::Instant::now();
+                if last_update_time.is_some() || !gui_state.is_queue_empty() {
+                    // Animations start with a zero time step after the loop was idle
+                    let delta_time = last_update_time.map(|time| (now-time).as_secs_f32().min(0.1)).unwrap_or(0.0);
+                    gui_state.queue_message(controls::Message::Tick(delta_time));
+                }
                 if !gui_state.is_queue_empty() {
                     let program = gui_state.program();
                     let cursor_position = conversion::cursor_position(program.pointer_position, viewport.scale_factor());
@@ -218,6 +310,14 @@ pub fn main() {
                     );
                     window.request_redraw();
                 }
+                last_update_time = if gui_state.program().is_animating() { Some(now) } else { None };
+                #[cfg(feature = "shader-reload")]
+                if let Some(shader_watcher) = &mut shader_watcher {
+                    if shader_watcher.poll(&device, &mut asset_pack) {
+                        shaders_reloaded = true;
+                        window.request_redraw();
+                    }
+                }
             }
             Event::RedrawRequested(_) => {
                 let controls = gui_state.program();
@@ -228,42 +328,41 @@ pub fn main() {
                     renderer.resize(&mut device, &swap_chain_descriptor);
                     resized = false;
                 }
+                if shaders_reloaded || renderer.get_render_options() != &controls.render_options {
+                    renderer.apply_render_options(&device, &controls.render_options, &asset_pack);
+                }
+                let screenshot_request = controls.screenshot_request;
+                let trace_export_request = controls.trace_export_request;
+                profiler.mode = controls.profiler_mode;
+                profiler.begin_frame();
+                renderer.set_frame_capture(&device, screenshot_request.is_some());
 
                 let mut encoder = device.create_command_encoder(
                     &wgpu::CommandEncoderDescriptor { label: None },
                 );
 
-                renderer.update_instances(
-                    &mut encoder,
-                    &queue,
-                    &[
-                        glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(2.0), glam::Quat::from_rotation_x(-0.1*std::f32::consts::PI), glam::Vec3::new(0.0, 14.5, 0.0)),
-                        glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(2.0), glam::Quat::identity(), glam::Vec3::new(14.5, 0.0, 0.0)),
-                        glam::Mat4::from_scale_rotation_translation(glam::Vec3::new(20.0, 20.0, 40.0), glam::Quat::identity(), glam::Vec3::new(0.0, 0.0, 20.0)),
-                        controls.selection_matrix,
-                        glam::Mat4::from_scale(glam::Vec3::splat(controls.planet.surface_radius())),
-                        glam::Mat4::from_scale(glam::Vec3::splat(controls.planet.atmosphere_radius())),
-                        glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(500.0), glam::Quat::identity(), glam::Vec3::new(0.0, 0.0, 5000.0)),
-                        glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(500.0*1.75), glam::Quat::identity(), glam::Vec3::new(0.0, 0.0, 5000.0)),
-                        glam::Mat4::identity(),
-                    ]
-                );
-                renderer.update_camera(&mut encoder, &queue, 8, &controls.camera);
-                {
-                    let mut surface_pass = renderer.render_surface_pass(&mut encoder);
-                    surface_pass.set_pipeline(&renderer.render_pipelines.surface_pass_pipeline);
-                    asset_pack.meshes[&std::path::PathBuf::from("assets/example/meshes/hex/hex/Circle")].render(&mut surface_pass, 3..4);
-                    star_renderer.render_surface(&mut surface_pass, 6..7);
-                    planet_renderer.render_surface(&mut surface_pass, 4..5);
-                    particle_renderer.render_surface(&mut surface_pass, &particle_system, 8..9);
-                }
-                {
-                    let mut volumetric_pass = renderer.render_volumetric_pass(&mut encoder);
-                    planet_renderer.render_atmosphere(&mut volumetric_pass, 5..6);
-                    star_renderer.render_atmosphere(&mut volumetric_pass, 7..8);
+                if shaders_reloaded {
+                    // The particles keep their state, only the pipelines and the precomputed textures are recreated
+                    renderer.recreate_compute_pipelines(&device, &asset_pack);
+                    mipmap_generator = assets::MipmapGenerator::new(&device, &asset_pack);
+                    profiler.scope(&mut encoder, "mipmaps", |encoder| asset_pack.generate_mipmaps(&device, encoder, &mipmap_generator));
+                    particle_renderer.recreate_pipeline(&device, &renderer, &asset_pack);
+                    planetary_system.recreate_renderers(&device, &mut encoder, &renderer, &asset_pack, &controls.planet, &mut profiler);
+                    shaders_reloaded = false;
                 }
+
+                scene.set_world_matrix(selection_instances.start, controls.selection_matrix);
+                planetary_system.update_instances(&mut scene, &controls.planet);
+                lights.update(&mut scene);
+                planetary_system.render_scene(&device, &queue, &mut encoder, &mut renderer, &scene, &lights, &controls.camera, &planetary_system::AdditionalDrawCalls {
+                    meshes: vec![(&asset_pack.meshes[&std::path::PathBuf::from("assets/example/meshes/hex/hex/Circle")], selection_instances.clone())],
+                    particles: Some((&particle_renderer, &particle_system, particle_instances.clone())),
+                }, &mut profiler);
+                let now = std::time::Instant::now();
+                let delta_time = last_redraw_time.map(|time| (now-time).as_secs_f32().min(0.1)).unwrap_or(0.0);
+                last_redraw_time = Some(now);
                 let frame = swap_chain.get_current_frame().unwrap();
-                renderer.render_frame(&mut encoder, &frame.output.view);
+                renderer.render_frame(&mut encoder, &frame.output.view, delta_time, &mut profiler);
 
                 let mouse_interaction = gui_renderer.backend_mut().draw(
                     &mut device,
@@ -283,6 +382,26 @@ pub fn main() {
 
                 local_pool.spawner().spawn(staging_belt.recall()).unwrap();
                 local_pool.run_until_stalled();
+
+                profiler.end_frame();
+                if let Some(profiler_frame) = profiler.get_last_frame() {
+                    if profiler.mode != profiler::ProfilerMode::Disabled {
+                        gui_state.queue_message(controls::Message::ProfilerFrame(profiler_frame.clone()));
+                    }
+                }
+                if trace_export_request {
+                    match profiler.save_chrome_trace() {
+                        Ok(path) => eprintln!("Saved trace to {:?}", path),
+                        Err(error) => eprintln!("Could not save trace: {:?}", error),
+                    }
+                    gui_state.queue_message(controls::Message::TraceExported);
+                }
+                if let Some(screenshot_request) = screenshot_request {
+                    if let Err(error) = screenshot::save_screenshot(&device, &queue, &renderer, screenshot_request) {
+                        eprintln!("Could not save screenshot: {:?}", error);
+                    }
+                    gui_state.queue_message(controls::Message::ScreenshotSaved);
+                }
             }
             _ => {}
         }
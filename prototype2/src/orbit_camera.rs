@@ -0,0 +1,274 @@
+const EPSILON: f32 = 0.0001;
+
+/// Advances a damped velocity with constant acceleration by delta_time exactly,
+/// so that the result does not depend on how the time is sliced into frames.
+/// Returns the new velocity and the distance travelled.
+fn integrate_damped(velocity: f32, acceleration: f32, damping: f32, delta_time: f32) -> (f32, f32) {
+    let terminal_velocity = acceleration/damping;
+    let decay = (-damping*delta_time).exp();
+    let new_velocity = terminal_velocity+(velocity-terminal_velocity)*decay;
+    let distance = terminal_velocity*delta_time+(velocity-terminal_velocity)*(1.0-decay)/damping;
+    (new_velocity, distance)
+}
+
+/// Factor to move towards a goal per frame, such that the remaining distance halves every ln(2)/smoothing seconds
+fn smoothing_factor(smoothing: f32, delta_time: f32) -> f32 {
+    1.0-(-smoothing*delta_time).exp()
+}
+
+fn ease_in_out(t: f32) -> f32 {
+    t*t*(3.0-2.0*t)
+}
+
+pub type AnimationCallback = Box<dyn FnOnce(&mut crate::controls::Controls)>;
+
+/// Slerps the view quaternion, the roll which the slerp picks up in between is dropped,
+/// as the orbit camera only has a yaw and a pitch
+pub struct CameraAnimation {
+    start_view_quaternion: glam::Quat,
+    end_view_quaternion: glam::Quat,
+    start_view_zoom: f32,
+    end_view_zoom: f32,
+    duration: f32,
+    elapsed: f32,
+    on_completion: Option<AnimationCallback>,
+}
+
+pub struct OrbitCamera {
+    pub target: glam::Vec3A,
+    pub follow_target: glam::Vec3A,
+    pub follow_smoothing: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    /// Yaw and pitch in radians per second
+    pub angular_velocity: glam::Vec2,
+    pub angular_acceleration: f32,
+    pub angular_damping: f32,
+    /// Direction of the held rotation keys, each component in [-1, 1]
+    pub rotation_input: glam::Vec2,
+    pub view_zoom: f32,
+    pub target_view_zoom: f32,
+    pub min_view_zoom: f32,
+    pub max_view_zoom: f32,
+    pub zoom_smoothing: f32,
+    is_dragging: bool,
+    drag_delta: glam::Vec2,
+    animation: Option<CameraAnimation>,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            target: glam::Vec3A::splat(0.0),
+            follow_target: glam::Vec3A::splat(0.0),
+            follow_smoothing: 5.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_pitch: -std::f32::consts::PI*0.45,
+            max_pitch: std::f32::consts::PI*0.45,
+            angular_velocity: glam::Vec2::splat(0.0),
+            angular_acceleration: 8.0,
+            angular_damping: 4.0,
+            rotation_input: glam::Vec2::splat(0.0),
+            view_zoom: 3.0,
+            target_view_zoom: 3.0,
+            min_view_zoom: 3.0,
+            max_view_zoom: 10.0,
+            zoom_smoothing: 10.0,
+            is_dragging: false,
+            drag_delta: glam::Vec2::splat(0.0),
+            animation: None,
+        }
+    }
+}
+
+impl OrbitCamera {
+    pub fn get_view_quaternion(&self) -> glam::Quat {
+        glam::Quat::from_rotation_x(self.pitch)*glam::Quat::from_rotation_y(self.yaw)
+    }
+
+    /// Inverse of get_view_quaternion, the roll of other orientations is lost
+    pub fn yaw_and_pitch_of_view_quaternion(view_quaternion: glam::Quat) -> (f32, f32) {
+        let rotation = glam::Mat4::from_quat(view_quaternion);
+        (rotation.z_axis().x().atan2(rotation.x_axis().x()), rotation.y_axis().z().atan2(rotation.y_axis().y()))
+    }
+
+    pub fn set_view_quaternion(&mut self, view_quaternion: glam::Quat) {
+        let (yaw, pitch) = Self::yaw_and_pitch_of_view_quaternion(view_quaternion);
+        self.yaw = yaw;
+        self.pitch = pitch.max(self.min_pitch).min(self.max_pitch);
+        self.angular_velocity = glam::Vec2::splat(0.0);
+    }
+
+    /// Orientation which places the camera on the ray from the target along the given direction
+    pub fn view_quaternion_from_direction(direction: glam::Vec3A) -> glam::Quat {
+        let horizontal_length = (direction.x()*direction.x()+direction.z()*direction.z()).sqrt();
+        glam::Quat::from_rotation_x(direction.y().atan2(horizontal_length))*glam::Quat::from_rotation_y((-direction.x()).atan2(direction.z()))
+    }
+
+    pub fn get_world_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_translation(glam::Vec3::from(self.target))
+            *glam::Mat4::from_quat(self.get_view_quaternion()).inverse()
+            *glam::Mat4::from_translation(glam::Vec3::new(0.0, 0.0, self.view_zoom.exp()))
+    }
+
+    pub fn zoom(&mut self, delta: f32) {
+        self.target_view_zoom = (self.target_view_zoom+delta).max(self.min_view_zoom).min(self.max_view_zoom);
+    }
+
+    /// Animates view_quaternion and view_zoom, the callback is called once the animation has finished.
+    /// The slerp takes the shorter way around and the end pitch is limited to min_pitch and max_pitch.
+    pub fn animate_to(&mut self, view_quaternion: glam::Quat, view_zoom: f32, duration: f32, on_completion: Option<AnimationCallback>) {
+        let (end_yaw, end_pitch) = Self::yaw_and_pitch_of_view_quaternion(view_quaternion);
+        let start_view_quaternion = self.get_view_quaternion();
+        let end_view_quaternion = glam::Quat::from_rotation_x(end_pitch.max(self.min_pitch).min(self.max_pitch))*glam::Quat::from_rotation_y(end_yaw);
+        self.animation = Some(CameraAnimation {
+            start_view_quaternion,
+            end_view_quaternion: if start_view_quaternion.dot(end_view_quaternion) < 0.0 { -end_view_quaternion } else { end_view_quaternion },
+            start_view_zoom: self.view_zoom,
+            end_view_zoom: view_zoom.max(self.min_view_zoom).min(self.max_view_zoom),
+            duration: duration.max(EPSILON),
+            elapsed: 0.0,
+            on_completion,
+        });
+        self.angular_velocity = glam::Vec2::splat(0.0);
+    }
+
+    /// Stops the animation without calling its callback
+    pub fn cancel_animation(&mut self) {
+        self.animation = None;
+    }
+
+    pub fn begin_drag(&mut self) {
+        self.cancel_animation();
+        self.is_dragging = true;
+        self.drag_delta = glam::Vec2::splat(0.0);
+    }
+
+    /// Rotation in radians (yaw, pitch) which is applied at the next update
+    pub fn drag(&mut self, delta: glam::Vec2) {
+        self.drag_delta += delta;
+    }
+
+    /// The angular velocity measured while dragging is kept, so the camera continues to spin
+    pub fn end_drag(&mut self) {
+        self.is_dragging = false;
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.is_dragging
+            || self.animation.is_some()
+            || self.rotation_input != glam::Vec2::splat(0.0)
+            || self.angular_velocity.length() > EPSILON
+            || (self.target_view_zoom-self.view_zoom).abs() > EPSILON
+            || (self.follow_target-self.target).length() > EPSILON
+    }
+
+    /// Returns the callback of the animation if it finished during this update
+    pub fn update(&mut self, delta_time: f32) -> Option<AnimationCallback> {
+        if self.rotation_input != glam::Vec2::splat(0.0) {
+            self.cancel_animation();
+        }
+        let mut on_completion = None;
+        if let Some(animation) = &mut self.animation {
+            animation.elapsed = (animation.elapsed+delta_time).min(animation.duration);
+            let t = ease_in_out(animation.elapsed/animation.duration);
+            let (yaw, pitch) = Self::yaw_and_pitch_of_view_quaternion(animation.start_view_quaternion.slerp(animation.end_view_quaternion, t).normalize());
+            self.yaw = yaw;
+            self.pitch = pitch.max(self.min_pitch).min(self.max_pitch);
+            self.view_zoom = animation.start_view_zoom+(animation.end_view_zoom-animation.start_view_zoom)*t;
+            self.target_view_zoom = self.view_zoom;
+            if animation.elapsed >= animation.duration {
+                on_completion = animation.on_completion.take();
+                self.animation = None;
+            }
+        } else if self.is_dragging {
+            if delta_time > 0.0 {
+                let factor = smoothing_factor(20.0, delta_time);
+                self.angular_velocity = self.angular_velocity+(self.drag_delta/delta_time-self.angular_velocity)*factor;
+            }
+            self.yaw += self.drag_delta.x();
+            self.pitch += self.drag_delta.y();
+            self.drag_delta = glam::Vec2::splat(0.0);
+        } else {
+            let (yaw_velocity, yaw_distance) = integrate_damped(self.angular_velocity.x(), self.rotation_input.x()*self.angular_acceleration, self.angular_damping, delta_time);
+            let (pitch_velocity, pitch_distance) = integrate_damped(self.angular_velocity.y(), self.rotation_input.y()*self.angular_acceleration, self.angular_damping, delta_time);
+            self.angular_velocity = glam::Vec2::new(yaw_velocity, pitch_velocity);
+            self.yaw += yaw_distance;
+            self.pitch += pitch_distance;
+            if self.angular_velocity.length() <= EPSILON {
+                self.angular_velocity = glam::Vec2::splat(0.0);
+            }
+        }
+        self.yaw = (self.yaw+std::f32::consts::PI).rem_euclid(std::f32::consts::PI*2.0)-std::f32::consts::PI;
+        if self.pitch < self.min_pitch || self.pitch > self.max_pitch {
+            self.pitch = self.pitch.max(self.min_pitch).min(self.max_pitch);
+            *self.angular_velocity.y_mut() = 0.0;
+        }
+        let factor = smoothing_factor(self.zoom_smoothing, delta_time);
+        self.view_zoom += (self.target_view_zoom-self.view_zoom)*factor;
+        if (self.target_view_zoom-self.view_zoom).abs() <= EPSILON {
+            self.view_zoom = self.target_view_zoom;
+        }
+        let factor = smoothing_factor(self.follow_smoothing, delta_time);
+        self.target += (self.follow_target-self.target)*factor;
+        if (self.follow_target-self.target).length() <= EPSILON {
+            self.target = self.follow_target;
+        }
+        on_completion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 0.001;
+
+    fn moving_camera() -> OrbitCamera {
+        let mut orbit_camera = OrbitCamera::default();
+        orbit_camera.rotation_input = glam::Vec2::new(1.0, 0.25);
+        orbit_camera.angular_velocity = glam::Vec2::new(-0.5, 0.1);
+        orbit_camera.zoom(4.0);
+        orbit_camera
+    }
+
+    #[test]
+    fn test_update_is_frame_rate_independent() {
+        let mut one_step = moving_camera();
+        one_step.update(1.0);
+        let mut many_steps = moving_camera();
+        for _ in 0..60 {
+            many_steps.update(1.0/60.0);
+        }
+        assert!((one_step.yaw-many_steps.yaw).abs() < TOLERANCE, "yaw {} != {}", one_step.yaw, many_steps.yaw);
+        assert!((one_step.pitch-many_steps.pitch).abs() < TOLERANCE, "pitch {} != {}", one_step.pitch, many_steps.pitch);
+        assert!((one_step.view_zoom-many_steps.view_zoom).abs() < TOLERANCE, "view_zoom {} != {}", one_step.view_zoom, many_steps.view_zoom);
+    }
+
+    #[test]
+    fn test_animation_slerps_the_shorter_way() {
+        let mut orbit_camera = OrbitCamera::default();
+        orbit_camera.yaw = 3.0;
+        orbit_camera.animate_to(glam::Quat::from_rotation_y(-3.0), 5.0, 1.0, None);
+        orbit_camera.update(0.5);
+        assert!((orbit_camera.yaw.abs()-std::f32::consts::PI).abs() < TOLERANCE, "yaw {}", orbit_camera.yaw);
+        assert!(orbit_camera.pitch.abs() < TOLERANCE, "pitch {}", orbit_camera.pitch);
+        assert!((orbit_camera.view_zoom-4.0).abs() < TOLERANCE, "view_zoom {}", orbit_camera.view_zoom);
+        orbit_camera.update(0.5);
+        assert!((orbit_camera.yaw+3.0).abs() < TOLERANCE, "yaw {}", orbit_camera.yaw);
+        assert!((orbit_camera.view_zoom-5.0).abs() < TOLERANCE, "view_zoom {}", orbit_camera.view_zoom);
+        assert!(!orbit_camera.is_animating());
+    }
+
+    #[test]
+    fn test_animation_limits_the_end_pitch() {
+        let mut orbit_camera = OrbitCamera::default();
+        orbit_camera.animate_to(glam::Quat::from_rotation_x(1.5)*glam::Quat::from_rotation_y(0.5), 3.0, 1.0, None);
+        orbit_camera.update(1.0);
+        assert!((orbit_camera.yaw-0.5).abs() < TOLERANCE, "yaw {}", orbit_camera.yaw);
+        assert!((orbit_camera.pitch-orbit_camera.max_pitch).abs() < TOLERANCE, "pitch {}", orbit_camera.pitch);
+    }
+}
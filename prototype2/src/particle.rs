@@ -2,6 +2,7 @@
 
 use iced_wgpu::{wgpu, wgpu::vertex_attr_array};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
+use crate::pipeline_layout::{BindGroupLayout, PipelineLayout, PipelineLayoutDescriptor};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
@@ -59,14 +60,14 @@ impl ParticleSystem {
 
 pub struct ParticleRenderer {
     surface_pipeline: wgpu::RenderPipeline,
-    surface_bind_group_layout: wgpu::BindGroupLayout,
+    surface_bind_group_layout: BindGroupLayout,
     surface_bind_group: wgpu::BindGroup,
-    particle_system_bind_group_layout: wgpu::BindGroupLayout,
+    particle_system_bind_group_layout: BindGroupLayout,
 }
 
 impl ParticleRenderer {
     pub fn new(device: &wgpu::Device, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack) -> Self {
-        let surface_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let surface_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[],
         });
@@ -75,7 +76,7 @@ impl ParticleRenderer {
             &surface_bind_group_layout,
         ));
 
-        let particle_system_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let particle_system_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -91,14 +92,25 @@ impl ParticleRenderer {
             ],
         });
 
+        let surface_pipeline = Self::create_surface_pipeline(device, renderer, asset_pack, &surface_bind_group_layout, &particle_system_bind_group_layout);
+
+        Self {
+            surface_pipeline,
+            surface_bind_group_layout,
+            surface_bind_group,
+            particle_system_bind_group_layout,
+        }
+    }
+
+    fn create_surface_pipeline(device: &wgpu::Device, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack, surface_bind_group_layout: &BindGroupLayout, particle_system_bind_group_layout: &BindGroupLayout) -> wgpu::RenderPipeline {
         let surface_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[],
-                bind_group_layouts: &[&surface_bind_group_layout, &renderer.bind_group_layouts.camera_uniforms_bind_group_layout, &particle_system_bind_group_layout],
+                bind_group_layouts: &[surface_bind_group_layout, &renderer.bind_group_layouts.camera_uniforms_bind_group_layout, particle_system_bind_group_layout],
             });
 
-        let surface_pipeline = device.create_render_pipeline(&surface_pass_pipeline_descriptor!(
+        let surface_pipeline = surface_pipeline_layout.create_render_pipeline(device, asset_pack, &surface_pass_pipeline_descriptor!(
             asset_pack,
             surface_pipeline_layout,
             "assets/shader_modules/particle_vert",
@@ -114,13 +126,12 @@ impl ParticleRenderer {
                 ],
             }
         ));
+        surface_pipeline
+    }
 
-        Self {
-            surface_pipeline,
-            surface_bind_group_layout,
-            surface_bind_group,
-            particle_system_bind_group_layout,
-        }
+    /// Keeps the bind group layouts, so that the particle systems stay valid, e.g. after the shaders were reloaded
+    pub fn recreate_pipeline(&mut self, device: &wgpu::Device, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack) {
+        self.surface_pipeline = Self::create_surface_pipeline(device, renderer, asset_pack, &self.surface_bind_group_layout, &self.particle_system_bind_group_layout);
     }
 
     pub fn render_surface<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, particle_system: &'a ParticleSystem, instances_indices: std::ops::Range<u32>) {
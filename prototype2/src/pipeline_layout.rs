@@ -0,0 +1,142 @@
+use iced_wgpu::wgpu;
+use crate::assets::AssetPack;
+
+/// Keeps the entries of the layout, so that pipelines can be validated against the shaders
+pub struct BindGroupLayout {
+    layout: wgpu::BindGroupLayout,
+    entries: Vec<wgpu::BindGroupLayoutEntry>,
+}
+
+impl BindGroupLayout {
+    pub fn new(device: &wgpu::Device, descriptor: &wgpu::BindGroupLayoutDescriptor) -> Self {
+        Self {
+            layout: device.create_bind_group_layout(descriptor),
+            entries: descriptor.entries.to_vec(),
+        }
+    }
+}
+
+impl std::ops::Deref for BindGroupLayout {
+    type Target = wgpu::BindGroupLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.layout
+    }
+}
+
+pub struct PipelineLayoutDescriptor<'a> {
+    pub label: Option<&'a str>,
+    pub bind_group_layouts: &'a [&'a BindGroupLayout],
+    pub push_constant_ranges: &'a [wgpu::PushConstantRange],
+}
+
+/// Creates pipelines after checking their bind groups, push constants and vertex inputs against the reflection of their shader modules.
+/// Mismatches panic in debug builds, release builds only report them and create the pipeline anyway, as the device does not validate shaders.
+pub struct PipelineLayout {
+    layout: wgpu::PipelineLayout,
+    bind_group_entries: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+    push_constant_ranges: Vec<wgpu::PushConstantRange>,
+}
+
+impl PipelineLayout {
+    pub fn new(device: &wgpu::Device, descriptor: &PipelineLayoutDescriptor) -> Self {
+        let bind_group_layouts: Vec<&wgpu::BindGroupLayout> = descriptor.bind_group_layouts.iter().map(|bind_group_layout| &bind_group_layout.layout).collect();
+        Self {
+            layout: device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: descriptor.label,
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: descriptor.push_constant_ranges,
+            }),
+            bind_group_entries: descriptor.bind_group_layouts.iter().map(|bind_group_layout| bind_group_layout.entries.clone()).collect(),
+            push_constant_ranges: descriptor.push_constant_ranges.to_vec(),
+        }
+    }
+
+    fn validate_stage(&self, asset_pack: &AssetPack, expected_stage: wgpu::ShaderStage, stage_descriptor: &wgpu::ProgrammableStageDescriptor, vertex_buffers: &[wgpu::VertexBufferDescriptor]) -> Vec<String> {
+        let (path, reflection) = match asset_pack.get_shader_reflection(stage_descriptor.module) {
+            Some(found) => found,
+            None => return Vec::new(),
+        };
+        let mut errors = Vec::new();
+        if reflection.stage != expected_stage {
+            errors.push(format!("is a {:?} shader but is used as {:?} stage", reflection.stage, expected_stage));
+        }
+        for descriptor_binding in reflection.bindings.iter() {
+            let location = format!("binding {} of set {}", descriptor_binding.binding, descriptor_binding.set);
+            let entry = match self.bind_group_entries.get(descriptor_binding.set as usize) {
+                Some(entries) => entries.iter().find(|entry| entry.binding == descriptor_binding.binding),
+                None => {
+                    errors.push(format!("{}: set is not in the pipeline layout", location));
+                    continue;
+                },
+            };
+            let entry = match entry {
+                Some(entry) => entry,
+                None => {
+                    errors.push(format!("{}: binding is not in the bind group layout", location));
+                    continue;
+                },
+            };
+            if !entry.visibility.contains(expected_stage) {
+                errors.push(format!("{}: is not visible to the {:?} stage", location, expected_stage));
+            }
+            if !descriptor_binding.kind.matches(&entry.ty) {
+                errors.push(format!("{}: is a {:?} in the shader but {:?} in the layout", location, descriptor_binding.kind, entry.ty));
+                continue;
+            }
+            let min_binding_size = match entry.ty {
+                wgpu::BindingType::UniformBuffer { min_binding_size, .. } | wgpu::BindingType::StorageBuffer { min_binding_size, .. } => min_binding_size,
+                _ => None,
+            };
+            if let Some(min_binding_size) = min_binding_size {
+                if descriptor_binding.size as u64 > min_binding_size.get() {
+                    errors.push(format!("{}: needs {} bytes but the layout only guarantees {}", location, descriptor_binding.size, min_binding_size.get()));
+                }
+            }
+        }
+        if reflection.push_constant_size > 0 && !self.push_constant_ranges.iter().any(|push_constant_range| {
+            push_constant_range.stages.contains(expected_stage) && push_constant_range.range.start == 0 && push_constant_range.range.end >= reflection.push_constant_size
+        }) {
+            errors.push(format!("push constants need {} bytes but no range of the layout covers them", reflection.push_constant_size));
+        }
+        for input_location in reflection.input_locations.iter() {
+            if !vertex_buffers.iter().flat_map(|vertex_buffer| vertex_buffer.attributes.iter()).any(|attribute| attribute.shader_location == *input_location) {
+                errors.push(format!("vertex input location {} is not provided by any vertex buffer", input_location));
+            }
+        }
+        errors.into_iter().map(|error| format!("{}: {}", path.display(), error)).collect()
+    }
+
+    fn report(errors: Vec<String>) {
+        for error in errors.iter() {
+            eprintln!("Pipeline does not match shader {}", error);
+        }
+        debug_assert!(errors.is_empty(), "{} mismatches between the pipeline and its shaders", errors.len());
+    }
+
+    /// The descriptor has to use this layout
+    pub fn create_render_pipeline(&self, device: &wgpu::Device, asset_pack: &AssetPack, descriptor: &wgpu::RenderPipelineDescriptor) -> wgpu::RenderPipeline {
+        debug_assert!(descriptor.layout.map_or(false, |layout| std::ptr::eq(layout, &self.layout)));
+        let mut errors = self.validate_stage(asset_pack, wgpu::ShaderStage::VERTEX, &descriptor.vertex_stage, descriptor.vertex_state.vertex_buffers);
+        if let Some(fragment_stage) = &descriptor.fragment_stage {
+            errors.extend(self.validate_stage(asset_pack, wgpu::ShaderStage::FRAGMENT, fragment_stage, &[]));
+        }
+        Self::report(errors);
+        device.create_render_pipeline(descriptor)
+    }
+
+    /// The descriptor has to use this layout
+    pub fn create_compute_pipeline(&self, device: &wgpu::Device, asset_pack: &AssetPack, descriptor: &wgpu::ComputePipelineDescriptor) -> wgpu::ComputePipeline {
+        debug_assert!(descriptor.layout.map_or(false, |layout| std::ptr::eq(layout, &self.layout)));
+        Self::report(self.validate_stage(asset_pack, wgpu::ShaderStage::COMPUTE, &descriptor.compute_stage, &[]));
+        device.create_compute_pipeline(descriptor)
+    }
+}
+
+impl std::ops::Deref for PipelineLayout {
+    type Target = wgpu::PipelineLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.layout
+    }
+}
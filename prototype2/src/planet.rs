@@ -2,6 +2,7 @@
 
 use iced_wgpu::{wgpu, wgpu::vertex_attr_array};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
+use crate::pipeline_layout::{BindGroupLayout, PipelineLayout, PipelineLayoutDescriptor};
 use num_integer::Integer;
 
 include!(concat!(env!("OUT_DIR"), "/planet_consts.rs"));
@@ -577,7 +578,7 @@ impl Planet {
         self.terrain_and_selection_texture.upload_pixels(queue, 0, unsafe { crate::transmute_slice(&pixels[..]) });
     }
 
-    pub fn generate_atmosphere(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, planet_renderer: &PlanetRenderer) {
+    pub fn generate_atmosphere(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, planet_renderer: &PlanetRenderer) {
         let atmosphere_density_texture = self.atmosphere_density_texture.as_ref().unwrap();
         let meta_data = &[
             self.atmosphere_radius(),
@@ -603,20 +604,20 @@ impl Planet {
 }
 
 pub struct PlanetRenderer {
-    pub atmosphere_precompute_bind_group_layout: wgpu::BindGroupLayout,
+    pub atmosphere_precompute_bind_group_layout: BindGroupLayout,
     pub atmosphere_precompute_pipeline: wgpu::ComputePipeline,
     atmosphere_sampler: wgpu::Sampler,
     surface_pipeline: wgpu::RenderPipeline,
-    surface_bind_group_layout: wgpu::BindGroupLayout,
+    surface_bind_group_layout: BindGroupLayout,
     surface_bind_group: Option<wgpu::BindGroup>,
     atmosphere_pipeline: wgpu::RenderPipeline,
-    atmosphere_bind_group_layout: wgpu::BindGroupLayout,
+    atmosphere_bind_group_layout: BindGroupLayout,
     atmosphere_bind_group: Option<wgpu::BindGroup>,
 }
 
 impl PlanetRenderer {
     pub fn new(device: &wgpu::Device, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack) -> Self {
-        let atmosphere_precompute_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let atmosphere_precompute_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -633,7 +634,7 @@ impl PlanetRenderer {
         });
 
         let atmosphere_precompute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[
                     wgpu::PushConstantRange {
@@ -644,7 +645,7 @@ impl PlanetRenderer {
                 bind_group_layouts: &[&atmosphere_precompute_bind_group_layout],
             });
 
-        let atmosphere_precompute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        let atmosphere_precompute_pipeline = atmosphere_precompute_pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
             label: None,
             layout: Some(&atmosphere_precompute_pipeline_layout),
             compute_stage: shader_module!(asset_pack, "assets/shader_modules/atmosphere_pre_comp"),
@@ -664,7 +665,7 @@ impl PlanetRenderer {
             anisotropy_clamp: None,
         });
 
-        let surface_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let surface_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -699,13 +700,13 @@ impl PlanetRenderer {
         });
 
         let surface_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[],
                 bind_group_layouts: &[&surface_bind_group_layout, &renderer.bind_group_layouts.camera_uniforms_bind_group_layout],
             });
 
-        let surface_pipeline = device.create_render_pipeline(&surface_pass_pipeline_descriptor!(
+        let surface_pipeline = surface_pipeline_layout.create_render_pipeline(device, asset_pack, &surface_pass_pipeline_descriptor!(
             asset_pack,
             surface_pipeline_layout,
             "assets/shader_modules/uv_sphere_billboard_vert",
@@ -722,7 +723,7 @@ impl PlanetRenderer {
             }
         ));
 
-        let atmosphere_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let atmosphere_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -747,7 +748,7 @@ impl PlanetRenderer {
         });
 
         let atmosphere_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[
                     wgpu::PushConstantRange {
@@ -763,7 +764,7 @@ impl PlanetRenderer {
             });
 
         let atmosphere_pipeline =
-            device.create_render_pipeline(&volumetric_pass_pipeline_descriptor!(
+            atmosphere_pipeline_layout.create_render_pipeline(device, asset_pack, &volumetric_pass_pipeline_descriptor!(
                 asset_pack, renderer,
                 atmosphere_pipeline_layout,
                 "assets/shader_modules/sphere_billboard_vert",
@@ -0,0 +1,113 @@
+use iced_wgpu::wgpu;
+
+/// Loads the shader modules, creates the renderer and then loads the assets of the given directories
+pub fn load_renderer(device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, swap_chain_descriptor: &wgpu::SwapChainDescriptor, render_options: &crate::renderer::RenderOptions, asset_directories: &[&str], profiler: &mut crate::profiler::Profiler) -> Result<(crate::assets::AssetPack, crate::assets::MipmapGenerator, crate::renderer::Renderer), crate::assets::AssetError> {
+    let mut asset_pack = crate::assets::AssetPack::default();
+    asset_pack.load_shader_modules(device)?;
+    let mipmap_generator = crate::assets::MipmapGenerator::new(device, &asset_pack);
+    let renderer = crate::renderer::Renderer::new(device, swap_chain_descriptor, render_options, &asset_pack);
+    let mut path_pool = crate::assets::AssetPack::create_path_pool();
+    for asset_directory in asset_directories {
+        crate::assets::AssetPack::collect_paths(&mut path_pool, &std::path::PathBuf::from(asset_directory));
+    }
+    asset_pack.load(device, queue, Some(&renderer.bind_group_layouts.surface_pass_bind_group_layout), Some(&renderer.sampler), true, &path_pool)?;
+    profiler.scope(encoder, "mipmaps", |encoder| asset_pack.generate_mipmaps(device, encoder, &mipmap_generator));
+    Ok((asset_pack, mipmap_generator, renderer))
+}
+
+/// Draw calls of the window which are not part of the planetary system, e.g. the selection and the clouds
+#[derive(Default)]
+pub struct AdditionalDrawCalls<'a> {
+    /// Drawn into the shadow maps and the surface pass
+    pub meshes: Vec<(&'a crate::assets::Mesh, std::ops::Range<u32>)>,
+    pub particles: Option<(&'a crate::particle::ParticleRenderer, &'a crate::particle::ParticleSystem, std::ops::Range<u32>)>,
+}
+
+/// Planet and star with their renderers and instances, shared by the window and the headless rendering
+pub struct PlanetarySystem {
+    pub planet_renderer: crate::planet::PlanetRenderer,
+    pub star_renderer: crate::star::StarRenderer,
+    pub planet_surface_instances: std::ops::Range<u32>,
+    pub planet_atmosphere_instances: std::ops::Range<u32>,
+    pub star_surface_instances: std::ops::Range<u32>,
+    pub star_atmosphere_instances: std::ops::Range<u32>,
+}
+
+impl PlanetarySystem {
+    /// Generates the terrain and atmosphere of the planet, the commands are recorded into the encoder
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack, scene: &mut crate::scene::Scene, planet: &mut crate::planet::Planet, star_position: glam::Vec3, star_radius: f32, profiler: &mut crate::profiler::Profiler) -> Self {
+        let mut planet_renderer = crate::planet::PlanetRenderer::new(device, renderer, asset_pack);
+        planet.generate_terrain_and_selection_texture(queue);
+        profiler.scope(encoder, "atmosphere precompute", |encoder| planet.generate_atmosphere(device, encoder, &planet_renderer));
+        planet_renderer.generate_bind_group(device, renderer, asset_pack, planet);
+        let star_renderer = crate::star::StarRenderer::new(device, renderer, asset_pack);
+        let planet_surface_instances = scene.allocate_instances(1);
+        let planet_atmosphere_instances = scene.allocate_instances(1);
+        let star_surface_instances = scene.allocate_instances(1);
+        scene.set_world_matrix(star_surface_instances.start, glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(star_radius), glam::Quat::identity(), star_position));
+        let star_atmosphere_instances = scene.allocate_instances(1);
+        scene.set_world_matrix(star_atmosphere_instances.start, glam::Mat4::from_scale_rotation_translation(glam::Vec3::splat(star_radius*1.75), glam::Quat::identity(), star_position));
+        let planetary_system = Self {
+            planet_renderer,
+            star_renderer,
+            planet_surface_instances,
+            planet_atmosphere_instances,
+            star_surface_instances,
+            star_atmosphere_instances,
+        };
+        planetary_system.update_instances(scene, planet);
+        planetary_system
+    }
+
+    /// Recreates the renderers and precomputes the atmosphere again, e.g. after their shaders were reloaded
+    pub fn recreate_renderers(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack, planet: &crate::planet::Planet, profiler: &mut crate::profiler::Profiler) {
+        self.planet_renderer = crate::planet::PlanetRenderer::new(device, renderer, asset_pack);
+        let planet_renderer = &self.planet_renderer;
+        profiler.scope(encoder, "atmosphere precompute", |encoder| planet.generate_atmosphere(device, encoder, planet_renderer));
+        self.planet_renderer.generate_bind_group(device, renderer, asset_pack, planet);
+        self.star_renderer = crate::star::StarRenderer::new(device, renderer, asset_pack);
+    }
+
+    pub fn update_instances(&self, scene: &mut crate::scene::Scene, planet: &crate::planet::Planet) {
+        scene.set_world_matrix(self.planet_surface_instances.start, glam::Mat4::from_scale(glam::Vec3::splat(planet.surface_radius())));
+        scene.set_world_matrix(self.planet_atmosphere_instances.start, glam::Mat4::from_scale(glam::Vec3::splat(planet.atmosphere_radius())));
+    }
+
+    /// Uploads the instances, lights and camera and records the shadow, surface and volumetric passes, each in a profiler scope.
+    /// The light and post processing passes follow with Renderer::render_frame.
+    pub fn render_scene(&self, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, renderer: &mut crate::renderer::Renderer, scene: &crate::scene::Scene, lights: &crate::light::Lights, camera: &crate::camera::Camera, additional_draw_calls: &AdditionalDrawCalls, profiler: &mut crate::profiler::Profiler) {
+        renderer.reserve_instances(device, scene.get_instance_count());
+        profiler.scope(encoder, "instance matrices", |encoder| renderer.update_instances(encoder, queue, scene.get_world_matrices()));
+        renderer.update_lights(device, queue, lights, camera);
+        profiler.scope(encoder, "mvp matrices", |encoder| renderer.update_camera(encoder, queue, scene.get_instance_count(), camera));
+        profiler.scope(encoder, "shadow mvp matrices", |encoder| renderer.update_shadow_maps(encoder, scene.get_instance_count()));
+        profiler.scope(encoder, "shadow maps", |encoder| {
+            for shadow_map_index in 0..renderer.get_shadow_map_count() {
+                let mut shadow_pass = renderer.render_shadow_pass(encoder, shadow_map_index);
+                shadow_pass.set_pipeline(&renderer.render_pipelines.general_shadow_pipeline);
+                for (mesh, instances) in additional_draw_calls.meshes.iter() {
+                    mesh.render_shadow(&mut shadow_pass, instances.clone());
+                }
+                renderer.render_sphere_shadows(&mut shadow_pass, self.planet_surface_instances.clone());
+                renderer.render_sphere_shadows(&mut shadow_pass, self.star_surface_instances.clone());
+            }
+        });
+        profiler.scope(encoder, "surface", |encoder| {
+            let mut surface_pass = renderer.render_surface_pass(encoder);
+            surface_pass.set_pipeline(&renderer.render_pipelines.surface_pass_pipeline);
+            for (mesh, instances) in additional_draw_calls.meshes.iter() {
+                mesh.render(&mut surface_pass, instances.clone());
+            }
+            self.star_renderer.render_surface(&mut surface_pass, self.star_surface_instances.clone());
+            self.planet_renderer.render_surface(&mut surface_pass, self.planet_surface_instances.clone());
+            if let Some((particle_renderer, particle_system, instances)) = &additional_draw_calls.particles {
+                particle_renderer.render_surface(&mut surface_pass, particle_system, instances.clone());
+            }
+        });
+        profiler.scope(encoder, "volumetric", |encoder| {
+            let mut volumetric_pass = renderer.render_volumetric_pass(encoder);
+            self.planet_renderer.render_atmosphere(&mut volumetric_pass, self.planet_atmosphere_instances.clone());
+            self.star_renderer.render_atmosphere(&mut volumetric_pass, self.star_atmosphere_instances.clone());
+        });
+    }
+}
@@ -0,0 +1,225 @@
+use iced_wgpu::wgpu;
+use serde::Serialize;
+
+const TRACE_DIRECTORY: &str = "traces";
+/// Frames kept for the trace export, about two seconds at 60 frames per second
+const RECORDED_FRAME_COUNT: usize = 120;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfilerMode {
+    Disabled,
+    /// Measures how long recording the commands of each scope takes on the CPU
+    Cpu,
+}
+
+impl ProfilerMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Disabled => Self::Cpu,
+            Self::Cpu => Self::Disabled,
+        }
+    }
+}
+
+/// Times are relative to the creation of the profiler
+#[derive(Clone, Debug)]
+pub struct ProfilerScope {
+    pub name: &'static str,
+    /// Recording of the commands
+    pub cpu: std::ops::Range<std::time::Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProfilerFrame {
+    pub time: std::ops::Range<std::time::Duration>,
+    pub scopes: Vec<ProfilerScope>,
+}
+
+#[derive(Serialize)]
+struct TraceEventArgs {
+    name: &'static str,
+}
+
+/// Event of the Chrome trace event format, times are in microseconds
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<TraceEventArgs>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+fn to_microseconds(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64()*1000000.0
+}
+
+/// Measures the CPU recording time of the passes and compute dispatches in named scopes, not how long the GPU executes them.
+/// GPU times need timestamp queries, which wgpu 0.6 does not have.
+/// The last frames can be exported as a Chrome trace, which chrome://tracing and Perfetto open.
+pub struct Profiler {
+    pub mode: ProfilerMode,
+    epoch: std::time::Instant,
+    frame_start: std::time::Duration,
+    scopes: Vec<ProfilerScope>,
+    frames: std::collections::VecDeque<ProfilerFrame>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            mode: ProfilerMode::Disabled,
+            epoch: std::time::Instant::now(),
+            frame_start: std::time::Duration::default(),
+            scopes: Vec::new(),
+            frames: std::collections::VecDeque::new(),
+        }
+    }
+}
+
+impl Profiler {
+    pub fn begin_frame(&mut self) {
+        self.frame_start = self.epoch.elapsed();
+        self.scopes.clear();
+    }
+
+    /// Has to be called after the frame was submitted
+    pub fn end_frame(&mut self) {
+        if self.mode == ProfilerMode::Disabled {
+            return;
+        }
+        if self.frames.len() == RECORDED_FRAME_COUNT {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(ProfilerFrame {
+            time: self.frame_start..self.epoch.elapsed(),
+            scopes: std::mem::take(&mut self.scopes),
+        });
+    }
+
+    /// Measures how long record takes to record its commands into the encoder
+    pub fn scope<R, F: FnOnce(&mut wgpu::CommandEncoder) -> R>(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str, record: F) -> R {
+        if self.mode == ProfilerMode::Disabled {
+            return record(encoder);
+        }
+        let cpu_start = self.epoch.elapsed();
+        let result = record(encoder);
+        self.scopes.push(ProfilerScope {
+            name,
+            cpu: cpu_start..self.epoch.elapsed(),
+        });
+        result
+    }
+
+    pub fn get_last_frame(&self) -> Option<&ProfilerFrame> {
+        self.frames.back()
+    }
+
+    /// Frames on the first thread, the CPU recording times of the scopes on the second
+    fn to_chrome_trace(&self) -> Trace {
+        let mut trace_events: Vec<TraceEvent> = ["Frames", "CPU recording"].iter().cloned().enumerate().map(|(tid, name)| TraceEvent {
+            name: "thread_name",
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid: 0,
+            tid: tid as u32,
+            args: Some(TraceEventArgs { name }),
+        }).collect();
+        let complete_event = |name: &'static str, tid: u32, time: &std::ops::Range<std::time::Duration>| TraceEvent {
+            name,
+            ph: "X",
+            ts: Some(to_microseconds(time.start)),
+            dur: Some(to_microseconds(time.end-time.start)),
+            pid: 0,
+            tid,
+            args: None,
+        };
+        for frame in self.frames.iter() {
+            trace_events.push(complete_event("frame", 0, &frame.time));
+            for scope in frame.scopes.iter() {
+                trace_events.push(complete_event(scope.name, 1, &scope.cpu));
+            }
+        }
+        Trace {
+            trace_events,
+            display_time_unit: "ms",
+        }
+    }
+
+    /// Writes the recorded frames to a JSON file in the Chrome trace event format and returns its path
+    pub fn save_chrome_trace(&self) -> Result<std::path::PathBuf, crate::assets::AssetError> {
+        let directory = std::path::Path::new(TRACE_DIRECTORY);
+        std::fs::create_dir_all(directory)?;
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+        let path = directory.join(format!("{}_trace.json", timestamp));
+        self.write_chrome_trace(&path)?;
+        Ok(path)
+    }
+
+    pub fn write_chrome_trace(&self, path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(writer, &self.to_chrome_trace())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milliseconds(start: u64, end: u64) -> std::ops::Range<std::time::Duration> {
+        std::time::Duration::from_millis(start)..std::time::Duration::from_millis(end)
+    }
+
+    #[test]
+    fn chrome_trace() {
+        let mut profiler = Profiler::default();
+        profiler.frames.push_back(ProfilerFrame {
+            time: milliseconds(10, 26),
+            scopes: vec![
+                ProfilerScope { name: "instance matrices", cpu: milliseconds(10, 11) },
+                ProfilerScope { name: "light volumes", cpu: milliseconds(13, 14) },
+            ],
+        });
+        let trace = serde_json::to_value(profiler.to_chrome_trace()).unwrap();
+        assert_eq!(trace["displayTimeUnit"], "ms");
+        let events = trace["traceEvents"].as_array().unwrap();
+        // Names of the two threads, the frame and two scope events
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0], serde_json::json!({ "name": "thread_name", "ph": "M", "pid": 0, "tid": 0, "args": { "name": "Frames" } }));
+        assert_eq!(events[1], serde_json::json!({ "name": "thread_name", "ph": "M", "pid": 0, "tid": 1, "args": { "name": "CPU recording" } }));
+        assert_eq!(events[2], serde_json::json!({ "name": "frame", "ph": "X", "ts": 10000.0, "dur": 16000.0, "pid": 0, "tid": 0 }));
+        assert_eq!(events[3], serde_json::json!({ "name": "instance matrices", "ph": "X", "ts": 10000.0, "dur": 1000.0, "pid": 0, "tid": 1 }));
+        assert_eq!(events[4]["name"], "light volumes");
+        assert_eq!(events[4]["tid"], 1);
+    }
+
+    #[test]
+    fn keeps_the_last_frames() {
+        let mut profiler = Profiler::default();
+        assert!(profiler.get_last_frame().is_none());
+        profiler.begin_frame();
+        profiler.end_frame();
+        assert!(profiler.get_last_frame().is_none(), "Disabled profilers do not record frames");
+        profiler.mode = ProfilerMode::Cpu;
+        for _ in 0..RECORDED_FRAME_COUNT+5 {
+            profiler.begin_frame();
+            profiler.end_frame();
+        }
+        assert_eq!(profiler.frames.len(), RECORDED_FRAME_COUNT);
+        assert!(profiler.get_last_frame().is_some());
+    }
+}
@@ -0,0 +1,409 @@
+use iced_wgpu::wgpu;
+
+/// Extent of an attachment, most of them follow the size of the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachmentSize {
+    Frame,
+    /// The frame size divided by the factor, at least one pixel
+    Divided(u32),
+    Fixed(wgpu::Extent3d),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AttachmentDescriptor {
+    pub format: wgpu::TextureFormat,
+    pub size: AttachmentSize,
+    /// Clamped to the number of levels the extent has
+    pub mip_level_count: u32,
+    pub usage: wgpu::TextureUsage,
+    /// Keeps its content across frames or is read after the frame, so it is never aliased
+    pub persistent: bool,
+}
+
+impl AttachmentDescriptor {
+    pub fn new(format: wgpu::TextureFormat, size: AttachmentSize) -> Self {
+        Self {
+            format,
+            size,
+            mip_level_count: 1,
+            usage: wgpu::TextureUsage::SAMPLED|wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            persistent: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttachmentHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PassHandle(usize);
+
+/// Records the commands of a pass, the context is the owner of the graph (e.g. the renderer)
+/// and frame_view is the view which the frame ends up in
+pub type ExecuteFn<C> = fn(&C, &mut wgpu::CommandEncoder, &wgpu::TextureView);
+
+struct Pass<C> {
+    /// Identifies the pass, e.g. in profiler scopes
+    name: &'static str,
+    reads: Vec<AttachmentHandle>,
+    writes: Vec<AttachmentHandle>,
+    enabled: bool,
+    /// None if the caller records the pass before executing the graph, e.g. the ones which draw the scene
+    execute: Option<ExecuteFn<C>>,
+}
+
+/// Textures which are equal in all of these can be shared by attachments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TextureKey {
+    format: wgpu::TextureFormat,
+    extent: wgpu::Extent3d,
+    mip_level_count: u32,
+    usage: wgpu::TextureUsage,
+}
+
+struct PhysicalTexture {
+    key: TextureKey,
+    /// Identifies the texture across compilations, to detect which attachments changed
+    id: usize,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+/// Passes declare which attachments they read and write, in the order in which they are executed.
+/// Compiling allocates the textures of the attachments which enabled passes use,
+/// attachments whose lifetimes do not overlap share a texture.
+pub struct RenderGraph<C> {
+    attachments: Vec<AttachmentDescriptor>,
+    passes: Vec<Pass<C>>,
+    frame_size: wgpu::Extent3d,
+    textures: Vec<PhysicalTexture>,
+    /// Index into textures, None if no enabled pass uses the attachment
+    attachment_textures: Vec<Option<usize>>,
+    next_texture_id: usize,
+    dirty: bool,
+}
+
+impl<C> RenderGraph<C> {
+    pub fn new(frame_size: wgpu::Extent3d) -> Self {
+        Self {
+            attachments: Vec::new(),
+            passes: Vec::new(),
+            frame_size,
+            textures: Vec::new(),
+            attachment_textures: Vec::new(),
+            next_texture_id: 0,
+            dirty: true,
+        }
+    }
+
+    pub fn add_attachment(&mut self, descriptor: AttachmentDescriptor) -> AttachmentHandle {
+        self.attachments.push(descriptor);
+        self.attachment_textures.push(None);
+        self.dirty = true;
+        AttachmentHandle(self.attachments.len()-1)
+    }
+
+    fn push_pass(&mut self, name: &'static str, reads: &[AttachmentHandle], writes: &[AttachmentHandle], execute: Option<ExecuteFn<C>>) -> PassHandle {
+        self.passes.push(Pass {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            enabled: true,
+            execute,
+        });
+        self.dirty = true;
+        PassHandle(self.passes.len()-1)
+    }
+
+    /// Passes are executed in the order in which they are added
+    pub fn add_pass(&mut self, name: &'static str, reads: &[AttachmentHandle], writes: &[AttachmentHandle], execute: ExecuteFn<C>) -> PassHandle {
+        self.push_pass(name, reads, writes, Some(execute))
+    }
+
+    /// Pass which the caller records before executing the graph, it only takes part in the allocation of the attachments
+    pub fn add_recorded_pass(&mut self, name: &'static str, reads: &[AttachmentHandle], writes: &[AttachmentHandle]) -> PassHandle {
+        self.push_pass(name, reads, writes, None)
+    }
+
+    /// Declares the attachments anew, e.g. when the render options change which of them a pass uses
+    pub fn set_pass(&mut self, pass: PassHandle, enabled: bool, reads: &[AttachmentHandle], writes: &[AttachmentHandle]) {
+        let pass = &mut self.passes[pass.0];
+        if pass.enabled != enabled || pass.reads != reads || pass.writes != writes {
+            pass.enabled = enabled;
+            pass.reads = reads.to_vec();
+            pass.writes = writes.to_vec();
+            self.dirty = true;
+        }
+    }
+
+    pub fn is_pass_enabled(&self, pass: PassHandle) -> bool {
+        self.passes[pass.0].enabled
+    }
+
+    #[cfg(test)]
+    pub fn get_pass_name(&self, pass: PassHandle) -> &'static str {
+        self.passes[pass.0].name
+    }
+
+    /// Hands the name and execute function of each enabled pass, which the caller does not record itself, in order to record.
+    /// This way the caller can wrap each pass, e.g. in a profiler scope. The graph has to be compiled first.
+    pub fn execute<F: FnMut(&'static str, ExecuteFn<C>)>(&self, mut record: F) {
+        assert!(!self.dirty, "Render graph has to be compiled before it is executed");
+        for pass in self.passes.iter().filter(|pass| pass.enabled) {
+            if let Some(execute) = pass.execute {
+                record(pass.name, execute);
+            }
+        }
+    }
+
+    pub fn resize(&mut self, frame_size: wgpu::Extent3d) {
+        if self.frame_size != frame_size {
+            self.frame_size = frame_size;
+            self.dirty = true;
+        }
+    }
+
+    pub fn get_frame_size(&self) -> wgpu::Extent3d {
+        self.frame_size
+    }
+
+    fn get_texture_key(&self, descriptor: &AttachmentDescriptor) -> TextureKey {
+        let extent = match descriptor.size {
+            AttachmentSize::Frame => self.frame_size,
+            AttachmentSize::Divided(factor) => wgpu::Extent3d {
+                width: (self.frame_size.width/factor).max(1),
+                height: (self.frame_size.height/factor).max(1),
+                depth: 1,
+            },
+            AttachmentSize::Fixed(extent) => extent,
+        };
+        TextureKey {
+            format: descriptor.format,
+            extent,
+            mip_level_count: descriptor.mip_level_count.min(32-extent.width.min(extent.height).leading_zeros()).max(1),
+            usage: descriptor.usage,
+        }
+    }
+
+    /// First and last index of the enabled passes which use each attachment
+    fn get_lifetimes(&self) -> Vec<Option<(usize, usize)>> {
+        let mut lifetimes = vec![None; self.attachments.len()];
+        for (pass_index, pass) in self.passes.iter().enumerate().filter(|(_, pass)| pass.enabled) {
+            for attachment in pass.reads.iter().chain(pass.writes.iter()) {
+                let lifetime: &mut Option<(usize, usize)> = &mut lifetimes[attachment.0];
+                *lifetime = Some(lifetime.map_or((pass_index, pass_index), |(first, _)| (first, pass_index)));
+            }
+        }
+        for (lifetime, _descriptor) in lifetimes.iter_mut().zip(self.attachments.iter()).filter(|(_, descriptor)| descriptor.persistent) {
+            *lifetime = lifetime.map(|_| (0, std::usize::MAX));
+        }
+        lifetimes
+    }
+
+    /// Returns the texture key of each slot and the slot of each attachment, None if no enabled pass uses the attachment
+    fn assign_slots(&self, lifetimes: &[Option<(usize, usize)>]) -> (Vec<TextureKey>, Vec<Option<usize>>) {
+        let mut order: Vec<usize> = (0..self.attachments.len()).filter(|index| lifetimes[*index].is_some()).collect();
+        order.sort_by_key(|index| lifetimes[*index].unwrap().0);
+        // Assign the attachments to slots, a slot is free again after the last pass which uses it
+        let mut slots: Vec<(TextureKey, usize)> = Vec::new();
+        let mut attachment_slots = vec![None; self.attachments.len()];
+        for attachment_index in order {
+            let key = self.get_texture_key(&self.attachments[attachment_index]);
+            let (first, last) = lifetimes[attachment_index].unwrap();
+            let slot_index = match slots.iter().position(|(slot_key, slot_last)| *slot_key == key && *slot_last < first) {
+                Some(slot_index) => slot_index,
+                None => {
+                    slots.push((key, 0));
+                    slots.len()-1
+                },
+            };
+            slots[slot_index].1 = last;
+            attachment_slots[attachment_index] = Some(slot_index);
+        }
+        (slots.into_iter().map(|(key, _last)| key).collect(), attachment_slots)
+    }
+
+    /// Returns true if any attachment got a different texture, then bind groups which use them have to be recreated.
+    /// Textures of the previous compilation are reused where possible, so resizing only replaces the ones which depend on the frame size.
+    pub fn compile(&mut self, device: &wgpu::Device) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+        let lifetimes = self.get_lifetimes();
+        let (slots, attachment_slots) = self.assign_slots(&lifetimes);
+        // Take the textures of the slots from the previous compilation first
+        let mut previous_textures: Vec<Option<PhysicalTexture>> = self.textures.drain(..).map(Some).collect();
+        let previous_attachment_ids: Vec<Option<usize>> = self.attachment_textures.iter().map(|texture_index| texture_index.map(|texture_index| previous_textures[texture_index].as_ref().unwrap().id)).collect();
+        let mut textures = Vec::with_capacity(slots.len());
+        for key in slots.iter() {
+            let reusable = previous_textures.iter().position(|texture| texture.as_ref().map_or(false, |texture| texture.key == *key));
+            textures.push(match reusable {
+                Some(index) => previous_textures[index].take().unwrap(),
+                None => {
+                    let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: None,
+                        size: key.extent,
+                        mip_level_count: key.mip_level_count,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: key.format,
+                        usage: key.usage,
+                    });
+                    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                    self.next_texture_id += 1;
+                    PhysicalTexture { key: *key, id: self.next_texture_id, texture, view }
+                },
+            });
+        }
+        self.textures = textures;
+        self.attachment_textures = attachment_slots;
+        self.attachment_textures.iter().zip(previous_attachment_ids.iter()).any(|(texture_index, previous_id)| {
+            texture_index.map(|texture_index| self.textures[texture_index].id) != *previous_id
+        })
+    }
+
+    pub fn is_attachment_used(&self, attachment: AttachmentHandle) -> bool {
+        self.attachment_textures[attachment.0].is_some()
+    }
+
+    /// Panics if no enabled pass uses the attachment
+    fn get_physical_texture(&self, attachment: AttachmentHandle) -> &PhysicalTexture {
+        &self.textures[self.attachment_textures[attachment.0].expect("Attachment is not used by any enabled pass")]
+    }
+
+    pub fn get_texture(&self, attachment: AttachmentHandle) -> &wgpu::Texture {
+        &self.get_physical_texture(attachment).texture
+    }
+
+    /// View of all mip levels and aspects
+    pub fn get_view(&self, attachment: AttachmentHandle) -> &wgpu::TextureView {
+        &self.get_physical_texture(attachment).view
+    }
+
+    pub fn get_extent(&self, attachment: AttachmentHandle) -> wgpu::Extent3d {
+        self.get_physical_texture(attachment).key.extent
+    }
+
+    pub fn get_mip_level_count(&self, attachment: AttachmentHandle) -> u32 {
+        self.get_physical_texture(attachment).key.mip_level_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_size() -> wgpu::Extent3d {
+        wgpu::Extent3d { width: 100, height: 60, depth: 1 }
+    }
+
+    fn add_color_attachment(graph: &mut RenderGraph<()>, format: wgpu::TextureFormat, persistent: bool) -> AttachmentHandle {
+        let mut descriptor = AttachmentDescriptor::new(format, AttachmentSize::Frame);
+        descriptor.persistent = persistent;
+        graph.add_attachment(descriptor)
+    }
+
+    fn skip(_context: &(), _encoder: &mut wgpu::CommandEncoder, _frame_view: &wgpu::TextureView) {}
+
+    #[test]
+    fn lifetimes_span_the_enabled_passes() {
+        let mut graph = RenderGraph::new(frame_size());
+        let a = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let b = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let c = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let unused = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        graph.add_recorded_pass("first", &[], &[a]);
+        let middle = graph.add_pass("middle", &[a], &[b], skip);
+        graph.add_pass("last", &[b], &[c], skip);
+        assert_eq!(graph.get_lifetimes(), vec![Some((0, 1)), Some((1, 2)), Some((2, 2)), None]);
+        graph.set_pass(middle, false, &[a], &[b]);
+        assert_eq!(graph.get_lifetimes(), vec![Some((0, 0)), Some((2, 2)), Some((2, 2)), None]);
+        graph.set_pass(middle, true, &[a], &[unused]);
+        assert_eq!(graph.get_lifetimes(), vec![Some((0, 1)), Some((2, 2)), Some((2, 2)), Some((1, 1))]);
+    }
+
+    #[test]
+    fn persistent_attachments_live_for_the_entire_frame_if_used() {
+        let mut graph = RenderGraph::new(frame_size());
+        let history = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, true);
+        let _unused_history = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, true);
+        let color = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, false);
+        graph.add_recorded_pass("color", &[], &[color]);
+        graph.add_pass("history", &[color], &[history], skip);
+        assert_eq!(graph.get_lifetimes(), vec![Some((0, std::usize::MAX)), None, Some((0, 1))]);
+    }
+
+    #[test]
+    fn slots_are_shared_by_disjoint_lifetimes_of_equal_textures() {
+        let mut graph = RenderGraph::new(frame_size());
+        let a = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let b = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let c = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let other_format = add_color_attachment(&mut graph, wgpu::TextureFormat::R8Unorm, false);
+        let d = add_color_attachment(&mut graph, wgpu::TextureFormat::R8Unorm, false);
+        let unused = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        graph.add_recorded_pass("first", &[], &[a, other_format]);
+        graph.add_pass("second", &[a], &[b], skip);
+        graph.add_pass("third", &[b], &[c], skip);
+        graph.add_pass("fourth", &[c], &[d], skip);
+        let (slots, attachment_slots) = graph.assign_slots(&graph.get_lifetimes());
+        // a ends in pass 1, so c which starts in pass 2 can take its slot, b overlaps both
+        assert_eq!(attachment_slots[a.0], attachment_slots[c.0]);
+        assert_ne!(attachment_slots[a.0], attachment_slots[b.0]);
+        // Attachments of other formats never share a slot with them
+        assert_eq!(attachment_slots[other_format.0], attachment_slots[d.0]);
+        assert_ne!(attachment_slots[other_format.0], attachment_slots[a.0]);
+        assert_eq!(attachment_slots[unused.0], None);
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[attachment_slots[a.0].unwrap()].format, wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(slots[attachment_slots[d.0].unwrap()].format, wgpu::TextureFormat::R8Unorm);
+    }
+
+    #[test]
+    fn persistent_attachments_get_their_own_slot() {
+        let mut graph = RenderGraph::new(frame_size());
+        let history = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, true);
+        let a = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, false);
+        let b = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba16Float, false);
+        graph.add_recorded_pass("color", &[], &[a]);
+        graph.add_pass("history", &[a], &[history], skip);
+        graph.add_pass("next", &[history], &[b], skip);
+        let (slots, attachment_slots) = graph.assign_slots(&graph.get_lifetimes());
+        assert_eq!(slots.len(), 2);
+        assert_ne!(attachment_slots[history.0], attachment_slots[a.0]);
+        assert_ne!(attachment_slots[history.0], attachment_slots[b.0]);
+        assert_eq!(attachment_slots[a.0], attachment_slots[b.0]);
+    }
+
+    #[test]
+    fn execute_hands_over_the_enabled_passes_in_order() {
+        let mut graph = RenderGraph::new(frame_size());
+        let a = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        let b = add_color_attachment(&mut graph, wgpu::TextureFormat::Rgba8Unorm, false);
+        graph.add_recorded_pass("surface", &[], &[a]);
+        let disabled = graph.add_pass("disabled", &[a], &[b], skip);
+        graph.add_pass("light volumes", &[a], &[b], skip);
+        graph.add_pass("post processing", &[b], &[], skip);
+        graph.set_pass(disabled, false, &[a], &[b]);
+        // Compiling needs a device, the textures are not used here
+        graph.dirty = false;
+        let mut names = Vec::new();
+        graph.execute(|name, _execute| names.push(name));
+        assert_eq!(names, vec!["light volumes", "post processing"]);
+        assert_eq!(graph.get_pass_name(disabled), "disabled");
+    }
+
+    #[test]
+    fn divided_sizes_clamp_the_mip_levels() {
+        let mut graph: RenderGraph<()> = RenderGraph::new(frame_size());
+        let mut descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float, AttachmentSize::Divided(2));
+        descriptor.mip_level_count = 8;
+        let bloom = graph.add_attachment(descriptor);
+        graph.add_recorded_pass("bloom", &[], &[bloom]);
+        let (slots, _attachment_slots) = graph.assign_slots(&graph.get_lifetimes());
+        assert_eq!(slots[0].extent, wgpu::Extent3d { width: 50, height: 30, depth: 1 });
+        // 30 pixels have the levels 30, 15, 7, 3 and 1
+        assert_eq!(slots[0].mip_level_count, 5);
+    }
+}
@@ -1,5 +1,7 @@
-use iced_wgpu::{wgpu, wgpu::vertex_attr_array};
+use iced_wgpu::{wgpu, wgpu::vertex_attr_array, wgpu::util::DeviceExt};
 use crate::assets::AssetPack;
+use crate::pipeline_layout::{BindGroupLayout, PipelineLayout, PipelineLayoutDescriptor};
+use crate::render_graph::{AttachmentDescriptor, AttachmentHandle, AttachmentSize, PassHandle, RenderGraph};
 
 macro_rules! bind_group_descriptor {
     ($layout:expr, $($loc:expr => $t:ident($e:expr)),* $(,)?) => {
@@ -189,15 +191,15 @@ macro_rules! surface_pass_pipeline_descriptor {
             }),
             primitive_topology: wgpu::PrimitiveTopology :: $primitive_topology,
             color_states: &[
-                color_state_descriptor_blend_none!(Rgba32Float), // Position
-                color_state_descriptor_blend_none!(Rgba16Sint), // Normal
-                color_state_descriptor_blend_none!(Rgba16Float), // Albedo
+                color_state_descriptor_blend_none!(R32Uint), // Instance
+                color_state_descriptor_blend_none!(Rg16Sint), // Normal
+                color_state_descriptor_blend_none!(Rgba8UnormSrgb), // Albedo
                 color_state_descriptor_blend_none!(Rgba8Unorm), // Material
             ],
             depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
                 format: wgpu::TextureFormat::Depth24PlusStencil8,
                 depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
+                depth_compare: wgpu::CompareFunction::Greater,
                 stencil: stencil_none!(),
             }),
             vertex_state: $vertex_state,
@@ -221,7 +223,7 @@ macro_rules! volumetric_pass_pipeline_descriptor {
             depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
                 format: wgpu::TextureFormat::Depth24PlusStencil8,
                 depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::Less,
+                depth_compare: wgpu::CompareFunction::Greater,
                 stencil: stencil_none!(),
             }),
             vertex_state: wgpu::VertexStateDescriptor {
@@ -246,8 +248,8 @@ macro_rules! align_to {
 }
 
 macro_rules! shader_module {
-    ($asset_pack:expr, $name:expr) => {wgpu::ProgrammableStageDescriptor {
-        module: &$asset_pack.shader_modules[&std::path::PathBuf::from($name)],
+    ($asset_pack:expr, $name:expr $(, $define:expr)* $(,)?) => {wgpu::ProgrammableStageDescriptor {
+        module: $asset_pack.get_shader_module($name, &[$($define),*]),
         entry_point: "main",
     }}
 }
@@ -260,6 +262,7 @@ struct CameraUniforms {
     world_matrix: glam::Mat4,
     projection_matrix: glam::Mat4,
     inverse_view_matrix: glam::Mat4,
+    view_matrix: glam::Mat4,
 }
 
 #[repr(C)]
@@ -268,6 +271,24 @@ struct LightAttributes {
     color: glam::Vec3,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct ParallelLightAttributes {
+    color: glam::Vec3,
+    shadow_map_index: i32,
+    shadow_cascade_count: i32,
+}
+
+/// Matches the std430 layout of ShadowMapSettings in light_shared.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct ShadowMapAttributes {
+    view_matrix: glam::Mat4,
+    split_far: f32,
+    blend_distance: f32,
+    padding: [f32; 2],
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
 struct SpotLightAttributes {
@@ -275,82 +296,389 @@ struct SpotLightAttributes {
     radius: f32,
     outer_angle_cos: f32,
     inner_angle_cos: f32,
+    shadow_map_index: i32,
+}
+
+
+
+/// Matches the push constants of post_processing_pass_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct PostProcessingSettings {
+    tonemapping: i32,
+    exposure: f32,
+    enable_auto_exposure: i32,
+    bloom_intensity: f32,
+}
+
+/// Matches the push constants of debug_view_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct DebugViewSettings {
+    debug_view: i32,
+}
+
+/// Matches the push constants of ambient_occlusion_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct AmbientOcclusionSettings {
+    radius: f32,
+    intensity: f32,
+    sample_count: i32,
+}
+
+/// Matches the push constants of ambient_occlusion_blur_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct AmbientOcclusionBlurSettings {
+    direction: [i32; 2],
+    blur_radius: i32,
+    inverse_radius: f32,
+}
+
+/// Matches the push constants of motion_vector_frag.glsl, the jitters are in texture coordinates
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct MotionVectorSettings {
+    jitter: [f32; 2],
+    previous_jitter: [f32; 2],
 }
 
+/// Matches the push constants of temporal_anti_aliasing_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct TemporalAntiAliasingSettings {
+    blend_factor: f32,
+}
+
+/// Matches the push constants of luminance_histogram_comp.glsl and exposure_adaptation_comp.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct AutoExposureSettings {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_factor: f32,
+    pixel_count: u32,
+}
+
+/// Matches the push constants of bloom_downsample_frag.glsl and bloom_upsample_frag.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct BloomPassSettings {
+    inverse_target_size: [f32; 2],
+    threshold: f32,
+    knee: f32,
+    prefilter: i32,
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    Fxaa,
+    /// Jitters the camera and accumulates the frames, which are reprojected by motion vectors
+    Temporal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemapping {
+    None,
+    Reinhard,
+    Aces,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmbientOcclusionQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl AmbientOcclusionQuality {
+    fn get_sample_count(self) -> i32 {
+        match self {
+            Self::Off => 0,
+            Self::Low => 8,
+            Self::Medium => 16,
+            Self::High => 32,
+        }
+    }
+
+    /// In pixels, the blur is separated into a horizontal and a vertical pass
+    fn get_blur_radius(self) -> i32 {
+        match self {
+            Self::Off => 0,
+            Self::Low => 2,
+            Self::Medium => 4,
+            Self::High => 6,
+        }
+    }
+}
+
+/// Replaces the final composite with a single channel of the GBuffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    None,
+    /// Reconstructed from the depth
+    Position,
+    /// Decoded from the octahedral encoding of the GBuffer
+    Normal,
+    Albedo,
+    Material,
+    LinearDepth,
+    Stencil,
+    /// Number of light volumes covering each pixel, the lights are not shaded in this mode
+    LightOverdraw,
+    /// Decoded normals on the left and normals of the reconstructed positions on the right, to validate both.
+    /// Positions and depths can not be compared, as the positions are reconstructed from the only stored depth.
+    GBufferComparison,
+}
 
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Position,
+            Self::Position => Self::Normal,
+            Self::Normal => Self::Albedo,
+            Self::Albedo => Self::Material,
+            Self::Material => Self::LinearDepth,
+            Self::LinearDepth => Self::Stencil,
+            Self::Stencil => Self::LightOverdraw,
+            Self::LightOverdraw => Self::GBufferComparison,
+            Self::GBufferComparison => Self::None,
+        }
+    }
+}
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct RenderOptions {
     pub scale_factor: f32,
     pub enable_frustum_culling: bool,
     pub enable_occulsion_culling: bool,
     pub enable_shadow_mapping: bool,
+    pub ambient_occlusion_quality: AmbientOcclusionQuality,
+    /// World space distance up to which surfaces occlude each other
+    pub ambient_occlusion_radius: f32,
+    pub ambient_occlusion_intensity: f32,
+    pub anti_aliasing: AntiAliasing,
+    pub tonemapping: Tonemapping,
+    /// Exposure compensation in EV if auto exposure is enabled, otherwise the absolute exposure
+    pub exposure: f32,
+    pub enable_auto_exposure: bool,
+    /// Luminance range of the histogram in EV, luminances outside of it are clamped
+    pub auto_exposure_min_ev: f32,
+    pub auto_exposure_max_ev: f32,
+    /// How fast the exposure adapts to a change of the luminance, per second
+    pub auto_exposure_adaptation_rate: f32,
+    pub enable_bloom: bool,
+    /// Brightness in the HDR color buffer (before the exposure) above which pixels glow
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub debug_view: DebugView,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            scale_factor: 1.0,
+            enable_frustum_culling: false,
+            enable_occulsion_culling: false,
+            enable_shadow_mapping: true,
+            ambient_occlusion_quality: AmbientOcclusionQuality::Medium,
+            ambient_occlusion_radius: 0.5,
+            ambient_occlusion_intensity: 1.5,
+            anti_aliasing: AntiAliasing::Fxaa,
+            tonemapping: Tonemapping::Aces,
+            exposure: 0.0,
+            enable_auto_exposure: true,
+            auto_exposure_min_ev: -8.0,
+            auto_exposure_max_ev: 4.0,
+            auto_exposure_adaptation_rate: 1.5,
+            enable_bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.1,
+            debug_view: DebugView::None,
+        }
+    }
+}
+
+/// Attachments of the GBuffer which can be captured for debugging
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GBufferAttachment {
+    Position,
+    Normal,
+    Albedo,
+    Material,
+    Color,
+    Depth,
+}
+
+impl GBufferAttachment {
+    pub const ALL: [Self; 6] = [Self::Position, Self::Normal, Self::Albedo, Self::Material, Self::Color, Self::Depth];
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Self::Position => "position",
+            Self::Normal => "normal",
+            Self::Albedo => "albedo",
+            Self::Material => "material",
+            Self::Color => "color",
+            Self::Depth => "depth",
+        }
+    }
+
+    /// Format of the texture of Renderer::capture_gbuffer_attachment
+    pub fn get_format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Position => wgpu::TextureFormat::Rgba32Float,
+            Self::Normal => wgpu::TextureFormat::Rg16Sint,
+            Self::Albedo => wgpu::TextureFormat::Rgba8UnormSrgb,
+            Self::Material => wgpu::TextureFormat::Rgba8Unorm,
+            Self::Color => wgpu::TextureFormat::Rgba16Float,
+            Self::Depth => wgpu::TextureFormat::R32Float,
+        }
+    }
+}
+
+/// Texture of Renderer::capture_gbuffer_attachment, either the attachment itself or a converted copy of it
+pub enum CapturedAttachment<'a> {
+    GBuffer(&'a wgpu::Texture),
+    Converted(wgpu::Texture),
+}
+
+impl std::ops::Deref for CapturedAttachment<'_> {
+    type Target = wgpu::Texture;
+
+    fn deref(&self) -> &wgpu::Texture {
+        match self {
+            Self::GBuffer(texture) => texture,
+            Self::Converted(texture) => texture,
+        }
+    }
+}
+
+/// Instance slots of the light volumes, which were uploaded last
+#[derive(Default)]
+struct LightInstances {
+    parallel_lights: std::ops::Range<u32>,
+    point_lights: std::ops::Range<u32>,
+    spot_lights: std::ops::Range<u32>,
 }
 
 struct AttributeAndUniformBuffers {
     camera_uniforms_buffer: wgpu::Buffer,
+    previous_camera_uniforms_buffer: wgpu::Buffer,
+    instance_capacity: usize,
     instances_world_matrix_buffer: wgpu::Buffer,
+    instances_previous_world_matrix_buffer: wgpu::Buffer,
     instances_inverse_world_matrix_buffer: wgpu::Buffer,
     instances_mvp_matrix_buffer: wgpu::Buffer,
     light_settings_buffer: wgpu::Buffer,
+    luminance_histogram_buffer: wgpu::Buffer,
+    exposure_buffer: wgpu::Buffer,
+}
+
+const LUMINANCE_HISTOGRAM_BIN_COUNT: usize = 256;
+
+fn create_instance_buffer(device: &wgpu::Device, size: usize, usage: wgpu::BufferUsage) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: size as wgpu::BufferAddress,
+        usage,
+        mapped_at_creation: false,
+    })
+}
+
+/// The device does not initialize buffers, so buffers which are read before they are first written have to be zeroed
+fn create_zeroed_buffer(device: &wgpu::Device, size: usize, usage: wgpu::BufferUsage) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: &vec![0; size],
+        usage,
+    })
 }
 
 impl AttributeAndUniformBuffers {
+    const INITIAL_INSTANCE_CAPACITY: usize = 16;
+
     fn new(device: &wgpu::Device) -> Self {
         let camera_uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
             size: std::mem::size_of::<CameraUniforms>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::UNIFORM,
-            mapped_at_creation: false,
-        });
-
-        const MAX_INSTANCES: usize = 16;
-
-        let instances_world_matrix_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<[glam::Mat4; MAX_INSTANCES]>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX,
-            mapped_at_creation: false,
-        });
-
-        let instances_inverse_world_matrix_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<[glam::Mat4; MAX_INSTANCES]>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX,
+            usage: wgpu::BufferUsage::COPY_SRC | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::UNIFORM,
             mapped_at_creation: false,
         });
+        let instance_capacity = Self::INITIAL_INSTANCE_CAPACITY;
+        Self {
+            camera_uniforms_buffer,
+            previous_camera_uniforms_buffer: create_instance_buffer(device, std::mem::size_of::<CameraUniforms>(), wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::UNIFORM),
+            instance_capacity,
+            instances_world_matrix_buffer: create_zeroed_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_SRC|wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX),
+            instances_previous_world_matrix_buffer: create_zeroed_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_DST),
+            instances_inverse_world_matrix_buffer: create_instance_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX),
+            instances_mvp_matrix_buffer: create_instance_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX),
+            light_settings_buffer: create_instance_buffer(device, Self::light_settings_size(instance_capacity), wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX),
+            luminance_histogram_buffer: create_zeroed_buffer(device, std::mem::size_of::<u32>()*LUMINANCE_HISTOGRAM_BIN_COUNT, wgpu::BufferUsage::STORAGE),
+            // Adapted average luminance, starts at zero which makes the first frame adapt instantly
+            exposure_buffer: create_zeroed_buffer(device, std::mem::size_of::<f32>(), wgpu::BufferUsage::STORAGE),
+        }
+    }
 
-        let instances_mvp_matrix_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<[glam::Mat4; MAX_INSTANCES]>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX,
-            mapped_at_creation: false,
-        });
+    /// Spot light region, then point light region, then parallel light region and the summed ambient light at the end.
+    /// Each region is indexed by the instance index, so that the light pass can draw with the instance index of the light.
+    fn light_settings_offsets(instance_capacity: usize) -> [wgpu::BufferAddress; 4] {
+        let spot_light_region = std::mem::size_of::<SpotLightAttributes>()*instance_capacity;
+        let point_light_region = std::mem::size_of::<LightAttributes>()*instance_capacity;
+        let parallel_light_region = std::mem::size_of::<ParallelLightAttributes>()*instance_capacity;
+        [
+            0,
+            spot_light_region as wgpu::BufferAddress,
+            (spot_light_region+point_light_region) as wgpu::BufferAddress,
+            (spot_light_region+point_light_region+parallel_light_region) as wgpu::BufferAddress,
+        ]
+    }
 
-        let light_settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<[LightAttributes; MAX_INSTANCES]>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX,
-            mapped_at_creation: false,
-        });
+    fn light_settings_size(instance_capacity: usize) -> usize {
+        (std::mem::size_of::<SpotLightAttributes>()+std::mem::size_of::<LightAttributes>()+std::mem::size_of::<ParallelLightAttributes>())*instance_capacity+std::mem::size_of::<LightAttributes>()
+    }
 
-        Self {
-            camera_uniforms_buffer,
-            instances_world_matrix_buffer,
-            instances_inverse_world_matrix_buffer,
-            instances_mvp_matrix_buffer,
-            light_settings_buffer,
+    /// Returns true if the buffers were recreated, their previous content is lost
+    fn reserve_instances(&mut self, device: &wgpu::Device, instance_count: usize) -> bool {
+        if instance_count <= self.instance_capacity {
+            return false;
         }
+        let instance_capacity = instance_count.next_power_of_two();
+        self.instance_capacity = instance_capacity;
+        // Zeroed world matrices mark instances without a previous frame for the motion vectors,
+        // the world matrices are copied to the previous ones at the end of each frame, including the unused ones.
+        self.instances_world_matrix_buffer = create_zeroed_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_SRC|wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX);
+        self.instances_previous_world_matrix_buffer = create_zeroed_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_DST);
+        self.instances_inverse_world_matrix_buffer = create_instance_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX);
+        self.instances_mvp_matrix_buffer = create_instance_buffer(device, std::mem::size_of::<glam::Mat4>()*instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX);
+        self.light_settings_buffer = create_instance_buffer(device, Self::light_settings_size(instance_capacity), wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::VERTEX);
+        true
     }
 }
 
 pub struct BindGroupLayouts {
     pub light_pass_color_states: [wgpu::ColorStateDescriptor; 1],
-    pub camera_uniforms_bind_group_layout: wgpu::BindGroupLayout,
-    pub shadow_pass_bind_group_layout: wgpu::BindGroupLayout,
-    pub surface_pass_bind_group_layout: wgpu::BindGroupLayout,
-    pub volumetric_pass_bind_group_layout: wgpu::BindGroupLayout,
-    light_pass_bind_group_layout: wgpu::BindGroupLayout,
-    post_processing_pass_bind_group_layout: wgpu::BindGroupLayout,
+    pub camera_uniforms_bind_group_layout: BindGroupLayout,
+    pub shadow_pass_bind_group_layout: BindGroupLayout,
+    pub surface_pass_bind_group_layout: BindGroupLayout,
+    pub volumetric_pass_bind_group_layout: BindGroupLayout,
+    light_pass_bind_group_layout: BindGroupLayout,
+    shadow_maps_bind_group_layout: BindGroupLayout,
+    ambient_occlusion_pass_bind_group_layout: BindGroupLayout,
+    ambient_occlusion_blur_pass_bind_group_layout: BindGroupLayout,
+    motion_vector_pass_bind_group_layout: BindGroupLayout,
+    motion_vector_instances_bind_group_layout: BindGroupLayout,
+    temporal_anti_aliasing_pass_bind_group_layout: BindGroupLayout,
+    filter_pass_bind_group_layout: BindGroupLayout,
+    post_processing_pass_bind_group_layout: BindGroupLayout,
+    texel_copy_pass_bind_group_layout: BindGroupLayout,
+    debug_view_pass_bind_group_layout: BindGroupLayout,
 }
 
 impl BindGroupLayouts {
@@ -359,19 +687,19 @@ impl BindGroupLayouts {
             color_state_descriptor_blend_add!(Rgba16Float), // Color
         ];
 
-        let camera_uniforms_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let camera_uniforms_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 bind_group_entry!(0, (VERTEX | FRAGMENT), CameraUniforms),
             ],
         });
 
-        let shadow_pass_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let shadow_pass_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[],
         });
 
-        let surface_pass_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let surface_pass_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -395,126 +723,358 @@ impl BindGroupLayouts {
             ],
         });
 
-        let volumetric_pass_bind_group_layout = device.create_bind_group_layout(&sample_attachment!(
-            0 => Float, // Position
+        let volumetric_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Depth
         ));
 
-        let light_pass_bind_group_layout = device.create_bind_group_layout(&sample_attachment!(
-            0 => Float, // Position
-            1 => Float, // Normal
+        let light_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Depth
+            1 => Sint, // Normal
             2 => Float, // Albedo
             3 => Float, // Material
+            4 => Float, // Ambient Occlusion
         ));
 
-        let post_processing_pass_bind_group_layout = device.create_bind_group_layout(&sample_attachment!(
-            0 => Float, // Color
-            1 => Float, // Depth
+        let ambient_occlusion_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Depth
+            1 => Sint, // Normal
         ));
 
-        Self {
-            light_pass_color_states,
-            camera_uniforms_bind_group_layout,
-            shadow_pass_bind_group_layout,
-            surface_pass_bind_group_layout,
-            volumetric_pass_bind_group_layout,
-            light_pass_bind_group_layout,
-            post_processing_pass_bind_group_layout,
-        }
-    }
-}
-
-pub struct RenderPipelines {
-    general_shadow_pipeline: wgpu::RenderPipeline,
-    sphere_shadow_pipeline: wgpu::RenderPipeline,
-    pub surface_pass_pipeline_layout: wgpu::PipelineLayout,
-    pub surface_pass_pipeline: wgpu::RenderPipeline,
-    ambient_light_pipeline: wgpu::RenderPipeline,
-    parallel_light_pipeline: wgpu::RenderPipeline,
-    point_light_pipeline: wgpu::RenderPipeline,
-    spot_light_pipeline: wgpu::RenderPipeline,
-    post_processing_pass_pipeline: wgpu::RenderPipeline,
-}
+        let ambient_occlusion_blur_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Ambient Occlusion
+            1 => Float, // Depth
+            2 => Sint, // Normal
+        ));
 
-impl RenderPipelines {
-    pub fn new(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, render_options: &RenderOptions, asset_pack: &AssetPack) -> Self {
-        let shadow_depth_stencil_state = wgpu::DepthStencilStateDescriptor {
-            format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: stencil_none!(),
-        };
+        let shadow_maps_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: true,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2Array,
+                        component_type: wgpu::TextureComponentType::DepthComparison,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ShadowMapAttributes>() as u64),
+                        readonly: true,
+                    },
+                    count: None,
+                },
+            ],
+        });
 
-        let shadow_pass_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                push_constant_ranges: &[],
-                bind_group_layouts: &[&bind_group_layouts.shadow_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
-            });
+        let motion_vector_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Uint, // Instance
+            1 => Float, // Depth
+        ));
 
-        let general_shadow_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&shadow_pass_pipeline_layout),
-                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/shadow_pass_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/shadow_pass_frag")),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Front,
-                    ..Default::default()
-                }),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
-                color_states: &[],
-                depth_stencil_state: Some(shadow_depth_stencil_state.clone()),
-                vertex_state: wgpu::VertexStateDescriptor {
-                    index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[
-                        instance_attributes_vertex_buffer_descriptor!(0),
-                        instance_attributes_vertex_buffer_descriptor!(4),
-                        instance_attributes_vertex_buffer_descriptor!(8),
-                        wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Vertex,
-                            attributes: &vertex_attr_array![12 => Float3],
-                        },
-                    ],
+        let motion_vector_instances_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<glam::Mat4>() as u64),
+                        readonly: true,
+                    },
+                    count: None,
                 },
-                sample_count: 1,
-                sample_mask: !0,
-                alpha_to_coverage_enabled: false,
-            });
-
-        let sphere_shadow_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&shadow_pass_pipeline_layout),
-                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/sphere_billboard_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/sphere_shadow_frag")),
-                rasterization_state: cull_mode_none!(),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
-                color_states: &[],
-                depth_stencil_state: Some(shadow_depth_stencil_state.clone()),
-                vertex_state: wgpu::VertexStateDescriptor {
-                    index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[
-                        instance_attributes_vertex_buffer_descriptor!(0),
-                        instance_attributes_vertex_buffer_descriptor!(4),
-                        instance_attributes_vertex_buffer_descriptor!(8),
-                    ],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<glam::Mat4>() as u64),
+                        readonly: true,
+                    },
+                    count: None,
                 },
-                sample_count: 1,
-                sample_mask: !0,
-                alpha_to_coverage_enabled: false,
-            });
+                bind_group_entry!(2, (FRAGMENT), CameraUniforms),
+            ],
+        });
 
-        let surface_pass_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                push_constant_ranges: &[],
-                bind_group_layouts: &[&bind_group_layouts.surface_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
-            });
+        let temporal_anti_aliasing_pass_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Color
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // History
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Motion Vectors
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // A sampler and the texture which is filtered
+        let filter_pass_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let post_processing_pass_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // Color
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Depth
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Bloom
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // Exposure
+                    binding: 4,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<f32>() as u64),
+                        readonly: true,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let texel_copy_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Source
+        ));
+
+        let debug_view_pass_bind_group_layout = BindGroupLayout::new(device, &sample_attachment!(
+            0 => Float, // Depth
+            1 => Sint, // Normal
+            2 => Float, // Albedo
+            3 => Float, // Material
+            4 => Uint, // Stencil
+            5 => Float, // Color
+        ));
+
+        Self {
+            light_pass_color_states,
+            camera_uniforms_bind_group_layout,
+            shadow_pass_bind_group_layout,
+            surface_pass_bind_group_layout,
+            volumetric_pass_bind_group_layout,
+            light_pass_bind_group_layout,
+            shadow_maps_bind_group_layout,
+            ambient_occlusion_pass_bind_group_layout,
+            ambient_occlusion_blur_pass_bind_group_layout,
+            motion_vector_pass_bind_group_layout,
+            motion_vector_instances_bind_group_layout,
+            temporal_anti_aliasing_pass_bind_group_layout,
+            filter_pass_bind_group_layout,
+            post_processing_pass_bind_group_layout,
+            texel_copy_pass_bind_group_layout,
+            debug_view_pass_bind_group_layout,
+        }
+    }
+}
+
+pub struct RenderPipelines {
+    pub general_shadow_pipeline: wgpu::RenderPipeline,
+    pub sphere_shadow_pipeline: wgpu::RenderPipeline,
+    pub surface_pass_pipeline_layout: PipelineLayout,
+    pub surface_pass_pipeline: wgpu::RenderPipeline,
+    ambient_light_pipeline: wgpu::RenderPipeline,
+    parallel_light_pipeline: wgpu::RenderPipeline,
+    point_light_pipeline: wgpu::RenderPipeline,
+    spot_light_pipeline: wgpu::RenderPipeline,
+    ambient_occlusion_pipeline: wgpu::RenderPipeline,
+    ambient_occlusion_blur_pipeline: wgpu::RenderPipeline,
+    motion_vector_pipeline: wgpu::RenderPipeline,
+    temporal_anti_aliasing_pipeline: wgpu::RenderPipeline,
+    bloom_downsample_pipeline: wgpu::RenderPipeline,
+    bloom_upsample_pipeline: wgpu::RenderPipeline,
+    post_processing_pass_pipeline: wgpu::RenderPipeline,
+    fxaa_pipeline: wgpu::RenderPipeline,
+    depth_capture_pipeline: wgpu::RenderPipeline,
+    position_capture_pipeline: wgpu::RenderPipeline,
+    frame_capture_pipeline: wgpu::RenderPipeline,
+    debug_view_pipeline: wgpu::RenderPipeline,
+}
+
+impl RenderPipelines {
+    pub fn new(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, render_options: &RenderOptions, asset_pack: &AssetPack) -> Self {
+        let shadow_depth_stencil_state = wgpu::DepthStencilStateDescriptor {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Greater,
+            stencil: stencil_none!(),
+        };
+
+        let shadow_pass_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[&bind_group_layouts.shadow_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
+
+        let general_shadow_pipeline =
+            shadow_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&shadow_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/shadow_pass_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/shadow_pass_frag")),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::Front,
+                    // Reverse-Z: a negative bias moves the depth away from the light
+                    depth_bias: -2,
+                    depth_bias_slope_scale: -2.0,
+                    ..Default::default()
+                }),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                color_states: &[],
+                depth_stencil_state: Some(shadow_depth_stencil_state.clone()),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        instance_attributes_vertex_buffer_descriptor!(0),
+                        instance_attributes_vertex_buffer_descriptor!(4),
+                        instance_attributes_vertex_buffer_descriptor!(8),
+                        wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Vertex,
+                            attributes: &vertex_attr_array![12 => Float3],
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let sphere_shadow_pipeline =
+            shadow_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&shadow_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/sphere_billboard_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/sphere_shadow_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[],
+                depth_stencil_state: Some(shadow_depth_stencil_state.clone()),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        instance_attributes_vertex_buffer_descriptor!(0),
+                        instance_attributes_vertex_buffer_descriptor!(4),
+                        instance_attributes_vertex_buffer_descriptor!(8),
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let surface_pass_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[&bind_group_layouts.surface_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
 
         let surface_pass_pipeline =
-            device.create_render_pipeline(&surface_pass_pipeline_descriptor!(
+            surface_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &surface_pass_pipeline_descriptor!(
                 asset_pack,
                 surface_pass_pipeline_layout,
                 "assets/shader_modules/surface_pass_vert",
@@ -536,35 +1096,395 @@ impl RenderPipelines {
                 }
             ));
 
-        let light_pass_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        let light_pass_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[&bind_group_layouts.light_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout, &bind_group_layouts.shadow_maps_bind_group_layout],
+            });
+
+        let enable_light_overdraw = render_options.debug_view == DebugView::LightOverdraw;
+
+        let light_pass_depth_stencil_state = wgpu::DepthStencilStateDescriptor {
+            format: wgpu::TextureFormat::Depth24PlusStencil8,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: stencil_none!(),
+        };
+
+        let ambient_light_pipeline =
+            light_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&light_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/ambient_light_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/ambient_light_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &bind_group_layouts.light_pass_color_states,
+                depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+                    format: wgpu::TextureFormat::Depth24PlusStencil8,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: stencil_none!(),
+                }),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<LightAttributes>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Instance,
+                            attributes: &vertex_attr_array![0 => Float3],
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let parallel_light_pipeline =
+            light_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&light_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/parallel_light_vert"),
+                fragment_stage: Some(if enable_light_overdraw { shader_module!(asset_pack, "assets/shader_modules/parallel_light_frag", "LIGHT_OVERDRAW") } else { shader_module!(asset_pack, "assets/shader_modules/parallel_light_frag") }),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::Front,
+                    ..Default::default()
+                }),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                color_states: &bind_group_layouts.light_pass_color_states,
+                depth_stencil_state: Some(light_pass_depth_stencil_state.clone()),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        instance_attributes_vertex_buffer_descriptor!(0),
+                        instance_attributes_vertex_buffer_descriptor!(4),
+                        instance_attributes_vertex_buffer_descriptor!(8),
+                        wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<ParallelLightAttributes>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Instance,
+                            attributes: &vertex_attr_array![12 => Float3, 13 => Int, 14 => Int],
+                        }, wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Vertex,
+                            attributes: &vertex_attr_array![15 => Float3],
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let point_light_pipeline =
+            light_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&light_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/point_light_vert"),
+                fragment_stage: Some(if enable_light_overdraw { shader_module!(asset_pack, "assets/shader_modules/point_light_frag", "LIGHT_OVERDRAW") } else { shader_module!(asset_pack, "assets/shader_modules/point_light_frag") }),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &bind_group_layouts.light_pass_color_states,
+                depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+                    format: wgpu::TextureFormat::Depth24PlusStencil8,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: stencil_none!(),
+                }),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        instance_attributes_vertex_buffer_descriptor!(0),
+                        instance_attributes_vertex_buffer_descriptor!(4),
+                        instance_attributes_vertex_buffer_descriptor!(8),
+                        wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<LightAttributes>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Instance,
+                            attributes: &vertex_attr_array![12 => Float3],
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let spot_light_pipeline =
+            light_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&light_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/spot_light_vert"),
+                fragment_stage: Some(if enable_light_overdraw { shader_module!(asset_pack, "assets/shader_modules/spot_light_frag", "LIGHT_OVERDRAW") } else { shader_module!(asset_pack, "assets/shader_modules/spot_light_frag") }),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::Front,
+                    ..Default::default()
+                }),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                color_states: &bind_group_layouts.light_pass_color_states,
+                depth_stencil_state: Some(light_pass_depth_stencil_state),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[
+                        instance_attributes_vertex_buffer_descriptor!(0),
+                        instance_attributes_vertex_buffer_descriptor!(4),
+                        instance_attributes_vertex_buffer_descriptor!(8),
+                        wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<SpotLightAttributes>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Instance,
+                            attributes: &vertex_attr_array![12 => Float3, 13 => Float, 14 => Float, 15 => Float, 16 => Int],
+                        }, wgpu::VertexBufferDescriptor {
+                            stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                            step_mode: wgpu::InputStepMode::Vertex,
+                            attributes: &vertex_attr_array![17 => Float3],
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let ambient_occlusion_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<AmbientOcclusionSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.ambient_occlusion_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
+
+        let ambient_occlusion_pipeline =
+            ambient_occlusion_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&ambient_occlusion_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/ambient_occlusion_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(R8Unorm), // Ambient Occlusion
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let ambient_occlusion_blur_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<AmbientOcclusionBlurSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.ambient_occlusion_blur_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
+
+        let ambient_occlusion_blur_pipeline =
+            ambient_occlusion_blur_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&ambient_occlusion_blur_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/ambient_occlusion_blur_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(R8Unorm), // Ambient Occlusion
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let motion_vector_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<MotionVectorSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.motion_vector_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout, &bind_group_layouts.motion_vector_instances_bind_group_layout],
+            });
+
+        let motion_vector_pipeline =
+            motion_vector_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&motion_vector_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/motion_vector_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Rg16Float), // Motion Vectors
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let temporal_anti_aliasing_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<TemporalAntiAliasingSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.temporal_anti_aliasing_pass_bind_group_layout],
+            });
+
+        let temporal_anti_aliasing_pipeline =
+            temporal_anti_aliasing_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&temporal_anti_aliasing_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/temporal_anti_aliasing_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Rgba16Float), // History
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let bloom_pass_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<BloomPassSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.filter_pass_bind_group_layout],
+            });
+
+        let bloom_downsample_pipeline =
+            bloom_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&bloom_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/bloom_downsample_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Rgba16Float), // Bloom
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let bloom_upsample_pipeline =
+            bloom_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&bloom_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/bloom_upsample_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_add!(Rgba16Float), // Bloom
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let post_processing_pass_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<PostProcessingSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.post_processing_pass_bind_group_layout],
+            });
+
+        let post_processing_pass_pipeline =
+            post_processing_pass_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&post_processing_pass_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/post_processing_pass_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Bgra8UnormSrgb), // Color
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        let fxaa_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[],
-                bind_group_layouts: &[&bind_group_layouts.light_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+                bind_group_layouts: &[&bind_group_layouts.filter_pass_bind_group_layout],
             });
 
-        let light_pass_depth_stencil_state = wgpu::DepthStencilStateDescriptor {
-            format: wgpu::TextureFormat::Depth24PlusStencil8,
-            depth_write_enabled: false,
-            depth_compare: wgpu::CompareFunction::GreaterEqual,
-            stencil: stencil_none!(),
-        };
-
-        let ambient_light_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let fxaa_pipeline =
+            fxaa_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
                 label: None,
-                layout: Some(&light_pass_pipeline_layout),
+                layout: Some(&fxaa_pipeline_layout),
                 vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/ambient_light_frag")),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/fxaa_frag")),
                 rasterization_state: cull_mode_none!(),
                 primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
-                color_states: &bind_group_layouts.light_pass_color_states,
-                depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
-                    format: wgpu::TextureFormat::Depth24PlusStencil8,
-                    depth_write_enabled: false,
-                    depth_compare: wgpu::CompareFunction::Always,
-                    stencil: stencil_none!(),
-                }),
+                color_states: &[
+                    color_state_descriptor_blend_none!(Bgra8UnormSrgb), // Color
+                ],
+                depth_stencil_state: None,
                 vertex_state: wgpu::VertexStateDescriptor {
                     index_format: wgpu::IndexFormat::Uint16,
                     vertex_buffers: &[],
@@ -574,124 +1494,101 @@ impl RenderPipelines {
                 alpha_to_coverage_enabled: false,
             });
 
-        let parallel_light_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let debug_view_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
-                layout: Some(&light_pass_pipeline_layout),
-                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/parallel_light_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/parallel_light_frag")),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Front,
-                    ..Default::default()
-                }),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                color_states: &bind_group_layouts.light_pass_color_states,
-                depth_stencil_state: Some(light_pass_depth_stencil_state.clone()),
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::FRAGMENT,
+                        range: 0..std::mem::size_of::<DebugViewSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[&bind_group_layouts.debug_view_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
+
+        let debug_view_pipeline =
+            debug_view_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&debug_view_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/debug_view_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Bgra8UnormSrgb), // Color
+                ],
+                depth_stencil_state: None,
                 vertex_state: wgpu::VertexStateDescriptor {
                     index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[
-                        instance_attributes_vertex_buffer_descriptor!(0),
-                        instance_attributes_vertex_buffer_descriptor!(4),
-                        instance_attributes_vertex_buffer_descriptor!(8),
-                        wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<LightAttributes>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Instance,
-                            attributes: &vertex_attr_array![12 => Float3],
-                        }, wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Vertex,
-                            attributes: &vertex_attr_array![13 => Float3],
-                        },
-                    ],
+                    vertex_buffers: &[],
                 },
                 sample_count: 1,
                 sample_mask: !0,
                 alpha_to_coverage_enabled: false,
             });
 
-        let point_light_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let texel_copy_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
-                layout: Some(&light_pass_pipeline_layout),
-                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/point_light_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/point_light_frag")),
+                push_constant_ranges: &[],
+                bind_group_layouts: &[&bind_group_layouts.texel_copy_pass_bind_group_layout],
+            });
+
+        let depth_capture_pipeline =
+            texel_copy_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&texel_copy_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/texel_copy_frag")),
                 rasterization_state: cull_mode_none!(),
                 primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
-                color_states: &bind_group_layouts.light_pass_color_states,
-                depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
-                    format: wgpu::TextureFormat::Depth24PlusStencil8,
-                    depth_write_enabled: false,
-                    depth_compare: wgpu::CompareFunction::Always,
-                    stencil: stencil_none!(),
-                }),
+                color_states: &[
+                    color_state_descriptor_blend_none!(R32Float), // Depth
+                ],
+                depth_stencil_state: None,
                 vertex_state: wgpu::VertexStateDescriptor {
                     index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[
-                        instance_attributes_vertex_buffer_descriptor!(0),
-                        instance_attributes_vertex_buffer_descriptor!(4),
-                        instance_attributes_vertex_buffer_descriptor!(8),
-                        wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<LightAttributes>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Instance,
-                            attributes: &vertex_attr_array![12 => Float3],
-                        },
-                    ],
+                    vertex_buffers: &[],
                 },
                 sample_count: 1,
                 sample_mask: !0,
                 alpha_to_coverage_enabled: false,
             });
 
-        let spot_light_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let position_capture_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
-                layout: Some(&light_pass_pipeline_layout),
-                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/spot_light_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/spot_light_frag")),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Front,
-                    ..Default::default()
-                }),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                color_states: &bind_group_layouts.light_pass_color_states,
-                depth_stencil_state: Some(light_pass_depth_stencil_state),
+                push_constant_ranges: &[],
+                bind_group_layouts: &[&bind_group_layouts.texel_copy_pass_bind_group_layout, &bind_group_layouts.camera_uniforms_bind_group_layout],
+            });
+
+        let position_capture_pipeline =
+            position_capture_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&position_capture_pipeline_layout),
+                vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/position_capture_frag")),
+                rasterization_state: cull_mode_none!(),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+                color_states: &[
+                    color_state_descriptor_blend_none!(Rgba32Float), // Position
+                ],
+                depth_stencil_state: None,
                 vertex_state: wgpu::VertexStateDescriptor {
                     index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[
-                        instance_attributes_vertex_buffer_descriptor!(0),
-                        instance_attributes_vertex_buffer_descriptor!(4),
-                        instance_attributes_vertex_buffer_descriptor!(8),
-                        wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<SpotLightAttributes>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Instance,
-                            attributes: &vertex_attr_array![12 => Float3, 13 => Float, 14 => Float, 15 => Float],
-                        }, wgpu::VertexBufferDescriptor {
-                            stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                            step_mode: wgpu::InputStepMode::Vertex,
-                            attributes: &vertex_attr_array![16 => Float3],
-                        },
-                    ],
+                    vertex_buffers: &[],
                 },
                 sample_count: 1,
                 sample_mask: !0,
                 alpha_to_coverage_enabled: false,
             });
 
-        let post_processing_pass_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                push_constant_ranges: &[],
-                bind_group_layouts: &[&bind_group_layouts.post_processing_pass_bind_group_layout],
-            });
-
-        let post_processing_pass_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let frame_capture_pipeline =
+            texel_copy_pipeline_layout.create_render_pipeline(device, asset_pack, &wgpu::RenderPipelineDescriptor {
                 label: None,
-                layout: Some(&post_processing_pass_pipeline_layout),
+                layout: Some(&texel_copy_pipeline_layout),
                 vertex_stage: shader_module!(asset_pack, "assets/shader_modules/screen_quad_vert"),
-                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/post_processing_pass_frag")),
+                fragment_stage: Some(shader_module!(asset_pack, "assets/shader_modules/texel_copy_frag")),
                 rasterization_state: cull_mode_none!(),
                 primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
                 color_states: &[
@@ -716,21 +1613,37 @@ impl RenderPipelines {
             parallel_light_pipeline,
             point_light_pipeline,
             spot_light_pipeline,
+            ambient_occlusion_pipeline,
+            ambient_occlusion_blur_pipeline,
+            motion_vector_pipeline,
+            temporal_anti_aliasing_pipeline,
+            bloom_downsample_pipeline,
+            bloom_upsample_pipeline,
             post_processing_pass_pipeline,
+            fxaa_pipeline,
+            depth_capture_pipeline,
+            position_capture_pipeline,
+            frame_capture_pipeline,
+            debug_view_pipeline,
         }
     }
 }
 
 struct ComputePipelines {
+    instance_matrices_bind_group_layout: BindGroupLayout,
     instance_matrices_bind_group: wgpu::BindGroup,
     instance_matrices_pipeline: wgpu::ComputePipeline,
+    mvp_matrix_bind_group_layout: BindGroupLayout,
     mvp_matrix_bind_group: wgpu::BindGroup,
     mvp_matrix_pipeline: wgpu::ComputePipeline,
+    auto_exposure_bind_group_layout: BindGroupLayout,
+    luminance_histogram_pipeline: wgpu::ComputePipeline,
+    exposure_adaptation_pipeline: wgpu::ComputePipeline,
 }
 
 impl ComputePipelines {
     fn new(device: &wgpu::Device, buffers: &AttributeAndUniformBuffers, asset_pack: &AssetPack) -> Self {
-        let instance_matrices_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let instance_matrices_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -756,26 +1669,7 @@ impl ComputePipelines {
             ],
         });
 
-        let instance_matrices_bind_group = device.create_bind_group(&bind_group_descriptor!(
-            &instance_matrices_bind_group_layout,
-            0 => Buffer(buffers.instances_world_matrix_buffer.slice(..)),
-            1 => Buffer(buffers.instances_inverse_world_matrix_buffer.slice(..)),
-        ));
-
-        let instance_matrices_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                push_constant_ranges: &[],
-                bind_group_layouts: &[&instance_matrices_bind_group_layout],
-            });
-
-        let instance_matrices_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: None,
-            layout: Some(&instance_matrices_pipeline_layout),
-            compute_stage: shader_module!(asset_pack, "assets/shader_modules/compute_instance_matrices_comp"),
-        });
-
-        let mvp_matrix_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let mvp_matrix_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -801,14 +1695,76 @@ impl ComputePipelines {
             ],
         });
 
-        let mvp_matrix_bind_group = device.create_bind_group(&bind_group_descriptor!(
-            &mvp_matrix_bind_group_layout,
-            0 => Buffer(buffers.instances_world_matrix_buffer.slice(..)),
-            1 => Buffer(buffers.instances_mvp_matrix_buffer.slice(..)),
-        ));
+        let (instance_matrices_bind_group, mvp_matrix_bind_group) = Self::generate_bind_groups(device, buffers, &instance_matrices_bind_group_layout, &mvp_matrix_bind_group_layout);
+
+        let auto_exposure_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new((std::mem::size_of::<u32>()*LUMINANCE_HISTOGRAM_BIN_COUNT) as u64),
+                        readonly: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<f32>() as u64),
+                        readonly: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let (instance_matrices_pipeline, mvp_matrix_pipeline, luminance_histogram_pipeline, exposure_adaptation_pipeline) = Self::create_pipelines(device, asset_pack, &instance_matrices_bind_group_layout, &mvp_matrix_bind_group_layout, &auto_exposure_bind_group_layout);
+
+        Self {
+            instance_matrices_bind_group_layout,
+            instance_matrices_bind_group,
+            instance_matrices_pipeline,
+            mvp_matrix_bind_group_layout,
+            mvp_matrix_bind_group,
+            mvp_matrix_pipeline,
+            auto_exposure_bind_group_layout,
+            luminance_histogram_pipeline,
+            exposure_adaptation_pipeline,
+        }
+    }
+
+    /// Returns the instance matrices, MVP matrix, luminance histogram and exposure adaptation pipelines
+    fn create_pipelines(device: &wgpu::Device, asset_pack: &AssetPack, instance_matrices_bind_group_layout: &BindGroupLayout, mvp_matrix_bind_group_layout: &BindGroupLayout, auto_exposure_bind_group_layout: &BindGroupLayout) -> (wgpu::ComputePipeline, wgpu::ComputePipeline, wgpu::ComputePipeline, wgpu::ComputePipeline) {
+        let instance_matrices_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[instance_matrices_bind_group_layout],
+            });
+
+        let instance_matrices_pipeline = instance_matrices_pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&instance_matrices_pipeline_layout),
+            compute_stage: shader_module!(asset_pack, "assets/shader_modules/compute_instance_matrices_comp"),
+        });
 
         let mvp_matrix_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[
                     wgpu::PushConstantRange {
@@ -816,62 +1772,299 @@ impl ComputePipelines {
                         range: 0..std::mem::size_of::<glam::Mat4>() as u32,
                     }
                 ],
-                bind_group_layouts: &[&mvp_matrix_bind_group_layout],
+                bind_group_layouts: &[mvp_matrix_bind_group_layout],
             });
 
-        let mvp_matrix_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        let mvp_matrix_pipeline = mvp_matrix_pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
             label: None,
             layout: Some(&mvp_matrix_pipeline_layout),
             compute_stage: shader_module!(asset_pack, "assets/shader_modules/compute_mvp_matrix_comp"),
         });
 
+        let auto_exposure_pipeline_layout =
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[
+                    wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::COMPUTE,
+                        range: 0..std::mem::size_of::<AutoExposureSettings>() as u32,
+                    }
+                ],
+                bind_group_layouts: &[auto_exposure_bind_group_layout],
+            });
+
+        let luminance_histogram_pipeline = auto_exposure_pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&auto_exposure_pipeline_layout),
+            compute_stage: shader_module!(asset_pack, "assets/shader_modules/luminance_histogram_comp"),
+        });
+
+        let exposure_adaptation_pipeline = auto_exposure_pipeline_layout.create_compute_pipeline(device, asset_pack, &wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&auto_exposure_pipeline_layout),
+            compute_stage: shader_module!(asset_pack, "assets/shader_modules/exposure_adaptation_comp"),
+        });
+
+        (instance_matrices_pipeline, mvp_matrix_pipeline, luminance_histogram_pipeline, exposure_adaptation_pipeline)
+    }
+
+    /// Keeps the bind group layouts and bind groups, e.g. after the shaders were reloaded
+    fn recreate_pipelines(&mut self, device: &wgpu::Device, asset_pack: &AssetPack) {
+        let (instance_matrices_pipeline, mvp_matrix_pipeline, luminance_histogram_pipeline, exposure_adaptation_pipeline) = Self::create_pipelines(device, asset_pack, &self.instance_matrices_bind_group_layout, &self.mvp_matrix_bind_group_layout, &self.auto_exposure_bind_group_layout);
+        self.instance_matrices_pipeline = instance_matrices_pipeline;
+        self.mvp_matrix_pipeline = mvp_matrix_pipeline;
+        self.luminance_histogram_pipeline = luminance_histogram_pipeline;
+        self.exposure_adaptation_pipeline = exposure_adaptation_pipeline;
+    }
+
+    fn generate_bind_groups(device: &wgpu::Device, buffers: &AttributeAndUniformBuffers, instance_matrices_bind_group_layout: &wgpu::BindGroupLayout, mvp_matrix_bind_group_layout: &wgpu::BindGroupLayout) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        let instance_matrices_bind_group = device.create_bind_group(&bind_group_descriptor!(
+            instance_matrices_bind_group_layout,
+            0 => Buffer(buffers.instances_world_matrix_buffer.slice(..)),
+            1 => Buffer(buffers.instances_inverse_world_matrix_buffer.slice(..)),
+        ));
+        let mvp_matrix_bind_group = device.create_bind_group(&bind_group_descriptor!(
+            mvp_matrix_bind_group_layout,
+            0 => Buffer(buffers.instances_world_matrix_buffer.slice(..)),
+            1 => Buffer(buffers.instances_mvp_matrix_buffer.slice(..)),
+        ));
+        (instance_matrices_bind_group, mvp_matrix_bind_group)
+    }
+
+    fn update_bind_groups(&mut self, device: &wgpu::Device, buffers: &AttributeAndUniformBuffers) {
+        let (instance_matrices_bind_group, mvp_matrix_bind_group) = Self::generate_bind_groups(device, buffers, &self.instance_matrices_bind_group_layout, &self.mvp_matrix_bind_group_layout);
+        self.instance_matrices_bind_group = instance_matrices_bind_group;
+        self.mvp_matrix_bind_group = mvp_matrix_bind_group;
+    }
+}
+
+/// Attachments of the render graph of a frame
+struct RenderGraphAttachments {
+    instance: AttachmentHandle,
+    normal: AttachmentHandle,
+    albedo: AttachmentHandle,
+    material: AttachmentHandle,
+    color: AttachmentHandle,
+    depth_stencil: AttachmentHandle,
+    ambient_occlusion: AttachmentHandle,
+    ambient_occlusion_blur: AttachmentHandle,
+    motion_vector: AttachmentHandle,
+    temporal_history: [AttachmentHandle; 2],
+    ldr: AttachmentHandle,
+    bloom: AttachmentHandle,
+    black: AttachmentHandle,
+    frame_capture: AttachmentHandle,
+}
+
+struct RenderGraphPasses {
+    ambient_occlusion: PassHandle,
+    debug_view: PassHandle,
+    motion_vector: PassHandle,
+    temporal_anti_aliasing: PassHandle,
+    auto_exposure: PassHandle,
+    bloom: PassHandle,
+    post_processing: PassHandle,
+    fxaa: PassHandle,
+    frame_capture: PassHandle,
+}
+
+const BLOOM_MIP_LEVEL_COUNT: u32 = 6;
+
+/// Declares the passes of a frame, the render options decide which of them are enabled and which attachments they use
+struct FrameRenderGraph {
+    graph: RenderGraph<Renderer>,
+    attachments: RenderGraphAttachments,
+    passes: RenderGraphPasses,
+}
+
+impl FrameRenderGraph {
+    fn new(size: wgpu::Extent3d) -> Self {
+        let mut graph: RenderGraph<Renderer> = RenderGraph::new(size);
+        let instance = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::R32Uint, AttachmentSize::Frame));
+        let mut normal_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rg16Sint, AttachmentSize::Frame);
+        normal_descriptor.usage |= wgpu::TextureUsage::COPY_SRC;
+        let normal = graph.add_attachment(normal_descriptor);
+        let mut albedo_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba8UnormSrgb, AttachmentSize::Frame);
+        albedo_descriptor.usage |= wgpu::TextureUsage::COPY_SRC;
+        let albedo = graph.add_attachment(albedo_descriptor);
+        let mut material_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba8Unorm, AttachmentSize::Frame);
+        material_descriptor.usage |= wgpu::TextureUsage::COPY_SRC;
+        let material = graph.add_attachment(material_descriptor);
+        let mut color_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float, AttachmentSize::Frame);
+        color_descriptor.usage |= wgpu::TextureUsage::COPY_SRC|wgpu::TextureUsage::COPY_DST;
+        let color = graph.add_attachment(color_descriptor);
+        let depth_stencil = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::Depth24PlusStencil8, AttachmentSize::Frame));
+        // The blur alternates between both, so that the result ends up in ambient_occlusion again
+        let ambient_occlusion = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::R8Unorm, AttachmentSize::Frame));
+        let ambient_occlusion_blur = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::R8Unorm, AttachmentSize::Frame));
+        let motion_vector = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::Rg16Float, AttachmentSize::Frame));
+        // Alternately one is the previous frame and the other one is resolved into
+        let mut temporal_history_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float, AttachmentSize::Frame);
+        temporal_history_descriptor.usage |= wgpu::TextureUsage::COPY_SRC;
+        temporal_history_descriptor.persistent = true;
+        let temporal_history = [
+            graph.add_attachment(temporal_history_descriptor),
+            graph.add_attachment(temporal_history_descriptor),
+        ];
+        // Tonemapped frame, which FXAA is applied to
+        let ldr = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::Bgra8UnormSrgb, AttachmentSize::Frame));
+        // The bloom chain starts at half the resolution and ends before a mip level would be smaller than one pixel
+        let mut bloom_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float, AttachmentSize::Divided(2));
+        bloom_descriptor.mip_level_count = BLOOM_MIP_LEVEL_COUNT;
+        let bloom = graph.add_attachment(bloom_descriptor);
+        // Replaces the bloom chain if bloom is disabled
+        let black = graph.add_attachment(AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float, AttachmentSize::Fixed(wgpu::Extent3d { width: 1, height: 1, depth: 1 })));
+        // The texture of a swap chain frame can not be copied, so a captured frame is rendered here and then copied to the frame
+        let mut frame_capture_descriptor = AttachmentDescriptor::new(wgpu::TextureFormat::Bgra8UnormSrgb, AttachmentSize::Frame);
+        frame_capture_descriptor.usage |= wgpu::TextureUsage::COPY_SRC;
+        frame_capture_descriptor.persistent = true;
+        let frame_capture = graph.add_attachment(frame_capture_descriptor);
+
+        // Surface pass and volumetric pass, which draw the scene
+        graph.add_recorded_pass("surface", &[], &[instance, normal, albedo, material, depth_stencil]);
+        graph.add_recorded_pass("volumetric", &[depth_stencil], &[color]);
+        // The attachments of the following passes are declared by apply_render_options
+        let ambient_occlusion_pass = graph.add_pass("ambient occlusion", &[], &[], |renderer, encoder, _frame_view| renderer.render_ambient_occlusion_pass(encoder));
+        graph.add_pass("light volumes", &[depth_stencil, normal, albedo, material, ambient_occlusion], &[color], |renderer, encoder, _frame_view| renderer.render_light_volumes(encoder));
+        let debug_view = graph.add_pass("debug view", &[], &[], |renderer, encoder, frame_view| renderer.render_debug_view_pass(encoder, frame_view));
+        let motion_vector_pass = graph.add_pass("motion vectors", &[], &[], |renderer, encoder, _frame_view| renderer.render_motion_vector_pass(encoder));
+        let temporal_anti_aliasing = graph.add_pass("temporal anti aliasing", &[], &[], |renderer, encoder, _frame_view| renderer.render_temporal_anti_aliasing_pass(encoder));
+        let auto_exposure = graph.add_pass("auto exposure", &[], &[], |renderer, encoder, _frame_view| renderer.render_auto_exposure_pass(encoder));
+        let bloom_pass = graph.add_pass("bloom", &[], &[], |renderer, encoder, _frame_view| renderer.render_bloom_pass(encoder));
+        let post_processing = graph.add_pass("post processing", &[], &[], |renderer, encoder, frame_view| renderer.render_post_processing_pass(encoder, frame_view));
+        let fxaa = graph.add_pass("fxaa", &[], &[], |renderer, encoder, frame_view| renderer.render_fxaa_pass(encoder, frame_view));
+        // Screenshots read the GBuffer after the frame
+        let frame_capture_pass = graph.add_pass("frame capture", &[], &[], |renderer, encoder, frame_view| renderer.render_frame_capture_pass(encoder, frame_view));
+
         Self {
-            instance_matrices_bind_group,
-            instance_matrices_pipeline,
-            mvp_matrix_bind_group,
-            mvp_matrix_pipeline,
+            graph,
+            attachments: RenderGraphAttachments {
+                instance,
+                normal,
+                albedo,
+                material,
+                color,
+                depth_stencil,
+                ambient_occlusion,
+                ambient_occlusion_blur,
+                motion_vector,
+                temporal_history,
+                ldr,
+                bloom,
+                black,
+                frame_capture,
+            },
+            passes: RenderGraphPasses {
+                ambient_occlusion: ambient_occlusion_pass,
+                debug_view,
+                motion_vector: motion_vector_pass,
+                temporal_anti_aliasing,
+                auto_exposure,
+                bloom: bloom_pass,
+                post_processing,
+                fxaa,
+                frame_capture: frame_capture_pass,
+            },
+        }
+    }
+
+    /// The frame is rendered into the frame capture attachment first if capture_frame is set
+    fn apply_render_options(&mut self, render_options: &RenderOptions, capture_frame: bool) {
+        let graph = &mut self.graph;
+        let passes = &self.passes;
+        let attachments = &self.attachments;
+        let enable_debug_view = render_options.debug_view != DebugView::None;
+        let enable_ambient_occlusion = render_options.ambient_occlusion_quality != AmbientOcclusionQuality::Off;
+        let enable_temporal_anti_aliasing = !enable_debug_view && render_options.anti_aliasing == AntiAliasing::Temporal;
+        let enable_bloom = !enable_debug_view && render_options.enable_bloom;
+        let enable_fxaa = !enable_debug_view && render_options.anti_aliasing == AntiAliasing::Fxaa;
+        let frame_capture = [attachments.frame_capture];
+        let frame_output: &[AttachmentHandle] = if capture_frame { &frame_capture } else { &[] };
+        // Without ambient occlusion the buffer is only cleared
+        if enable_ambient_occlusion {
+            graph.set_pass(passes.ambient_occlusion, true, &[attachments.depth_stencil, attachments.normal], &[attachments.ambient_occlusion, attachments.ambient_occlusion_blur]);
+        } else {
+            graph.set_pass(passes.ambient_occlusion, true, &[], &[attachments.ambient_occlusion]);
+        }
+        graph.set_pass(passes.debug_view, enable_debug_view, &[attachments.depth_stencil, attachments.normal, attachments.albedo, attachments.material, attachments.color], frame_output);
+        graph.set_pass(passes.motion_vector, enable_temporal_anti_aliasing, &[attachments.instance, attachments.depth_stencil], &[attachments.motion_vector]);
+        graph.set_pass(passes.temporal_anti_aliasing, enable_temporal_anti_aliasing, &[attachments.color, attachments.motion_vector, attachments.temporal_history[0], attachments.temporal_history[1]], &[attachments.color]);
+        graph.set_pass(passes.auto_exposure, !enable_debug_view && render_options.enable_auto_exposure, &[attachments.color], &[]);
+        // Without bloom only the black texture is cleared, which the post processing adds instead
+        let bloom = if enable_bloom { attachments.bloom } else { attachments.black };
+        graph.set_pass(passes.bloom, !enable_debug_view, if enable_bloom { &[attachments.color] } else { &[] }, &[bloom]);
+        graph.set_pass(passes.post_processing, !enable_debug_view, &[attachments.color, attachments.depth_stencil, bloom], if enable_fxaa { &[attachments.ldr] } else { frame_output });
+        graph.set_pass(passes.fxaa, enable_fxaa, &[attachments.ldr], frame_output);
+        graph.set_pass(passes.frame_capture, capture_frame, &[attachments.normal, attachments.albedo, attachments.material, attachments.color, attachments.depth_stencil, attachments.frame_capture], &[]);
+    }
+
+    fn is_pass_enabled(&self, pass: PassHandle) -> bool {
+        self.graph.is_pass_enabled(pass)
+    }
+
+    fn get_texture(&self, attachment: AttachmentHandle) -> &wgpu::Texture {
+        self.graph.get_texture(attachment)
+    }
+
+    fn get_view(&self, attachment: AttachmentHandle) -> &wgpu::TextureView {
+        self.graph.get_view(attachment)
+    }
+
+    /// View which the last pass of the frame renders into
+    fn get_output_view<'a>(&'a self, frame_view: &'a wgpu::TextureView) -> &'a wgpu::TextureView {
+        if self.is_pass_enabled(self.passes.frame_capture) {
+            self.get_view(self.attachments.frame_capture)
+        } else {
+            frame_view
         }
     }
 }
 
+struct AmbientOcclusionBindGroups {
+    pass: wgpu::BindGroup,
+    blur_passes: [wgpu::BindGroup; 2],
+}
+
+struct TemporalAntiAliasingBindGroups {
+    motion_vector_pass: wgpu::BindGroup,
+    temporal_anti_aliasing_passes: [wgpu::BindGroup; 2],
+}
+
+struct BloomBindGroups {
+    size: wgpu::Extent3d,
+    views: Vec<wgpu::TextureView>,
+    downsample_passes: Vec<wgpu::BindGroup>,
+    upsample_passes: Vec<wgpu::BindGroup>,
+}
+
+/// Views and bind groups of the attachments in the render graph, the ones of disabled passes are None
 struct GBuffer {
     size: wgpu::Extent3d,
-    position_view: wgpu::TextureView,
-    normal_view: wgpu::TextureView,
-    albedo_view: wgpu::TextureView,
-    material_view: wgpu::TextureView,
-    color_view: wgpu::TextureView,
-    depth_stencil_view: wgpu::TextureView,
     depth_view: wgpu::TextureView,
-    stencil_view: wgpu::TextureView,
     volumetric_pass_bind_group: wgpu::BindGroup,
     light_pass_bind_group: wgpu::BindGroup,
-    post_processing_pass_bind_group: wgpu::BindGroup,
+    ambient_occlusion: Option<AmbientOcclusionBindGroups>,
+    temporal_anti_aliasing: Option<TemporalAntiAliasingBindGroups>,
+    bloom: Option<BloomBindGroups>,
+    auto_exposure_bind_group: Option<wgpu::BindGroup>,
+    post_processing_pass_bind_group: Option<wgpu::BindGroup>,
+    fxaa_pass_bind_group: Option<wgpu::BindGroup>,
+    debug_view_pass_bind_group: Option<wgpu::BindGroup>,
+    depth_capture_pass_bind_group: wgpu::BindGroup,
+    frame_capture_pass_bind_group: Option<wgpu::BindGroup>,
 }
 
 impl GBuffer {
-    fn new(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, size: wgpu::Extent3d) -> Self {
-        let texture_view_descriptor = wgpu::TextureViewDescriptor {
-            ..wgpu::TextureViewDescriptor::default()
-        };
-
-        let position_buffer = device.create_texture(&create_attachment!(size, Rgba32Float));
-        let position_view = position_buffer.create_view(&texture_view_descriptor);
-
-        let normal_buffer = device.create_texture(&create_attachment!(size, Rgba16Sint));
-        let normal_view = normal_buffer.create_view(&texture_view_descriptor);
-
-        let albedo_buffer = device.create_texture(&create_attachment!(size, Rgba16Float));
-        let albedo_view = albedo_buffer.create_view(&texture_view_descriptor);
-
-        let material_buffer = device.create_texture(&create_attachment!(size, Rgba8Unorm));
-        let material_view = material_buffer.create_view(&texture_view_descriptor);
-
-        let color_buffer = device.create_texture(&create_attachment!(size, Rgba16Float));
-        let color_view = color_buffer.create_view(&texture_view_descriptor);
-
-        let depth_stencil_buffer = device.create_texture(&create_attachment!(size, Depth24PlusStencil8));
-        let depth_stencil_view = depth_stencil_buffer.create_view(&texture_view_descriptor);
+    fn new(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, compute_pipelines: &ComputePipelines, buffers: &AttributeAndUniformBuffers, post_processing_sampler: &wgpu::Sampler, frame_graph: &FrameRenderGraph) -> Self {
+        let attachments = &frame_graph.attachments;
+        let passes = &frame_graph.passes;
+        let normal_view = frame_graph.get_view(attachments.normal);
+        let albedo_view = frame_graph.get_view(attachments.albedo);
+        let material_view = frame_graph.get_view(attachments.material);
+        let color_view = frame_graph.get_view(attachments.color);
+        let ambient_occlusion_view = frame_graph.get_view(attachments.ambient_occlusion);
+
+        let depth_stencil_buffer = frame_graph.get_texture(attachments.depth_stencil);
         let depth_view = depth_stencil_buffer.create_view(&wgpu::TextureViewDescriptor {
             aspect: wgpu::TextureAspect::DepthOnly,
             ..wgpu::TextureViewDescriptor::default()
@@ -883,36 +2076,274 @@ impl GBuffer {
 
         let volumetric_pass_bind_group = device.create_bind_group(&bind_group_descriptor!(
             &bind_group_layouts.volumetric_pass_bind_group_layout,
-            0 => TextureView(&position_view),
+            0 => TextureView(&depth_view),
         ));
 
         let light_pass_bind_group = device.create_bind_group(&bind_group_descriptor!(
             &bind_group_layouts.light_pass_bind_group_layout,
-            0 => TextureView(&position_view),
-            1 => TextureView(&normal_view),
-            2 => TextureView(&albedo_view),
-            3 => TextureView(&material_view),
+            0 => TextureView(&depth_view),
+            1 => TextureView(normal_view),
+            2 => TextureView(albedo_view),
+            3 => TextureView(material_view),
+            4 => TextureView(ambient_occlusion_view),
         ));
 
-        let post_processing_pass_bind_group = device.create_bind_group(&bind_group_descriptor!(
-            &bind_group_layouts.post_processing_pass_bind_group_layout,
-            0 => TextureView(&color_view),
-            1 => TextureView(&depth_view),
+        let ambient_occlusion = if frame_graph.graph.is_attachment_used(attachments.ambient_occlusion_blur) {
+            let ambient_occlusion_blur_view = frame_graph.get_view(attachments.ambient_occlusion_blur);
+            Some(AmbientOcclusionBindGroups {
+                pass: device.create_bind_group(&bind_group_descriptor!(
+                    &bind_group_layouts.ambient_occlusion_pass_bind_group_layout,
+                    0 => TextureView(&depth_view),
+                    1 => TextureView(normal_view),
+                )),
+                blur_passes: [
+                    device.create_bind_group(&bind_group_descriptor!(
+                        &bind_group_layouts.ambient_occlusion_blur_pass_bind_group_layout,
+                        0 => TextureView(ambient_occlusion_view),
+                        1 => TextureView(&depth_view),
+                        2 => TextureView(normal_view),
+                    )),
+                    device.create_bind_group(&bind_group_descriptor!(
+                        &bind_group_layouts.ambient_occlusion_blur_pass_bind_group_layout,
+                        0 => TextureView(ambient_occlusion_blur_view),
+                        1 => TextureView(&depth_view),
+                        2 => TextureView(normal_view),
+                    )),
+                ],
+            })
+        } else {
+            None
+        };
+
+        let temporal_anti_aliasing = if frame_graph.is_pass_enabled(passes.temporal_anti_aliasing) {
+            let motion_vector_view = frame_graph.get_view(attachments.motion_vector);
+            Some(TemporalAntiAliasingBindGroups {
+                motion_vector_pass: device.create_bind_group(&bind_group_descriptor!(
+                    &bind_group_layouts.motion_vector_pass_bind_group_layout,
+                    0 => TextureView(frame_graph.get_view(attachments.instance)),
+                    1 => TextureView(&depth_view),
+                )),
+                temporal_anti_aliasing_passes: [
+                    device.create_bind_group(&bind_group_descriptor!(
+                        &bind_group_layouts.temporal_anti_aliasing_pass_bind_group_layout,
+                        0 => Sampler(post_processing_sampler),
+                        1 => TextureView(color_view),
+                        2 => TextureView(frame_graph.get_view(attachments.temporal_history[1])),
+                        3 => TextureView(motion_vector_view),
+                    )),
+                    device.create_bind_group(&bind_group_descriptor!(
+                        &bind_group_layouts.temporal_anti_aliasing_pass_bind_group_layout,
+                        0 => Sampler(post_processing_sampler),
+                        1 => TextureView(color_view),
+                        2 => TextureView(frame_graph.get_view(attachments.temporal_history[0])),
+                        3 => TextureView(motion_vector_view),
+                    )),
+                ],
+            })
+        } else {
+            None
+        };
+
+        let bloom = if frame_graph.graph.is_attachment_used(attachments.bloom) {
+            let bloom_buffer = frame_graph.get_texture(attachments.bloom);
+            let bloom_mip_level_count = frame_graph.graph.get_mip_level_count(attachments.bloom);
+            let views: Vec<wgpu::TextureView> = (0..bloom_mip_level_count).map(|mip_level| {
+                bloom_buffer.create_view(&wgpu::TextureViewDescriptor {
+                    label: None,
+                    format: None,
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    aspect: wgpu::TextureAspect::All,
+                    base_mip_level: mip_level,
+                    level_count: std::num::NonZeroU32::new(1),
+                    base_array_layer: 0,
+                    array_layer_count: std::num::NonZeroU32::new(1),
+                })
+            }).collect();
+            // Each mip level is downsampled from the previous one, the first one from the color buffer
+            let downsample_passes = (0..bloom_mip_level_count as usize).map(|mip_level| {
+                device.create_bind_group(&bind_group_descriptor!(
+                    &bind_group_layouts.filter_pass_bind_group_layout,
+                    0 => Sampler(post_processing_sampler),
+                    1 => TextureView(if mip_level == 0 { color_view } else { &views[mip_level-1] }),
+                ))
+            }).collect();
+            // Each mip level is added to the next larger one
+            let upsample_passes = (1..bloom_mip_level_count as usize).map(|mip_level| {
+                device.create_bind_group(&bind_group_descriptor!(
+                    &bind_group_layouts.filter_pass_bind_group_layout,
+                    0 => Sampler(post_processing_sampler),
+                    1 => TextureView(&views[mip_level]),
+                ))
+            }).collect();
+            Some(BloomBindGroups {
+                size: frame_graph.graph.get_extent(attachments.bloom),
+                views,
+                downsample_passes,
+                upsample_passes,
+            })
+        } else {
+            None
+        };
+
+        let auto_exposure_bind_group = if frame_graph.is_pass_enabled(passes.auto_exposure) {
+            Some(device.create_bind_group(&bind_group_descriptor!(
+                &compute_pipelines.auto_exposure_bind_group_layout,
+                0 => TextureView(color_view),
+                1 => Buffer(buffers.luminance_histogram_buffer.slice(..)),
+                2 => Buffer(buffers.exposure_buffer.slice(..)),
+            )))
+        } else {
+            None
+        };
+
+        let post_processing_pass_bind_group = if frame_graph.is_pass_enabled(passes.post_processing) {
+            // Only the first mip level of the bloom chain is added to the frame
+            let bloom_attachment = if frame_graph.graph.is_attachment_used(attachments.bloom) { attachments.bloom } else { attachments.black };
+            let bloom_view = frame_graph.get_texture(bloom_attachment).create_view(&wgpu::TextureViewDescriptor {
+                level_count: std::num::NonZeroU32::new(1),
+                ..wgpu::TextureViewDescriptor::default()
+            });
+            Some(device.create_bind_group(&bind_group_descriptor!(
+                &bind_group_layouts.post_processing_pass_bind_group_layout,
+                0 => TextureView(color_view),
+                1 => TextureView(&depth_view),
+                2 => Sampler(post_processing_sampler),
+                3 => TextureView(&bloom_view),
+                4 => Buffer(buffers.exposure_buffer.slice(..)),
+            )))
+        } else {
+            None
+        };
+
+        let fxaa_pass_bind_group = if frame_graph.is_pass_enabled(passes.fxaa) {
+            Some(device.create_bind_group(&bind_group_descriptor!(
+                &bind_group_layouts.filter_pass_bind_group_layout,
+                0 => Sampler(post_processing_sampler),
+                1 => TextureView(frame_graph.get_view(attachments.ldr)),
+            )))
+        } else {
+            None
+        };
+
+        let debug_view_pass_bind_group = if frame_graph.is_pass_enabled(passes.debug_view) {
+            Some(device.create_bind_group(&bind_group_descriptor!(
+                &bind_group_layouts.debug_view_pass_bind_group_layout,
+                0 => TextureView(&depth_view),
+                1 => TextureView(normal_view),
+                2 => TextureView(albedo_view),
+                3 => TextureView(material_view),
+                4 => TextureView(&stencil_view),
+                5 => TextureView(color_view),
+            )))
+        } else {
+            None
+        };
+
+        let depth_capture_pass_bind_group = device.create_bind_group(&bind_group_descriptor!(
+            &bind_group_layouts.texel_copy_pass_bind_group_layout,
+            0 => TextureView(&depth_view),
         ));
 
+        let frame_capture_pass_bind_group = if frame_graph.is_pass_enabled(passes.frame_capture) {
+            Some(device.create_bind_group(&bind_group_descriptor!(
+                &bind_group_layouts.texel_copy_pass_bind_group_layout,
+                0 => TextureView(frame_graph.get_view(attachments.frame_capture)),
+            )))
+        } else {
+            None
+        };
+
         Self {
-            size,
-            position_view,
-            normal_view,
-            albedo_view,
-            material_view,
-            color_view,
-            depth_stencil_view,
+            size: frame_graph.graph.get_frame_size(),
             depth_view,
-            stencil_view,
             volumetric_pass_bind_group,
             light_pass_bind_group,
+            ambient_occlusion,
+            temporal_anti_aliasing,
+            bloom,
+            auto_exposure_bind_group,
             post_processing_pass_bind_group,
+            fxaa_pass_bind_group,
+            debug_view_pass_bind_group,
+            depth_capture_pass_bind_group,
+            frame_capture_pass_bind_group,
+        }
+    }
+}
+
+const SHADOW_MAP_SIZE: u32 = 2048;
+
+struct ShadowMap {
+    view_matrix: glam::Mat4,
+    depth_view: wgpu::TextureView,
+    camera_uniforms_buffer: wgpu::Buffer,
+    camera_uniforms_bind_group: wgpu::BindGroup,
+    mvp_matrix_buffer: wgpu::Buffer,
+    mvp_matrix_bind_group: wgpu::BindGroup,
+}
+
+/// One layer of a Depth32Float texture array per shadow casting light
+struct ShadowMaps {
+    instance_capacity: usize,
+    shadow_maps: Vec<ShadowMap>,
+    attributes_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMaps {
+    fn new(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, compute_pipelines: &ComputePipelines, buffers: &AttributeAndUniformBuffers, shadow_sampler: &wgpu::Sampler, shadow_map_count: usize) -> Self {
+        // The texture array needs at least one layer, even if no light casts a shadow
+        let layer_count = shadow_map_count.max(1) as u32;
+        let mut texture_descriptor = create_attachment!(wgpu::Extent3d { width: SHADOW_MAP_SIZE, height: SHADOW_MAP_SIZE, depth: layer_count }, Depth32Float);
+        texture_descriptor.usage = wgpu::TextureUsage::SAMPLED|wgpu::TextureUsage::OUTPUT_ATTACHMENT;
+        let texture = device.create_texture(&texture_descriptor);
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+        let attributes_buffer = create_instance_buffer(device, std::mem::size_of::<ShadowMapAttributes>()*layer_count as usize, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::COPY_DST);
+        let shadow_maps = (0..shadow_map_count as u32).map(|layer| {
+            let depth_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: None,
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                level_count: std::num::NonZeroU32::new(1),
+                base_array_layer: layer,
+                array_layer_count: std::num::NonZeroU32::new(1),
+            });
+            let camera_uniforms_buffer = create_instance_buffer(device, std::mem::size_of::<CameraUniforms>(), wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::UNIFORM);
+            let camera_uniforms_bind_group = device.create_bind_group(&bind_group_descriptor!(
+                &bind_group_layouts.camera_uniforms_bind_group_layout,
+                0 => Buffer(camera_uniforms_buffer.slice(..)),
+            ));
+            let mvp_matrix_buffer = create_instance_buffer(device, std::mem::size_of::<glam::Mat4>()*buffers.instance_capacity, wgpu::BufferUsage::STORAGE|wgpu::BufferUsage::VERTEX);
+            let mvp_matrix_bind_group = device.create_bind_group(&bind_group_descriptor!(
+                &compute_pipelines.mvp_matrix_bind_group_layout,
+                0 => Buffer(buffers.instances_world_matrix_buffer.slice(..)),
+                1 => Buffer(mvp_matrix_buffer.slice(..)),
+            ));
+            ShadowMap {
+                view_matrix: glam::Mat4::identity(),
+                depth_view,
+                camera_uniforms_buffer,
+                camera_uniforms_bind_group,
+                mvp_matrix_buffer,
+                mvp_matrix_bind_group,
+            }
+        }).collect();
+        let bind_group = device.create_bind_group(&bind_group_descriptor!(
+            &bind_group_layouts.shadow_maps_bind_group_layout,
+            0 => Sampler(shadow_sampler),
+            1 => TextureView(&array_view),
+            2 => Buffer(attributes_buffer.slice(..)),
+        ));
+        Self {
+            instance_capacity: buffers.instance_capacity,
+            shadow_maps,
+            attributes_buffer,
+            bind_group,
         }
     }
 }
@@ -923,10 +2354,45 @@ pub struct Renderer {
     pub bind_group_layouts: BindGroupLayouts,
     pub render_pipelines: RenderPipelines,
     compute_pipelines: ComputePipelines,
+    frame_graph: FrameRenderGraph,
     gbuffer: GBuffer,
     camera_uniforms_bind_group: wgpu::BindGroup,
     parallel_light_mesh: crate::assets::Mesh,
     spot_light_mesh: crate::assets::Mesh,
+    light_instances: LightInstances,
+    render_options: RenderOptions,
+    shadow_sampler: wgpu::Sampler,
+    shadow_pass_bind_group: wgpu::BindGroup,
+    shadow_maps: ShadowMaps,
+    post_processing_sampler: wgpu::Sampler,
+    motion_vector_instances_bind_group: wgpu::BindGroup,
+    frame_index: usize,
+    /// Time since the previous frame, set by render_frame
+    delta_time: f32,
+    temporal_history_valid: bool,
+}
+
+const TEMPORAL_JITTER_SAMPLE_COUNT: usize = 8;
+
+/// Low discrepancy sequence in the unit interval
+fn halton(mut index: usize, base: usize) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction*(index%base) as f32;
+        index /= base;
+    }
+    result
+}
+
+fn create_motion_vector_instances_bind_group(device: &wgpu::Device, bind_group_layouts: &BindGroupLayouts, buffers: &AttributeAndUniformBuffers) -> wgpu::BindGroup {
+    device.create_bind_group(&bind_group_descriptor!(
+        &bind_group_layouts.motion_vector_instances_bind_group_layout,
+        0 => Buffer(buffers.instances_previous_world_matrix_buffer.slice(..)),
+        1 => Buffer(buffers.instances_inverse_world_matrix_buffer.slice(..)),
+        2 => Buffer(buffers.previous_camera_uniforms_buffer.slice(..)),
+    ))
 }
 
 impl Renderer {
@@ -941,42 +2407,129 @@ impl Renderer {
             mipmap_filter: wgpu::FilterMode::Linear,
             lod_min_clamp: 0.0,
             lod_max_clamp: std::f32::INFINITY,
-            compare: None, // wgpu::CompareFunction::LessEqual
+            compare: None, // wgpu::CompareFunction::GreaterEqual
             anisotropy_clamp: None, // std::num::NonZeroU8::new(16),
         });
         let buffers = AttributeAndUniformBuffers::new(device);
         let bind_group_layouts = BindGroupLayouts::new(device);
         let render_pipelines = RenderPipelines::new(device, &bind_group_layouts, render_options, asset_pack);
         let compute_pipelines = ComputePipelines::new(&device, &buffers, asset_pack);
+        let post_processing_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare: None,
+            anisotropy_clamp: None,
+        });
         let size = wgpu::Extent3d { width: swap_chain_descriptor.width, height: swap_chain_descriptor.height, depth: 1 };
-        let gbuffer = GBuffer::new(device, &bind_group_layouts, size);
+        let mut frame_graph = FrameRenderGraph::new(size);
+        frame_graph.apply_render_options(render_options, false);
+        frame_graph.graph.compile(device);
+        let gbuffer = GBuffer::new(device, &bind_group_layouts, &compute_pipelines, &buffers, &post_processing_sampler, &frame_graph);
         let camera_uniforms_bind_group = device.create_bind_group(&bind_group_descriptor!(
             &bind_group_layouts.camera_uniforms_bind_group_layout,
             0 => Buffer(buffers.camera_uniforms_buffer.slice(0..std::mem::size_of::<CameraUniforms>() as wgpu::BufferAddress)),
         ));
+        let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            // Reverse-Z: the fragment is lit if it is at least as close to the light as the occluder
+            compare: Some(wgpu::CompareFunction::GreaterEqual),
+            anisotropy_clamp: None,
+        });
+        let shadow_pass_bind_group = device.create_bind_group(&bind_group_descriptor!(
+            &bind_group_layouts.shadow_pass_bind_group_layout,
+        ));
+        let shadow_maps = ShadowMaps::new(device, &bind_group_layouts, &compute_pipelines, &buffers, &shadow_sampler, 0);
+        let motion_vector_instances_bind_group = create_motion_vector_instances_bind_group(device, &bind_group_layouts, &buffers);
         Self {
             sampler,
             buffers,
             bind_group_layouts,
             render_pipelines,
             compute_pipelines,
+            frame_graph,
             gbuffer,
             camera_uniforms_bind_group,
             parallel_light_mesh: crate::assets::Mesh::new_light_cube(device, true),
-            spot_light_mesh: crate::assets::Mesh::new_light_cone(device, 8),
+            spot_light_mesh: crate::assets::Mesh::new_light_cone(device, crate::light::SPOT_LIGHT_CONE_RESOLUTION),
+            light_instances: LightInstances::default(),
+            render_options: *render_options,
+            shadow_sampler,
+            shadow_pass_bind_group,
+            shadow_maps,
+            post_processing_sampler,
+            motion_vector_instances_bind_group,
+            frame_index: 0,
+            delta_time: 0.0,
+            temporal_history_valid: false,
         }
     }
 
+    /// Compiles the render graph and recreates the bind groups, only attachments which changed get new textures
+    fn rebuild_gbuffer(&mut self, device: &wgpu::Device) {
+        if self.frame_graph.graph.compile(device) {
+            self.temporal_history_valid = false;
+        }
+        self.gbuffer = GBuffer::new(device, &self.bind_group_layouts, &self.compute_pipelines, &self.buffers, &self.post_processing_sampler, &self.frame_graph);
+    }
+
     pub fn resize(&mut self, device: &wgpu::Device, swap_chain_descriptor: &wgpu::SwapChainDescriptor) {
         let size = wgpu::Extent3d { width: swap_chain_descriptor.width, height: swap_chain_descriptor.height, depth: 1 };
-        self.gbuffer = GBuffer::new(&device, &self.bind_group_layouts, size);
+        self.frame_graph.graph.resize(size);
+        self.rebuild_gbuffer(device);
     }
 
     pub fn apply_render_options(&mut self, device: &wgpu::Device, render_options: &RenderOptions, asset_pack: &AssetPack) {
         self.render_pipelines = RenderPipelines::new(&device, &self.bind_group_layouts, render_options, asset_pack);
+        self.render_options = *render_options;
+        let capture_frame = self.frame_graph.is_pass_enabled(self.frame_graph.passes.frame_capture);
+        self.frame_graph.apply_render_options(render_options, capture_frame);
+        self.rebuild_gbuffer(device);
+        self.temporal_history_valid = false;
+    }
+
+    /// The render pipelines are recreated by apply_render_options instead
+    pub fn recreate_compute_pipelines(&mut self, device: &wgpu::Device, asset_pack: &AssetPack) {
+        self.compute_pipelines.recreate_pipelines(device, asset_pack);
+    }
+
+    pub fn get_render_options(&self) -> &RenderOptions {
+        &self.render_options
+    }
+
+    /// Keeps the next frames in a texture which can be read back by get_captured_frame, together with the GBuffer.
+    /// Has to be set before the scene is rendered, as it can change the textures of the attachments.
+    pub fn set_frame_capture(&mut self, device: &wgpu::Device, capture_frame: bool) {
+        if self.frame_graph.is_pass_enabled(self.frame_graph.passes.frame_capture) != capture_frame {
+            self.frame_graph.apply_render_options(&self.render_options, capture_frame);
+            self.rebuild_gbuffer(device);
+        }
+    }
+
+    /// Grows the instance buffers, has to be called before update_instances
+    pub fn reserve_instances(&mut self, device: &wgpu::Device, instance_count: usize) {
+        if self.buffers.reserve_instances(device, instance_count) {
+            self.compute_pipelines.update_bind_groups(device, &self.buffers);
+            self.motion_vector_instances_bind_group = create_motion_vector_instances_bind_group(device, &self.bind_group_layouts, &self.buffers);
+        }
     }
 
     pub fn update_instances<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, queue: &wgpu::Queue, world_matrices: &[glam::Mat4]) {
+        assert!(world_matrices.len() <= self.buffers.instance_capacity);
         let data = unsafe { std::slice::from_raw_parts(world_matrices.as_ptr() as *const u8, world_matrices.len()*std::mem::size_of::<glam::Mat4>()) };
         queue.write_buffer(&self.buffers.instances_world_matrix_buffer, 0, data);
 
@@ -985,54 +2538,175 @@ impl Renderer {
         pass.set_bind_group(0, &self.compute_pipelines.instance_matrices_bind_group, &[]);
         let x_work_group_count = 32;
         pass.dispatch((world_matrices.len() as u32+x_work_group_count-1)/x_work_group_count, 1, 1);
+    }
 
-        let mut light_settings_buffer = [LightAttributes::default(); 4];
-        let spot_light_settings = unsafe { crate::transmute_slice_mut::<LightAttributes, SpotLightAttributes>(&mut light_settings_buffer[0..2]) };
-        spot_light_settings[0] = SpotLightAttributes {
-            color: glam::Vec3::splat(1.0),
-            radius: 2.0,
-            outer_angle_cos: (std::f32::consts::PI*0.25).cos(),
-            inner_angle_cos: (std::f32::consts::PI*0.2).cos(),
+    /// Uploads the attributes of all lights and assigns the shadow maps, has to be called after reserve_instances.
+    /// The camera is the one the frame is rendered from, the shadow cascades are fit to it.
+    pub fn update_lights(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lights: &crate::light::Lights, camera: &crate::camera::Camera) {
+        let enable_shadow_mapping = self.render_options.enable_shadow_mapping;
+        let mut shadow_cascades: Vec<crate::light::ShadowCascade> = Vec::new();
+        // Returns the index of the first shadow map, the cascades of a light are consecutive
+        let mut shadow_map_index = |casts_shadow: bool, cascades: Vec<crate::light::ShadowCascade>| -> i32 {
+            if !enable_shadow_mapping || !casts_shadow {
+                return -1;
+            }
+            let first_index = shadow_cascades.len() as i32;
+            shadow_cascades.extend(cascades);
+            first_index
         };
-        light_settings_buffer[2] = LightAttributes {
-            color: glam::Vec3::splat(1.0),
+        let light_settings_offsets = AttributeAndUniformBuffers::light_settings_offsets(self.buffers.instance_capacity);
+        let ambient_light_settings = LightAttributes {
+            color: lights.get_ambient_color(),
         };
-        light_settings_buffer[3] = LightAttributes {
-            color: glam::Vec3::splat(0.8),
-        };
-        queue.write_buffer(&self.buffers.light_settings_buffer, 0, unsafe { crate::transmute_slice::<LightAttributes, u8>(&light_settings_buffer[..]) });
+        queue.write_buffer(&self.buffers.light_settings_buffer, light_settings_offsets[3], unsafe { crate::transmute_slice::<LightAttributes, u8>(&[ambient_light_settings]) });
+        self.light_instances.spot_lights = lights.spot_lights.get_instances();
+        if !lights.spot_lights.is_empty() {
+            let spot_light_settings: Vec<SpotLightAttributes> = lights.spot_lights.iter().map(|light| SpotLightAttributes {
+                color: light.color*light.intensity,
+                radius: light.radius,
+                outer_angle_cos: light.outer_angle.cos(),
+                inner_angle_cos: light.inner_angle.cos(),
+                shadow_map_index: shadow_map_index(light.casts_shadow, vec![crate::light::ShadowCascade::new(light.get_shadow_camera())]),
+            }).collect();
+            let offset = light_settings_offsets[0]+(std::mem::size_of::<SpotLightAttributes>()*self.light_instances.spot_lights.start as usize) as wgpu::BufferAddress;
+            queue.write_buffer(&self.buffers.light_settings_buffer, offset, unsafe { crate::transmute_slice::<SpotLightAttributes, u8>(&spot_light_settings[..]) });
+        }
+        self.light_instances.point_lights = lights.point_lights.get_instances();
+        if !lights.point_lights.is_empty() {
+            let point_light_settings: Vec<LightAttributes> = lights.point_lights.iter().map(|light| LightAttributes {
+                color: light.color*light.intensity,
+            }).collect();
+            let offset = light_settings_offsets[1]+(std::mem::size_of::<LightAttributes>()*self.light_instances.point_lights.start as usize) as wgpu::BufferAddress;
+            queue.write_buffer(&self.buffers.light_settings_buffer, offset, unsafe { crate::transmute_slice::<LightAttributes, u8>(&point_light_settings[..]) });
+        }
+        self.light_instances.parallel_lights = lights.parallel_lights.get_instances();
+        if !lights.parallel_lights.is_empty() {
+            let parallel_light_settings: Vec<ParallelLightAttributes> = lights.parallel_lights.iter().map(|light| {
+                let cascades = light.get_shadow_cascades(camera, SHADOW_MAP_SIZE);
+                let shadow_cascade_count = cascades.len() as i32;
+                ParallelLightAttributes {
+                    color: light.color*light.intensity,
+                    shadow_map_index: shadow_map_index(light.casts_shadow, cascades),
+                    shadow_cascade_count,
+                }
+            }).collect();
+            let offset = light_settings_offsets[2]+(std::mem::size_of::<ParallelLightAttributes>()*self.light_instances.parallel_lights.start as usize) as wgpu::BufferAddress;
+            queue.write_buffer(&self.buffers.light_settings_buffer, offset, unsafe { crate::transmute_slice::<ParallelLightAttributes, u8>(&parallel_light_settings[..]) });
+        }
+        if shadow_cascades.len() != self.shadow_maps.shadow_maps.len() || self.buffers.instance_capacity != self.shadow_maps.instance_capacity {
+            self.shadow_maps = ShadowMaps::new(device, &self.bind_group_layouts, &self.compute_pipelines, &self.buffers, &self.shadow_sampler, shadow_cascades.len());
+        }
+        let shadow_map_settings: Vec<ShadowMapAttributes> = shadow_cascades.iter().map(|cascade| ShadowMapAttributes {
+            view_matrix: cascade.camera.get_view_matrix(),
+            split_far: cascade.split_far,
+            blend_distance: cascade.blend_distance,
+            padding: [0.0; 2],
+        }).collect();
+        queue.write_buffer(&self.shadow_maps.attributes_buffer, 0, unsafe { crate::transmute_slice::<ShadowMapAttributes, u8>(&shadow_map_settings[..]) });
+        for (shadow_map, cascade) in self.shadow_maps.shadow_maps.iter_mut().zip(shadow_cascades.iter()) {
+            let camera_uniforms = CameraUniforms {
+                world_matrix: cascade.camera.get_world_matrix(),
+                projection_matrix: cascade.camera.get_projection_matrix(),
+                inverse_view_matrix: cascade.camera.get_inverse_view_matrix(),
+                view_matrix: cascade.camera.get_view_matrix(),
+            };
+            queue.write_buffer(&shadow_map.camera_uniforms_buffer, 0, unsafe { crate::transmute_slice::<CameraUniforms, u8>(&[camera_uniforms]) });
+            shadow_map.view_matrix = cascade.camera.get_view_matrix();
+        }
+    }
+
+    /// Subpixel offset of the projection in normalized device coordinates, only temporal anti aliasing jitters
+    fn get_jitter(&self, frame_index: usize) -> glam::Vec2 {
+        if self.render_options.anti_aliasing != AntiAliasing::Temporal {
+            return glam::Vec2::zero();
+        }
+        let sample_index = frame_index%TEMPORAL_JITTER_SAMPLE_COUNT+1;
+        glam::Vec2::new(
+            (halton(sample_index, 2)-0.5)*2.0/self.gbuffer.size.width as f32,
+            (halton(sample_index, 3)-0.5)*2.0/self.gbuffer.size.height as f32,
+        )
     }
 
     pub fn update_camera<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, queue: &wgpu::Queue, instance_count: usize, camera: &crate::camera::Camera) {
+        let jitter = self.get_jitter(self.frame_index);
+        let jitter_matrix = glam::Mat4::from_translation(glam::Vec3::new(jitter.x(), jitter.y(), 0.0));
+        let view_matrix = jitter_matrix*camera.get_view_matrix();
         let camera_uniforms = CameraUniforms {
             world_matrix: camera.get_world_matrix(),
-            projection_matrix: camera.get_projection_matrix(),
-            inverse_view_matrix: camera.get_inverse_view_matrix(),
+            projection_matrix: jitter_matrix*camera.get_projection_matrix(),
+            inverse_view_matrix: camera.get_inverse_view_matrix()*jitter_matrix.inverse(),
+            view_matrix,
         };
         let data = unsafe { std::slice::from_raw_parts(&camera_uniforms as *const _ as *const u8, std::mem::size_of::<CameraUniforms>()) };
         queue.write_buffer(&self.buffers.camera_uniforms_buffer, 0, data);
 
         let mut pass = encoder.begin_compute_pass();
         pass.set_pipeline(&self.compute_pipelines.mvp_matrix_pipeline);
-        let camera_uniforms = camera.get_view_matrix().to_cols_array();
+        let camera_uniforms = view_matrix.to_cols_array();
         pass.set_push_constants(0, unsafe { crate::transmute_slice::<f32, u32>(&camera_uniforms[..]) });
         pass.set_bind_group(0, &self.compute_pipelines.mvp_matrix_bind_group, &[]);
         let x_work_group_count = 32;
         pass.dispatch((instance_count as u32+x_work_group_count-1)/x_work_group_count, 1, 1);
     }
 
+    /// Computes the mvp matrices of all instances for each shadow map
+    pub fn update_shadow_maps<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, instance_count: usize) {
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_pipeline(&self.compute_pipelines.mvp_matrix_pipeline);
+        for shadow_map in self.shadow_maps.shadow_maps.iter() {
+            let view_matrix = shadow_map.view_matrix.to_cols_array();
+            pass.set_push_constants(0, unsafe { crate::transmute_slice::<f32, u32>(&view_matrix[..]) });
+            pass.set_bind_group(0, &shadow_map.mvp_matrix_bind_group, &[]);
+            let x_work_group_count = 32;
+            pass.dispatch((instance_count as u32+x_work_group_count-1)/x_work_group_count, 1, 1);
+        }
+    }
+
+    pub fn get_shadow_map_count(&self) -> usize {
+        self.shadow_maps.shadow_maps.len()
+    }
+
+    /// Shadow casters are drawn with the general_shadow_pipeline or render_sphere_shadows
+    pub fn render_shadow_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, shadow_map_index: usize) -> wgpu::RenderPass<'a> {
+        let shadow_map = &self.shadow_maps.shadow_maps[shadow_map_index];
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &shadow_map.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_bind_group(0, &self.shadow_pass_bind_group, &[]);
+        render_pass.set_bind_group(1, &shadow_map.camera_uniforms_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.buffers.instances_world_matrix_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.buffers.instances_inverse_world_matrix_buffer.slice(..));
+        render_pass.set_vertex_buffer(2, shadow_map.mvp_matrix_buffer.slice(..));
+        render_pass
+    }
+
+    /// Ray traced spheres (e.g. planets and stars) as shadow casters, the radius is the length of the z axis of their world matrix
+    pub fn render_sphere_shadows<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, instances_indices: std::ops::Range<u32>) {
+        render_pass.set_pipeline(&self.render_pipelines.sphere_shadow_pipeline);
+        render_pass.draw(0..4 as u32, instances_indices);
+    }
+
     pub fn render_surface_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        let attachments = &self.frame_graph.attachments;
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[
-                clear_attachment!(&self.gbuffer.position_view, (0.0, 0.0, 0.0, 0.0)),
-                clear_attachment!(&self.gbuffer.normal_view, (0.0, 0.0, 0.0, 0.0)),
-                clear_attachment!(&self.gbuffer.albedo_view, (0.0, 0.0, 0.0, 0.0)),
-                clear_attachment!(&self.gbuffer.material_view, (0.0, 0.0, 0.0, 0.0)),
+                clear_attachment!(self.frame_graph.get_view(attachments.instance), (0.0, 0.0, 0.0, 0.0)),
+                clear_attachment!(self.frame_graph.get_view(attachments.normal), (0.0, 0.0, 0.0, 0.0)),
+                clear_attachment!(self.frame_graph.get_view(attachments.albedo), (0.0, 0.0, 0.0, 0.0)),
+                clear_attachment!(self.frame_graph.get_view(attachments.material), (0.0, 0.0, 0.0, 0.0)),
             ],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                attachment: &self.gbuffer.depth_stencil_view,
+                attachment: self.frame_graph.get_view(attachments.depth_stencil),
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Clear(0.0),
                     store: true,
                 }),
                 stencil_ops: None,
@@ -1049,14 +2723,12 @@ impl Renderer {
     pub fn render_volumetric_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[
-                clear_attachment!(&self.gbuffer.color_view, (0.0, 0.0, 0.0, 0.0)),
+                clear_attachment!(self.frame_graph.get_view(self.frame_graph.attachments.color), (0.0, 0.0, 0.0, 0.0)),
             ],
+            // Read only, so that the depth can be sampled to reconstruct the positions at the same time
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
                 attachment: &self.gbuffer.depth_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: false,
-                }),
+                depth_ops: None,
                 stencil_ops: None,
             }),
         });
@@ -1068,52 +2740,418 @@ impl Renderer {
         render_pass
     }
 
-    pub fn render_frame(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+    /// Executes the passes of the render graph which follow the surface and volumetric pass, each in a profiler scope named after it.
+    /// delta_time is the time since the previous frame, the auto exposure adapts over time.
+    pub fn render_frame(&mut self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView, delta_time: f32, profiler: &mut crate::profiler::Profiler) {
+        self.delta_time = delta_time;
+        let renderer: &Self = self;
+        renderer.frame_graph.graph.execute(|name, execute| profiler.scope(encoder, name, |encoder| execute(renderer, encoder, frame_view)));
+        self.temporal_history_valid = self.frame_graph.is_pass_enabled(self.frame_graph.passes.temporal_anti_aliasing);
+        // Keep the matrices of this frame for the motion vectors of the next frame
+        encoder.copy_buffer_to_buffer(&self.buffers.instances_world_matrix_buffer, 0, &self.buffers.instances_previous_world_matrix_buffer, 0, (std::mem::size_of::<glam::Mat4>()*self.buffers.instance_capacity) as wgpu::BufferAddress);
+        encoder.copy_buffer_to_buffer(&self.buffers.camera_uniforms_buffer, 0, &self.buffers.previous_camera_uniforms_buffer, 0, std::mem::size_of::<CameraUniforms>() as wgpu::BufferAddress);
+        self.frame_index += 1;
+    }
+
+    /// Copies the frame, which was rendered into the frame capture attachment, onto the actual frame
+    fn render_frame_capture_pass(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        let bind_group = match &self.gbuffer.frame_capture_pass_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[
+                load_attachment!(frame_view),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipelines.frame_capture_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..4 as u32, 0..1);
+    }
+
+    /// Bgra8UnormSrgb texture of the size of the GBuffer, only exists while the frame capture is set
+    pub fn get_captured_frame(&self) -> &wgpu::Texture {
+        self.frame_graph.get_texture(self.frame_graph.attachments.frame_capture)
+    }
+
+    pub fn get_size(&self) -> wgpu::Extent3d {
+        self.gbuffer.size
+    }
+
+    /// Returns a texture which can be copied into a buffer.
+    /// Depth stencil formats can not be copied, so the depth and position are converted into a texture which only lives as long as the capture.
+    pub fn capture_gbuffer_attachment(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, attachment: GBufferAttachment) -> CapturedAttachment {
+        let attachments = &self.frame_graph.attachments;
+        let pipeline = match attachment {
+            GBufferAttachment::Position => &self.render_pipelines.position_capture_pipeline,
+            GBufferAttachment::Normal => return CapturedAttachment::GBuffer(self.frame_graph.get_texture(attachments.normal)),
+            GBufferAttachment::Albedo => return CapturedAttachment::GBuffer(self.frame_graph.get_texture(attachments.albedo)),
+            GBufferAttachment::Material => return CapturedAttachment::GBuffer(self.frame_graph.get_texture(attachments.material)),
+            GBufferAttachment::Color => return CapturedAttachment::GBuffer(self.frame_graph.get_texture(attachments.color)),
+            GBufferAttachment::Depth => &self.render_pipelines.depth_capture_pipeline,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: self.gbuffer.size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: attachment.get_format(),
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT|wgpu::TextureUsage::COPY_SRC,
+        });
+        {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[
+                    clear_attachment!(&view, (0.0, 0.0, 0.0, 0.0)),
+                ],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.gbuffer.depth_capture_pass_bind_group, &[]);
+            if attachment == GBufferAttachment::Position {
+                render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
+            }
+            render_pass.draw(0..4 as u32, 0..1);
+        }
+        CapturedAttachment::Converted(texture)
+    }
+
+    /// Shows a single channel of the GBuffer instead of the tonemapped color
+    fn render_debug_view_pass(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        let bind_group = match &self.gbuffer.debug_view_pass_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let debug_view_settings = DebugViewSettings {
+            debug_view: self.render_options.debug_view as i32,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[
+                load_attachment!(self.frame_graph.get_output_view(frame_view)),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipelines.debug_view_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
+        render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<DebugViewSettings, u32>(&[debug_view_settings]) });
+        render_pass.draw(0..4 as u32, 0..1);
+    }
+
+    /// Screen space motion of each pixel since the previous frame, from the previous and current instance matrices
+    fn render_motion_vector_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let bind_groups = match &self.gbuffer.temporal_anti_aliasing {
+            Some(bind_groups) => bind_groups,
+            None => return,
+        };
+        // Texture coordinates point downwards
+        let to_texture_space = |jitter: glam::Vec2| [jitter.x()*0.5, -jitter.y()*0.5];
+        let motion_vector_settings = MotionVectorSettings {
+            jitter: to_texture_space(self.get_jitter(self.frame_index)),
+            previous_jitter: to_texture_space(self.get_jitter(self.frame_index.wrapping_sub(1))),
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[
+                clear_attachment!(self.frame_graph.get_view(self.frame_graph.attachments.motion_vector), (0.0, 0.0, 0.0, 0.0)),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipelines.motion_vector_pipeline);
+        render_pass.set_bind_group(0, &bind_groups.motion_vector_pass, &[]);
+        render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.motion_vector_instances_bind_group, &[]);
+        render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<MotionVectorSettings, u32>(&[motion_vector_settings]) });
+        render_pass.draw(0..4 as u32, 0..1);
+    }
+
+    /// Blends the color buffer into the reprojected history and copies the result back into the color buffer
+    fn render_temporal_anti_aliasing_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let bind_groups = match &self.gbuffer.temporal_anti_aliasing {
+            Some(bind_groups) => bind_groups,
+            None => return,
+        };
+        let attachments = &self.frame_graph.attachments;
+        let history_index = self.frame_index%2;
+        let temporal_anti_aliasing_settings = TemporalAntiAliasingSettings {
+            blend_factor: if self.temporal_history_valid { 0.1 } else { 1.0 },
+        };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[
+                    clear_attachment!(self.frame_graph.get_view(attachments.temporal_history[history_index]), (0.0, 0.0, 0.0, 0.0)),
+                ],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.render_pipelines.temporal_anti_aliasing_pipeline);
+            render_pass.set_bind_group(0, &bind_groups.temporal_anti_aliasing_passes[history_index], &[]);
+            render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<TemporalAntiAliasingSettings, u32>(&[temporal_anti_aliasing_settings]) });
+            render_pass.draw(0..4 as u32, 0..1);
+        }
+        encoder.copy_texture_to_texture(
+            wgpu::TextureCopyView {
+                texture: self.frame_graph.get_texture(attachments.temporal_history[history_index]),
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TextureCopyView {
+                texture: self.frame_graph.get_texture(attachments.color),
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            self.gbuffer.size,
+        );
+    }
+
+    /// Copies the lit HDR color of the GBuffer into a layer of a texture of the same size and format (Rgba16Float)
+    pub fn copy_color_to_texture(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, array_layer: u32) {
+        encoder.copy_texture_to_texture(
+            wgpu::TextureCopyView {
+                texture: self.frame_graph.get_texture(self.frame_graph.attachments.color),
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: array_layer },
+            },
+            wgpu::Extent3d { width: self.gbuffer.size.width, height: self.gbuffer.size.height, depth: 1 },
+        );
+    }
+
+    /// Without ambient occlusion the buffer is cleared to one, so that the ambient light is not occluded
+    fn render_ambient_occlusion_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let quality = self.render_options.ambient_occlusion_quality;
+        let ambient_occlusion_view = self.frame_graph.get_view(self.frame_graph.attachments.ambient_occlusion);
+        let bind_groups = match &self.gbuffer.ambient_occlusion {
+            Some(bind_groups) => bind_groups,
+            None => {
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[
+                        clear_attachment!(ambient_occlusion_view, (1.0, 1.0, 1.0, 1.0)),
+                    ],
+                    depth_stencil_attachment: None,
+                });
+                return;
+            },
+        };
+        {
+            let ambient_occlusion_settings = AmbientOcclusionSettings {
+                radius: self.render_options.ambient_occlusion_radius,
+                intensity: self.render_options.ambient_occlusion_intensity,
+                sample_count: quality.get_sample_count(),
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[
+                    clear_attachment!(ambient_occlusion_view, (1.0, 1.0, 1.0, 1.0)),
+                ],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.render_pipelines.ambient_occlusion_pipeline);
+            render_pass.set_bind_group(0, &bind_groups.pass, &[]);
+            render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
+            render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<AmbientOcclusionSettings, u32>(&[ambient_occlusion_settings]) });
+            render_pass.draw(0..4 as u32, 0..1);
+        }
+        // Horizontal into the blur buffer, then vertical back into the ambient occlusion buffer
+        let passes = [
+            ([1, 0], self.frame_graph.get_view(self.frame_graph.attachments.ambient_occlusion_blur), &bind_groups.blur_passes[0]),
+            ([0, 1], ambient_occlusion_view, &bind_groups.blur_passes[1]),
+        ];
+        for (direction, target_view, bind_group) in passes.iter() {
+            let blur_settings = AmbientOcclusionBlurSettings {
+                direction: *direction,
+                blur_radius: quality.get_blur_radius(),
+                inverse_radius: 1.0/self.render_options.ambient_occlusion_radius,
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[
+                    clear_attachment!(target_view, (1.0, 1.0, 1.0, 1.0)),
+                ],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.render_pipelines.ambient_occlusion_blur_pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
+            render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<AmbientOcclusionBlurSettings, u32>(&[blur_settings]) });
+            render_pass.draw(0..4 as u32, 0..1);
+        }
+    }
+
+    /// Starts with the ambient occlusion, which the ambient light is multiplied with
+    pub fn render_light_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.render_ambient_occlusion_pass(encoder);
+        self.render_light_volumes(encoder);
+    }
+
+    /// Adds the ambient light and the light volumes to the color buffer
+    fn render_light_volumes(&self, encoder: &mut wgpu::CommandEncoder) {
+        // The light volumes are counted from zero, without the ambient light and the volumetric pass
+        let enable_light_overdraw = self.render_options.debug_view == DebugView::LightOverdraw;
+        let color_view = self.frame_graph.get_view(self.frame_graph.attachments.color);
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[
-                    load_attachment!(&self.gbuffer.color_view),
+                    if enable_light_overdraw { clear_attachment!(color_view, (0.0, 0.0, 0.0, 0.0)) } else { load_attachment!(color_view) },
                 ],
+                // Read only, so that the depth can be sampled to reconstruct the positions at the same time
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                    attachment: &self.gbuffer.depth_stencil_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: false,
-                    }),
+                    attachment: self.frame_graph.get_view(self.frame_graph.attachments.depth_stencil),
+                    depth_ops: None,
                     stencil_ops: None,
                 }),
             });
-            render_pass.set_vertex_buffer(0, self.buffers.instances_world_matrix_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.buffers.instances_inverse_world_matrix_buffer.slice(..));
-            render_pass.set_vertex_buffer(2, self.buffers.instances_mvp_matrix_buffer.slice(..));
+            let light_settings_offsets = AttributeAndUniformBuffers::light_settings_offsets(self.buffers.instance_capacity);
+            let light_instances = &self.light_instances;
 
             render_pass.set_pipeline(&self.render_pipelines.ambient_light_pipeline);
             render_pass.set_bind_group(0, &self.gbuffer.light_pass_bind_group, &[]);
             render_pass.set_bind_group(1, &self.camera_uniforms_bind_group, &[]);
-            render_pass.draw(0..4 as u32, 0..1);
-
-            render_pass.set_pipeline(&self.render_pipelines.spot_light_pipeline);
-            render_pass.set_vertex_buffer(3, self.buffers.light_settings_buffer.slice(0..std::mem::size_of::<SpotLightAttributes>() as wgpu::BufferAddress*1));
-            self.spot_light_mesh.render(&mut render_pass, 0..1);
+            render_pass.set_bind_group(2, &self.shadow_maps.bind_group, &[]);
+            if !enable_light_overdraw {
+                render_pass.set_vertex_buffer(0, self.buffers.light_settings_buffer.slice(light_settings_offsets[3]..));
+                render_pass.draw(0..4 as u32, 0..1);
+            }
 
-            render_pass.set_pipeline(&self.render_pipelines.point_light_pipeline);
-            render_pass.set_vertex_buffer(3, self.buffers.light_settings_buffer.slice(std::mem::size_of::<LightAttributes>() as wgpu::BufferAddress*1..));
-            render_pass.draw(0..4 as u32, 1..2);
+            render_pass.set_vertex_buffer(0, self.buffers.instances_world_matrix_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, self.buffers.instances_inverse_world_matrix_buffer.slice(..));
+            render_pass.set_vertex_buffer(2, self.buffers.instances_mvp_matrix_buffer.slice(..));
 
-            render_pass.set_pipeline(&self.render_pipelines.parallel_light_pipeline);
-            self.parallel_light_mesh.render(&mut render_pass, 2..3);
+            if light_instances.spot_lights.start < light_instances.spot_lights.end {
+                render_pass.set_pipeline(&self.render_pipelines.spot_light_pipeline);
+                render_pass.set_vertex_buffer(3, self.buffers.light_settings_buffer.slice(light_settings_offsets[0]..light_settings_offsets[1]));
+                self.spot_light_mesh.render(&mut render_pass, light_instances.spot_lights.clone());
+            }
+
+            if light_instances.point_lights.start < light_instances.point_lights.end {
+                render_pass.set_pipeline(&self.render_pipelines.point_light_pipeline);
+                render_pass.set_vertex_buffer(3, self.buffers.light_settings_buffer.slice(light_settings_offsets[1]..light_settings_offsets[2]));
+                render_pass.draw(0..4 as u32, light_instances.point_lights.clone());
+            }
+
+            if light_instances.parallel_lights.start < light_instances.parallel_lights.end {
+                render_pass.set_pipeline(&self.render_pipelines.parallel_light_pipeline);
+                render_pass.set_vertex_buffer(3, self.buffers.light_settings_buffer.slice(light_settings_offsets[2]..light_settings_offsets[3]));
+                self.parallel_light_mesh.render(&mut render_pass, light_instances.parallel_lights.clone());
+            }
         }
+    }
 
-        {
+    /// Accumulates a luminance histogram of the color buffer and adapts the average luminance towards it
+    fn render_auto_exposure_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let bind_group = match &self.gbuffer.auto_exposure_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let render_options = &self.render_options;
+        let min_log_luminance = render_options.auto_exposure_min_ev;
+        let auto_exposure_settings = AutoExposureSettings {
+            min_log_luminance,
+            log_luminance_range: (render_options.auto_exposure_max_ev-min_log_luminance).max(std::f32::EPSILON),
+            adaptation_factor: 1.0-(-self.delta_time*render_options.auto_exposure_adaptation_rate).exp(),
+            pixel_count: self.gbuffer.size.width*self.gbuffer.size.height,
+        };
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_push_constants(0, unsafe { crate::transmute_slice::<AutoExposureSettings, u32>(&[auto_exposure_settings]) });
+        pass.set_pipeline(&self.compute_pipelines.luminance_histogram_pipeline);
+        let work_group_size = 16;
+        pass.dispatch((self.gbuffer.size.width+work_group_size-1)/work_group_size, (self.gbuffer.size.height+work_group_size-1)/work_group_size, 1);
+        pass.set_pipeline(&self.compute_pipelines.exposure_adaptation_pipeline);
+        pass.dispatch(1, 1, 1);
+    }
+
+    /// Extracts the bright parts of the color buffer and blurs them by downsampling and upsampling a mip chain.
+    /// Without bloom the black texture, which the post processing adds instead, is cleared.
+    fn render_bloom_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let bloom = match &self.gbuffer.bloom {
+            Some(bloom) => bloom,
+            None => {
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[
+                        clear_attachment!(self.frame_graph.get_view(self.frame_graph.attachments.black), (0.0, 0.0, 0.0, 0.0)),
+                    ],
+                    depth_stencil_attachment: None,
+                });
+                return;
+            },
+        };
+        let bloom_settings = |mip_level: usize, prefilter: bool| -> BloomPassSettings {
+            let width = (bloom.size.width>>mip_level).max(1);
+            let height = (bloom.size.height>>mip_level).max(1);
+            BloomPassSettings {
+                inverse_target_size: [1.0/width as f32, 1.0/height as f32],
+                threshold: self.render_options.bloom_threshold,
+                knee: self.render_options.bloom_threshold*0.5,
+                prefilter: prefilter as i32,
+            }
+        };
+        for (mip_level, bind_group) in bloom.downsample_passes.iter().enumerate() {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[
+                    clear_attachment!(&bloom.views[mip_level], (0.0, 0.0, 0.0, 0.0)),
+                ],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.render_pipelines.bloom_downsample_pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<BloomPassSettings, u32>(&[bloom_settings(mip_level, mip_level == 0)]) });
+            render_pass.draw(0..4 as u32, 0..1);
+        }
+        for (mip_level, bind_group) in bloom.upsample_passes.iter().enumerate().rev() {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[
-                    load_attachment!(&frame_view),
+                    load_attachment!(&bloom.views[mip_level]),
                 ],
                 depth_stencil_attachment: None,
             });
-            render_pass.set_pipeline(&self.render_pipelines.post_processing_pass_pipeline);
-            render_pass.set_bind_group(0, &self.gbuffer.post_processing_pass_bind_group, &[]);
+            render_pass.set_pipeline(&self.render_pipelines.bloom_upsample_pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<BloomPassSettings, u32>(&[bloom_settings(mip_level, false)]) });
             render_pass.draw(0..4 as u32, 0..1);
         }
     }
+
+    /// Tonemaps the color buffer into the frame, or into the LDR buffer if FXAA follows
+    fn render_post_processing_pass(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        let post_processing_pass_bind_group = match &self.gbuffer.post_processing_pass_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let render_options = &self.render_options;
+        let post_processing_settings = PostProcessingSettings {
+            tonemapping: render_options.tonemapping as i32,
+            exposure: render_options.exposure.exp2(),
+            enable_auto_exposure: self.gbuffer.auto_exposure_bind_group.is_some() as i32,
+            bloom_intensity: if self.gbuffer.bloom.is_some() { render_options.bloom_intensity } else { 0.0 },
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[
+                load_attachment!(if self.gbuffer.fxaa_pass_bind_group.is_some() { self.frame_graph.get_view(self.frame_graph.attachments.ldr) } else { self.frame_graph.get_output_view(frame_view) }),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipelines.post_processing_pass_pipeline);
+        render_pass.set_bind_group(0, post_processing_pass_bind_group, &[]);
+        render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, unsafe { crate::transmute_slice::<PostProcessingSettings, u32>(&[post_processing_settings]) });
+        render_pass.draw(0..4 as u32, 0..1);
+    }
+
+    fn render_fxaa_pass(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        let fxaa_pass_bind_group = match &self.gbuffer.fxaa_pass_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[
+                load_attachment!(self.frame_graph.get_output_view(frame_view)),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipelines.fxaa_pipeline);
+        render_pass.set_bind_group(0, fxaa_pass_bind_group, &[]);
+        render_pass.draw(0..4 as u32, 0..1);
+    }
 }
@@ -0,0 +1,140 @@
+/// Allocates instance slots, the world matrices are uploaded to the renderer as one contiguous array
+#[derive(Default)]
+pub struct Scene {
+    world_matrices: Vec<glam::Mat4>,
+    free_ranges: Vec<std::ops::Range<u32>>,
+}
+
+impl Scene {
+    /// Reuses the first free range which is large enough, otherwise appends new slots
+    pub fn allocate_instances(&mut self, count: u32) -> std::ops::Range<u32> {
+        if let Some(index) = self.free_ranges.iter().position(|range| range.end-range.start >= count) {
+            let range = &mut self.free_ranges[index];
+            let allocated = range.start..range.start+count;
+            range.start += count;
+            if range.start == range.end {
+                self.free_ranges.remove(index);
+            }
+            for world_matrix in &mut self.world_matrices[allocated.start as usize..allocated.end as usize] {
+                *world_matrix = glam::Mat4::identity();
+            }
+            return allocated;
+        }
+        let start = self.world_matrices.len() as u32;
+        self.world_matrices.resize(self.world_matrices.len()+count as usize, glam::Mat4::identity());
+        start..start+count
+    }
+
+    /// Freed slots are scaled to zero until they are allocated again
+    pub fn free_instances(&mut self, range: std::ops::Range<u32>) {
+        debug_assert!(range.start < range.end, "Freed an empty range {:?}", range);
+        debug_assert!(range.end as usize <= self.world_matrices.len(), "Freed {:?} which was never allocated", range);
+        debug_assert!(!self.free_ranges.iter().any(|other| other.start < range.end && range.start < other.end), "Freed {:?} twice", range);
+        for world_matrix in &mut self.world_matrices[range.start as usize..range.end as usize] {
+            *world_matrix = glam::Mat4::zero();
+        }
+        let index = self.free_ranges.iter().position(|other| other.start > range.start).unwrap_or(self.free_ranges.len());
+        self.free_ranges.insert(index, range);
+        let mut merged: Vec<std::ops::Range<u32>> = Vec::with_capacity(self.free_ranges.len());
+        for range in self.free_ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => merged.push(range),
+            }
+        }
+        self.free_ranges = merged;
+        while let Some(last) = self.free_ranges.last() {
+            if last.end as usize != self.world_matrices.len() {
+                break;
+            }
+            self.world_matrices.truncate(last.start as usize);
+            self.free_ranges.pop();
+        }
+    }
+
+    pub fn set_world_matrix(&mut self, instance_index: u32, world_matrix: glam::Mat4) {
+        self.world_matrices[instance_index as usize] = world_matrix;
+    }
+
+    pub fn get_world_matrix(&self, instance_index: u32) -> glam::Mat4 {
+        self.world_matrices[instance_index as usize]
+    }
+
+    pub fn get_world_matrices(&self) -> &[glam::Mat4] {
+        &self.world_matrices
+    }
+
+    pub fn get_instance_count(&self) -> usize {
+        self.world_matrices.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_appends() {
+        let mut scene = Scene::default();
+        assert_eq!(scene.allocate_instances(3), 0..3);
+        assert_eq!(scene.allocate_instances(2), 3..5);
+        assert_eq!(scene.get_instance_count(), 5);
+        assert_eq!(scene.get_world_matrix(4), glam::Mat4::identity());
+    }
+
+    #[test]
+    fn test_free_zeroes_and_merges_neighbours() {
+        let mut scene = Scene::default();
+        let a = scene.allocate_instances(2);
+        let b = scene.allocate_instances(2);
+        let c = scene.allocate_instances(2);
+        let _d = scene.allocate_instances(1);
+        scene.free_instances(a);
+        scene.free_instances(c);
+        assert_eq!(scene.free_ranges, vec![0..2, 4..6]);
+        assert_eq!(scene.get_world_matrix(4), glam::Mat4::zero());
+        scene.free_instances(b);
+        assert_eq!(scene.free_ranges, vec![0..6]);
+        assert_eq!(scene.get_instance_count(), 7);
+    }
+
+    #[test]
+    fn test_free_truncates_the_tail() {
+        let mut scene = Scene::default();
+        let a = scene.allocate_instances(2);
+        let b = scene.allocate_instances(2);
+        let c = scene.allocate_instances(2);
+        scene.free_instances(b);
+        scene.free_instances(c);
+        assert_eq!(scene.get_instance_count(), 2);
+        assert!(scene.free_ranges.is_empty());
+        scene.free_instances(a);
+        assert_eq!(scene.get_instance_count(), 0);
+        assert!(scene.free_ranges.is_empty());
+    }
+
+    #[test]
+    fn test_allocate_reuses_free_ranges() {
+        let mut scene = Scene::default();
+        let a = scene.allocate_instances(4);
+        let _b = scene.allocate_instances(1);
+        scene.free_instances(a);
+        assert_eq!(scene.allocate_instances(5), 5..10);
+        assert_eq!(scene.allocate_instances(3), 0..3);
+        assert_eq!(scene.get_world_matrix(0), glam::Mat4::identity());
+        assert_eq!(scene.free_ranges, vec![3..4]);
+        assert_eq!(scene.allocate_instances(1), 3..4);
+        assert!(scene.free_ranges.is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_double_free() {
+        let mut scene = Scene::default();
+        let a = scene.allocate_instances(2);
+        let _b = scene.allocate_instances(1);
+        scene.free_instances(a.clone());
+        scene.free_instances(a);
+    }
+}
@@ -0,0 +1,228 @@
+use iced_wgpu::wgpu;
+use crate::renderer::GBufferAttachment;
+
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenshotRequest {
+    /// Also save each attachment of the GBuffer next to the frame
+    pub include_gbuffer: bool,
+}
+
+fn get_bytes_per_pixel(format: wgpu::TextureFormat) -> u32 {
+    match format {
+        wgpu::TextureFormat::Rgba32Float => 16,
+        wgpu::TextureFormat::Rgba16Float => 8,
+        _ => 4,
+    }
+}
+
+/// Copies a texture into a buffer, waits for the GPU and returns the pixels without row padding
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: wgpu::Extent3d, format: wgpu::TextureFormat) -> Vec<u8> {
+    let bytes_per_pixel = get_bytes_per_pixel(format);
+    let bytes_per_row = align_to!(size.width*bytes_per_pixel, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row*size.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::COPY_DST|wgpu::BufferUsage::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &readback_buffer,
+            layout: wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row,
+                rows_per_image: size.height,
+            },
+        },
+        size,
+    );
+    queue.submit(Some(encoder.finish()));
+    let buffer_slice = readback_buffer.slice(..);
+    let map_future = buffer_slice.map_async(wgpu::MapMode::Read);
+    device.poll(wgpu::Maintain::Wait);
+    iced_winit::futures::executor::block_on(map_future).unwrap();
+    let row_size = (size.width*bytes_per_pixel) as usize;
+    let mut pixels = Vec::with_capacity(row_size*size.height as usize);
+    {
+        let mapped_range = buffer_slice.get_mapped_range();
+        for row in mapped_range.chunks(bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..row_size]);
+        }
+    }
+    readback_buffer.unmap();
+    pixels
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits&0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits>>10)&0x1F) as i32;
+    let mantissa = (bits&0x3FF) as f32;
+    sign*match exponent {
+        0 => mantissa*(2.0f32).powi(-24),
+        0x1F => if mantissa == 0.0 { std::f32::INFINITY } else { std::f32::NAN },
+        _ => (1.0+mantissa/1024.0)*(2.0f32).powi(exponent-15),
+    }
+}
+
+/// Inverse of the octahedral encoding in gbuffer.glsl
+fn decode_normal(x: f32, y: f32) -> glam::Vec3 {
+    let z = 1.0-x.abs()-y.abs();
+    let (x, y) = if z < 0.0 {
+        ((1.0-y.abs())*x.signum(), (1.0-x.abs())*y.signum())
+    } else {
+        (x, y)
+    };
+    glam::Vec3::new(x, y, z).normalize()
+}
+
+/// Portable float map, keeps the exact values including negative ones, which PNG and Radiance HDR can not
+fn save_pfm(path: &std::path::Path, width: u32, height: u32, channel_count: usize, values: &[f32]) -> Result<(), crate::assets::AssetError> {
+    use std::io::Write;
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    // A negative scale marks little endian
+    write!(writer, "{}\n{} {}\n-1.0\n", if channel_count == 1 { "Pf" } else { "PF" }, width, height)?;
+    // Rows are stored from bottom to top
+    for row in values.chunks(width as usize*channel_count).rev() {
+        for value in row {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a Bgra8UnormSrgb texture to an image file
+pub fn save_frame(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: wgpu::Extent3d, path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+    let mut pixels = read_texture(device, queue, texture, size, wgpu::TextureFormat::Bgra8UnormSrgb);
+    for bgra in pixels.chunks_mut(4) {
+        bgra.swap(0, 2);
+    }
+    image::save_buffer(path, &pixels, size.width, size.height, image::ColorType::Rgba8)?;
+    Ok(())
+}
+
+/// Reconstructed positions, HDR colors and depths are saved as float maps, normals, albedo and material as PNG
+fn save_gbuffer_attachment(device: &wgpu::Device, queue: &wgpu::Queue, renderer: &crate::renderer::Renderer, attachment: GBufferAttachment, path: &std::path::Path) -> Result<(), crate::assets::AssetError> {
+    let size = renderer.get_size();
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let texture = renderer.capture_gbuffer_attachment(device, &mut encoder, attachment);
+    queue.submit(Some(encoder.finish()));
+    let pixels = read_texture(device, queue, &texture, size, attachment.get_format());
+    match attachment {
+        GBufferAttachment::Position => {
+            // The w component marks where a surface was rendered
+            let values: Vec<f32> = pixels.chunks_exact(16).flat_map(|texel| texel[0..12].chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))).collect();
+            save_pfm(&path.with_extension("pfm"), size.width, size.height, 3, &values)
+        },
+        GBufferAttachment::Normal => {
+            // Normals are octahedral encoded and stored as signed integers scaled by 32767
+            let values: Vec<u8> = pixels.chunks_exact(4).flat_map(|texel| {
+                let x = i16::from_le_bytes([texel[0], texel[1]]);
+                let y = i16::from_le_bytes([texel[2], texel[3]]);
+                let normal = if x == 0 && y == 0 { glam::Vec3::zero() } else { decode_normal(x as f32/32767.0, y as f32/32767.0) };
+                let normal = normal*0.5+glam::Vec3::splat(0.5);
+                vec![(normal.x()*255.0).round() as u8, (normal.y()*255.0).round() as u8, (normal.z()*255.0).round() as u8]
+            }).collect();
+            image::save_buffer(&path.with_extension("png"), &values, size.width, size.height, image::ColorType::Rgb8)?;
+            Ok(())
+        },
+        GBufferAttachment::Albedo => {
+            // Already stored as sRGB, like PNG expects
+            let values: Vec<u8> = pixels.chunks(4).flat_map(|texel| texel[0..3].iter().cloned()).collect();
+            image::save_buffer(&path.with_extension("png"), &values, size.width, size.height, image::ColorType::Rgb8)?;
+            Ok(())
+        },
+        GBufferAttachment::Material => {
+            image::save_buffer(&path.with_extension("png"), &pixels, size.width, size.height, image::ColorType::Rgba8)?;
+            Ok(())
+        },
+        GBufferAttachment::Color => {
+            let values: Vec<f32> = pixels.chunks_exact(8).flat_map(|texel| texel[0..6].chunks_exact(2).map(|bytes| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))).collect();
+            save_pfm(&path.with_extension("pfm"), size.width, size.height, 3, &values)
+        },
+        GBufferAttachment::Depth => {
+            let values: Vec<f32> = pixels.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect();
+            save_pfm(&path.with_extension("pfm"), size.width, size.height, 1, &values)
+        },
+    }
+}
+
+/// Has to be called after the frame was rendered with Renderer::set_frame_capture and submitted
+pub fn save_screenshot(device: &wgpu::Device, queue: &wgpu::Queue, renderer: &crate::renderer::Renderer, request: ScreenshotRequest) -> Result<(), crate::assets::AssetError> {
+    let directory = std::path::Path::new(SCREENSHOT_DIRECTORY);
+    std::fs::create_dir_all(directory)?;
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+    save_frame(device, queue, renderer.get_captured_frame(), renderer.get_size(), &directory.join(format!("{}_frame.png", timestamp)))?;
+    if request.include_gbuffer {
+        for attachment in GBufferAttachment::ALL.iter() {
+            save_gbuffer_attachment(device, queue, renderer, *attachment, &directory.join(format!("{}_{}", timestamp, attachment.get_name())))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same as encode_normal in gbuffer.glsl
+    fn encode_normal(normal: glam::Vec3) -> (f32, f32) {
+        let normal = normal/(normal.x().abs()+normal.y().abs()+normal.z().abs());
+        if normal.z() >= 0.0 {
+            (normal.x(), normal.y())
+        } else {
+            ((1.0-normal.y().abs())*normal.x().signum(), (1.0-normal.x().abs())*normal.y().signum())
+        }
+    }
+
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3C00), 1.0);
+        assert_eq!(f16_to_f32(0xC000), -2.0);
+        assert_eq!(f16_to_f32(0x7BFF), 65504.0);
+        assert_eq!(f16_to_f32(0x0001), (2.0f32).powi(-24));
+        assert_eq!(f16_to_f32(0x83FF), -1023.0*(2.0f32).powi(-24));
+        assert_eq!(f16_to_f32(0x7C00), std::f32::INFINITY);
+        assert_eq!(f16_to_f32(0xFC00), std::f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7E00).is_nan());
+    }
+
+    #[test]
+    fn test_decode_normal() {
+        assert!((decode_normal(0.0, 0.0)-glam::Vec3::new(0.0, 0.0, 1.0)).length() < 0.0001);
+        assert!((decode_normal(1.0, 0.0)-glam::Vec3::new(1.0, 0.0, 0.0)).length() < 0.0001);
+        // The corners are the folded pole of the lower hemisphere
+        assert!((decode_normal(1.0, 1.0)-glam::Vec3::new(0.0, 0.0, -1.0)).length() < 0.0001);
+        assert!((decode_normal(-1.0, -1.0)-glam::Vec3::new(0.0, 0.0, -1.0)).length() < 0.0001);
+        for normal in &[
+            glam::Vec3::new(0.3, -0.5, 0.8),
+            glam::Vec3::new(0.6, 0.0, -0.8),
+            glam::Vec3::new(-0.2, 0.7, -0.4),
+            glam::Vec3::new(-0.5, -0.5, -0.1),
+        ] {
+            let normal = normal.normalize();
+            let (x, y) = encode_normal(normal);
+            assert!((decode_normal(x, y)-normal).length() < 0.0001, "{:?} decoded as {:?}", normal, decode_normal(x, y));
+        }
+    }
+
+    #[test]
+    fn test_save_pfm() {
+        let path = std::env::temp_dir().join(format!("screenshot_{}.pfm", std::process::id()));
+        save_pfm(&path, 2, 2, 1, &[1.0, 2.0, 3.0, -4.0]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let header = b"Pf\n2 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], &header[..]);
+        let values: Vec<f32> = bytes[header.len()..].chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect();
+        assert_eq!(values, vec![3.0, -4.0, 1.0, 2.0]);
+    }
+}
@@ -0,0 +1,16 @@
+#version 460
+
+layout(location=0) in vec3 light_setting_color;
+layout(location=0) out vec3 out_light_color;
+
+const vec2 quad_vertices[] = vec2[](
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(-1.0, 1.0),
+    vec2(1.0, 1.0)
+);
+
+void main() {
+    gl_Position = vec4(quad_vertices[gl_VertexIndex], 0.0, 1.0);
+    out_light_color = light_setting_color;
+}
@@ -0,0 +1,40 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out float ambientOcclusion;
+
+layout(set=0, binding=0) uniform texture2D ambientOcclusionSource;
+layout(set=0, binding=1) uniform texture2D gBufferDepth;
+layout(set=0, binding=2) uniform itexture2D gBufferNormal;
+
+#include "include/camera_uniforms.glsl"
+#include "include/gbuffer.glsl"
+
+layout(push_constant) uniform AmbientOcclusionBlur {
+    ivec2 direction;
+    int blur_radius;
+    float inverse_radius;
+} settings;
+
+// Bilateral: samples on other surfaces get a lower weight, so that the occlusion does not bleed over edges
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    ivec2 texture_size = textureSize(ambientOcclusionSource, 0);
+    vec3 position = reconstruct_position(at, texture_size, texelFetch(gBufferDepth, at, 0).r);
+    vec3 normal = fetch_gbuffer_normal(at);
+    float sigma = float(settings.blur_radius)*0.5+0.5;
+    float sum = 0.0;
+    float weight_sum = 0.0;
+    for(int i = -settings.blur_radius; i <= settings.blur_radius; ++i) {
+        ivec2 sample_at = clamp(at+settings.direction*i, ivec2(0), texture_size-ivec2(1));
+        vec3 sample_position = reconstruct_position(sample_at, texture_size, texelFetch(gBufferDepth, sample_at, 0).r);
+        vec3 sample_normal = fetch_gbuffer_normal(sample_at);
+        float spatial_weight = exp(-float(i*i)/(2.0*sigma*sigma));
+        float plane_distance = abs(dot(normal, sample_position-position))*settings.inverse_radius;
+        float weight = spatial_weight*exp(-plane_distance*plane_distance*16.0)*pow(max(0.0, dot(normal, sample_normal)), 8.0);
+        sum += texelFetch(ambientOcclusionSource, sample_at, 0).r*weight;
+        weight_sum += weight;
+    }
+    ambientOcclusion = (weight_sum > 0.0) ? sum/weight_sum : texelFetch(ambientOcclusionSource, at, 0).r;
+}
@@ -0,0 +1,66 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out float ambientOcclusion;
+
+layout(set=0, binding=0) uniform texture2D gBufferDepth;
+layout(set=0, binding=1) uniform itexture2D gBufferNormal;
+
+#include "include/camera_uniforms.glsl"
+#include "include/gbuffer.glsl"
+#include "include/hash_noise.glsl"
+
+layout(push_constant) uniform AmbientOcclusion {
+    float radius;
+    float intensity;
+    int sample_count;
+} settings;
+
+const float GOLDEN_ANGLE = 2.39996323;
+const float PI = 3.14159265;
+
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    // Reverse-Z: nothing was rendered where the depth is still cleared
+    float depth = texelFetch(gBufferDepth, at, 0).r;
+    if(depth == 0.0) {
+        ambientOcclusion = 1.0;
+        return;
+    }
+    ivec2 texture_size = textureSize(gBufferDepth, 0);
+    vec3 position = reconstruct_position(at, texture_size, depth);
+    vec3 normal = fetch_gbuffer_normal(at);
+    vec3 tangent = normalize(cross(normal, (abs(normal.x) > 0.9) ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0)));
+    vec3 bitangent = cross(normal, tangent);
+
+    // Random rotation per pixel, the blur removes the resulting noise
+    float rotation = noise(vec2(at))*2.0*PI;
+    float length_offset = noise(vec2(at.yx));
+    float occlusion = 0.0;
+    for(int i = 0; i < settings.sample_count; ++i) {
+        // Spiral over the hemisphere, cosine weighted
+        float t = (float(i)+0.5)/float(settings.sample_count);
+        float angle = float(i)*GOLDEN_ANGLE+rotation;
+        vec3 direction = (tangent*cos(angle)+bitangent*sin(angle))*sqrt(t)+normal*sqrt(1.0-t);
+        // More samples close to the surface point
+        float sample_length = fract(float(i)*0.618034+length_offset);
+        vec3 sample_position = position+direction*settings.radius*mix(0.1, 1.0, sample_length*sample_length);
+
+        vec4 clip_position = camera.view_matrix*vec4(sample_position, 1.0);
+        vec2 texcoord = clip_position.xy/clip_position.w*vec2(0.5, -0.5)+vec2(0.5);
+        ivec2 sample_at = ivec2(texcoord*vec2(texture_size));
+        if(any(lessThan(sample_at, ivec2(0))) || any(greaterThanEqual(sample_at, texture_size)))
+            continue;
+        float sample_depth = texelFetch(gBufferDepth, sample_at, 0).r;
+        if(sample_depth == 0.0)
+            continue;
+
+        // The surface visible at the sample occludes, if it is above the tangent plane and within the radius
+        vec3 to_occluder = reconstruct_position(sample_at, texture_size, sample_depth)-position;
+        float distance = length(to_occluder);
+        float range_check = smoothstep(0.0, 1.0, settings.radius/max(distance, 0.0001));
+        occlusion += max(0.0, dot(normal, to_occluder)/max(distance, 0.0001)-0.1)*range_check;
+    }
+    ambientOcclusion = clamp(1.0-settings.intensity*occlusion/float(max(settings.sample_count, 1)), 0.0, 1.0);
+}
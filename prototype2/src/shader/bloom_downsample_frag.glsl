@@ -0,0 +1,51 @@
+#version 460
+
+layout(location=0) out vec4 bloomColor;
+
+layout(set=0, binding=0) uniform sampler linearSampler;
+layout(set=0, binding=1) uniform texture2D sourceTexture;
+
+layout(push_constant) uniform Bloom {
+    vec2 inverse_target_size;
+    float threshold;
+    float knee;
+    int prefilter;
+} settings;
+
+// Soft threshold, the knee blends quadratically into the linear part above the threshold
+vec3 prefilter(vec3 color) {
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness-settings.threshold+settings.knee, 0.0, 2.0*settings.knee);
+    soft = soft*soft/(4.0*settings.knee+0.00001);
+    return color*max(soft, brightness-settings.threshold)/max(brightness, 0.00001);
+}
+
+#define sample_source(x, y) texture(sampler2D(sourceTexture, linearSampler), uv+texel_size*vec2(x, y)).rgb
+
+void main() {
+    vec2 uv = gl_FragCoord.xy*settings.inverse_target_size;
+    vec2 texel_size = 1.0/vec2(textureSize(sampler2D(sourceTexture, linearSampler), 0));
+
+    // 13 taps: a box of four overlapping boxes, which avoids the blocky artifacts of a plain 2x2 box
+    vec3 a = sample_source(-2.0, -2.0);
+    vec3 b = sample_source(0.0, -2.0);
+    vec3 c = sample_source(2.0, -2.0);
+    vec3 d = sample_source(-2.0, 0.0);
+    vec3 e = sample_source(0.0, 0.0);
+    vec3 f = sample_source(2.0, 0.0);
+    vec3 g = sample_source(-2.0, 2.0);
+    vec3 h = sample_source(0.0, 2.0);
+    vec3 i = sample_source(2.0, 2.0);
+    vec3 j = sample_source(-1.0, -1.0);
+    vec3 k = sample_source(1.0, -1.0);
+    vec3 l = sample_source(-1.0, 1.0);
+    vec3 m = sample_source(1.0, 1.0);
+    vec3 color = e*0.125;
+    color += (a+c+g+i)*0.03125;
+    color += (b+d+f+h)*0.0625;
+    color += (j+k+l+m)*0.125;
+
+    if(settings.prefilter != 0)
+        color = prefilter(color);
+    bloomColor = vec4(color, 1.0);
+}
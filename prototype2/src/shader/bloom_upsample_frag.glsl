@@ -0,0 +1,26 @@
+#version 460
+
+layout(location=0) out vec4 bloomColor;
+
+layout(set=0, binding=0) uniform sampler linearSampler;
+layout(set=0, binding=1) uniform texture2D sourceTexture;
+
+layout(push_constant) uniform Bloom {
+    vec2 inverse_target_size;
+    float threshold;
+    float knee;
+    int prefilter;
+} settings;
+
+#define sample_source(x, y) texture(sampler2D(sourceTexture, linearSampler), uv+texel_size*vec2(x, y)).rgb
+
+void main() {
+    vec2 uv = gl_FragCoord.xy*settings.inverse_target_size;
+    vec2 texel_size = 1.0/vec2(textureSize(sampler2D(sourceTexture, linearSampler), 0));
+
+    // 3x3 tent filter, the result is added to the target by blending
+    vec3 color = sample_source(0.0, 0.0)*4.0;
+    color += (sample_source(0.0, -1.0)+sample_source(-1.0, 0.0)+sample_source(1.0, 0.0)+sample_source(0.0, 1.0))*2.0;
+    color += sample_source(-1.0, -1.0)+sample_source(1.0, -1.0)+sample_source(-1.0, 1.0)+sample_source(1.0, 1.0);
+    bloomColor = vec4(color/16.0, 1.0);
+}
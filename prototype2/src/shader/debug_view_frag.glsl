@@ -0,0 +1,86 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out vec4 frameColor;
+
+layout(set=0, binding=0) uniform texture2D gBufferDepth;
+layout(set=0, binding=1) uniform itexture2D gBufferNormal;
+layout(set=0, binding=2) uniform texture2D gBufferAlbedo;
+layout(set=0, binding=3) uniform texture2D gBufferMaterial;
+layout(set=0, binding=4) uniform utexture2D gBufferStencil;
+layout(set=0, binding=5) uniform texture2D gBufferColor;
+
+#include "include/camera_uniforms.glsl"
+#include "include/gbuffer.glsl"
+
+layout(push_constant) uniform DebugView {
+    int debug_view;
+} settings;
+
+// Linear depths between these are mapped logarithmically, so that near and far objects can be told apart
+const float MIN_LINEAR_DEPTH = 0.1;
+const float MAX_LINEAR_DEPTH = 10000.0;
+// Overdraw at which the heat map saturates
+const float MAX_OVERDRAW = 8.0;
+
+vec3 heat_map(float value) {
+    return clamp(vec3(value*3.0, value*3.0-1.0, value*3.0-2.0), 0.0, 1.0);
+}
+
+// Normal of the reconstructed positions of the neighboring texels, the smaller difference avoids silhouettes
+vec3 reconstructed_normal(ivec2 at, ivec2 texture_size, vec3 position) {
+    ivec2 max_at = texture_size-ivec2(1);
+    vec3 right = reconstruct_position(min(at+ivec2(1, 0), max_at), texture_size, texelFetch(gBufferDepth, min(at+ivec2(1, 0), max_at), 0).r)-position;
+    vec3 left = position-reconstruct_position(max(at-ivec2(1, 0), ivec2(0)), texture_size, texelFetch(gBufferDepth, max(at-ivec2(1, 0), ivec2(0)), 0).r);
+    vec3 down = reconstruct_position(min(at+ivec2(0, 1), max_at), texture_size, texelFetch(gBufferDepth, min(at+ivec2(0, 1), max_at), 0).r)-position;
+    vec3 up = position-reconstruct_position(max(at-ivec2(0, 1), ivec2(0)), texture_size, texelFetch(gBufferDepth, max(at-ivec2(0, 1), ivec2(0)), 0).r);
+    vec3 horizontal = (dot(right, right) < dot(left, left)) ? right : left;
+    vec3 vertical = (dot(down, down) < dot(up, up)) ? down : up;
+    // The texture rows go downwards, which flips the orientation
+    return normalize(cross(vertical, horizontal));
+}
+
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    ivec2 texture_size = textureSize(gBufferDepth, 0);
+    float depth = texelFetch(gBufferDepth, at, 0).r;
+    vec3 position = reconstruct_position(at, texture_size, depth);
+    vec3 color = vec3(0.0);
+    switch(settings.debug_view) {
+        case 1: // Position, one stripe per world space unit
+            color = (depth == 0.0) ? vec3(0.0) : fract(position);
+            break;
+        case 2: // Normal
+            color = fetch_gbuffer_normal(at)*0.5+0.5;
+            break;
+        case 3: // Albedo
+            color = texelFetch(gBufferAlbedo, at, 0).rgb;
+            break;
+        case 4: // Material
+            color = texelFetch(gBufferMaterial, at, 0).rgb;
+            break;
+        case 5: { // Linear depth, reverse-Z: the background is cleared to 0
+            float linear_depth = dot(camera.world_matrix[3].xyz-position, camera.world_matrix[2].xyz);
+            float value = log(max(linear_depth, MIN_LINEAR_DEPTH)/MIN_LINEAR_DEPTH)/log(MAX_LINEAR_DEPTH/MIN_LINEAR_DEPTH);
+            color = (depth == 0.0) ? vec3(0.0) : vec3(1.0-value);
+        } break;
+        case 6: // Stencil
+            color = vec3(float(texelFetch(gBufferStencil, at, 0).r)/255.0);
+            break;
+        case 7: // Light volume overdraw, counted by the light pass in the color buffer
+            color = heat_map(texelFetch(gBufferColor, at, 0).r/MAX_OVERDRAW);
+            break;
+        case 8: // Left: decoded normals, right: normals derived from the reconstructed positions, both halves should match
+            if(depth == 0.0)
+                break;
+            if(at.x == texture_size.x/2)
+                color = vec3(1.0);
+            else if(at.x < texture_size.x/2)
+                color = fetch_gbuffer_normal(at)*0.5+0.5;
+            else
+                color = reconstructed_normal(at, texture_size, position)*0.5+0.5;
+            break;
+    }
+    frameColor = vec4(color, 1.0);
+}
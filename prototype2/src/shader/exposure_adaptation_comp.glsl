@@ -0,0 +1,45 @@
+#version 460
+
+layout(local_size_x=256, local_size_y=1, local_size_z=1) in;
+
+layout(set=0, binding=1) buffer LuminanceHistogram {
+    uint bins[256];
+};
+
+layout(set=0, binding=2) buffer Exposure {
+    float average_luminance;
+};
+
+layout(push_constant) uniform AutoExposure {
+    float min_log_luminance;
+    float log_luminance_range;
+    float adaptation_factor;
+    uint pixel_count;
+} settings;
+
+shared float weighted_bins[256];
+
+void main() {
+    uint bin_index = gl_LocalInvocationIndex;
+    uint count = bins[bin_index];
+    weighted_bins[bin_index] = float(count)*float(bin_index);
+    // Reset the histogram for the next frame
+    bins[bin_index] = 0;
+    barrier();
+
+    for(uint stride = 128; stride > 0; stride >>= 1) {
+        if(bin_index < stride)
+            weighted_bins[bin_index] += weighted_bins[bin_index+stride];
+        barrier();
+    }
+
+    if(bin_index == 0) {
+        // count is the number of black pixels here
+        float weighted_average = weighted_bins[0]/max(float(settings.pixel_count-count), 1.0)-1.0;
+        float luminance = exp2(max(weighted_average, 0.0)/254.0*settings.log_luminance_range+settings.min_log_luminance);
+        if(average_luminance <= 0.0 || isnan(average_luminance))
+            average_luminance = luminance;
+        else
+            average_luminance += (luminance-average_luminance)*settings.adaptation_factor;
+    }
+}
@@ -0,0 +1,45 @@
+#version 460
+
+layout(location=0) out vec4 frameColor;
+
+layout(set=0, binding=0) uniform sampler linearSampler;
+layout(set=0, binding=1) uniform texture2D ldrColor;
+
+const float FXAA_SPAN_MAX = 8.0;
+const float FXAA_REDUCE_MUL = 1.0/8.0;
+const float FXAA_REDUCE_MIN = 1.0/128.0;
+
+#define sample_color(offset) texture(sampler2D(ldrColor, linearSampler), uv+(offset)).rgb
+
+// Perceptual luma, the texture is decoded from sRGB when sampled
+float luma(vec3 color) {
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+void main() {
+    vec2 texel_size = 1.0/vec2(textureSize(sampler2D(ldrColor, linearSampler), 0));
+    vec2 uv = gl_FragCoord.xy*texel_size;
+    vec3 color = sample_color(vec2(0.0));
+    float luma_nw = luma(sample_color(vec2(-1.0, -1.0)*texel_size));
+    float luma_ne = luma(sample_color(vec2(1.0, -1.0)*texel_size));
+    float luma_sw = luma(sample_color(vec2(-1.0, 1.0)*texel_size));
+    float luma_se = luma(sample_color(vec2(1.0, 1.0)*texel_size));
+    float luma_m = luma(color);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur along the edge, which is perpendicular to the luma gradient
+    vec2 direction = vec2(
+        -((luma_nw+luma_ne)-(luma_sw+luma_se)),
+        (luma_nw+luma_sw)-(luma_ne+luma_se)
+    );
+    float direction_reduce = max((luma_nw+luma_ne+luma_sw+luma_se)*0.25*FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float inverse_direction_min = 1.0/(min(abs(direction.x), abs(direction.y))+direction_reduce);
+    direction = clamp(direction*inverse_direction_min, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX))*texel_size;
+
+    vec3 color_a = 0.5*(sample_color(direction*(1.0/3.0-0.5))+sample_color(direction*(2.0/3.0-0.5)));
+    vec3 color_b = color_a*0.5+0.25*(sample_color(direction*-0.5)+sample_color(direction*0.5));
+    float luma_b = luma(color_b);
+    // The wider blur is rejected if it leaves the local luma range
+    frameColor = vec4((luma_b < luma_min || luma_b > luma_max) ? color_a : color_b, 1.0);
+}
@@ -15,6 +15,18 @@ const vec2 quad_vertices[] = vec2[](
 
 void billboard_sphere(vec3 world_position, float radius) {
     vec3 direction = world_position-camera.world_matrix[3].xyz;
+    if(camera.projection_matrix[3][3] == 1.0) {
+        // Orthographic projection, e.g. of the shadow map of a parallel light
+        vec2 corner = (quad_vertices[gl_VertexIndex&3]*2.0-vec2(1.0))*radius;
+        gl_Position = camera.projection_matrix*vec4(
+            dot(direction, camera.world_matrix[0].xyz)+corner.x,
+            dot(direction, camera.world_matrix[1].xyz)+corner.y,
+            dot(direction, camera.world_matrix[2].xyz),
+            1.0
+        );
+        vClipPos = gl_Position;
+        return;
+    }
     gl_Position.z = dot(direction, camera.world_matrix[2].xyz);
     float distance_to_camera = length(direction);
     float sphere_angle = asin(radius/distance_to_camera);
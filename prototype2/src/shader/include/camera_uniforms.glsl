@@ -2,4 +2,12 @@ layout(set=1, binding=0) readonly buffer CameraUniforms {
     mat4 world_matrix;
     mat4 projection_matrix;
     mat4 inverse_view_matrix;
+    mat4 view_matrix;
 } camera;
+
+// World space position of a texel from its depth, which is written by the surface pass
+vec3 reconstruct_position(ivec2 at, ivec2 size, float depth) {
+    vec2 ndc = (vec2(at)+vec2(0.5))/vec2(size)*vec2(2.0, -2.0)+vec2(-1.0, 1.0);
+    vec4 position = camera.inverse_view_matrix*vec4(ndc, depth, 1.0);
+    return position.xyz/position.w;
+}
@@ -0,0 +1,25 @@
+// Octahedral normal encoding: The normal is projected onto an octahedron, which is unfolded into a square
+// Stored in two signed 16 bit integer components, scaled by 32767
+
+vec2 sign_not_zero(vec2 v) {
+    return vec2((v.x >= 0.0) ? 1.0 : -1.0, (v.y >= 0.0) ? 1.0 : -1.0);
+}
+
+vec2 encode_normal(vec3 normal) {
+    normal /= abs(normal.x)+abs(normal.y)+abs(normal.z);
+    // The lower hemisphere is folded over the diagonals
+    return (normal.z >= 0.0) ? normal.xy : (1.0-abs(normal.yx))*sign_not_zero(normal.xy);
+}
+
+vec3 decode_normal(vec2 encoded) {
+    vec3 normal = vec3(encoded, 1.0-abs(encoded.x)-abs(encoded.y));
+    if(normal.z < 0.0)
+        normal.xy = (1.0-abs(normal.yx))*sign_not_zero(normal.xy);
+    return normalize(normal);
+}
+
+#define write_gbuffer_normal(normal) \
+    gBufferNormal = ivec2(round(encode_normal(normal)*32767.0));
+
+#define fetch_gbuffer_normal(at) \
+    decode_normal(vec2(texelFetch(gBufferNormal, at, 0).xy)/32767.0)
@@ -2,28 +2,62 @@
 
 layout(location=0) out vec4 gBufferColor;
 
-layout(set=0, binding=0) uniform texture2D gBufferPosition;
+layout(set=0, binding=0) uniform texture2D gBufferDepth;
 layout(set=0, binding=1) uniform itexture2D gBufferNormal;
 layout(set=0, binding=2) uniform texture2D gBufferAlbedo;
 layout(set=0, binding=3) uniform texture2D gBufferMaterial;
 
 #include "camera_uniforms.glsl"
+#include "gbuffer.glsl"
 
-float specular_factor(vec3 light_direction, vec3 position, vec3 normal, float specularPower) {
-    vec3 viewDir = normalize(camera.world_matrix[3].xyz-position);
-    vec3 halfDir = normalize(light_direction+viewDir);
-    return pow(max(dot(normal, halfDir), 0.0), specularPower);
+layout(set=2, binding=0) uniform samplerShadow shadowSampler;
+layout(set=2, binding=1) uniform texture2DArray shadowMaps;
+struct ShadowMapSettings {
+    mat4 view_matrix;
+    float split_far;
+    float blend_distance;
+};
+layout(set=2, binding=2) readonly buffer ShadowMapSettingsBuffer {
+    ShadowMapSettings shadow_maps[];
+};
+
+// 3x3 percentage closer filtering, on top of the 2x2 of the comparison sampler
+float sample_shadow_map(int shadow_map_index, vec3 position) {
+    vec4 shadow_position = shadow_maps[shadow_map_index].view_matrix*vec4(position, 1.0);
+    shadow_position.xyz /= shadow_position.w;
+    vec2 texcoord = shadow_position.xy*vec2(0.5, -0.5)+vec2(0.5);
+    if(texcoord.x < 0.0 || texcoord.x > 1.0 || texcoord.y < 0.0 || texcoord.y > 1.0 || shadow_position.z < 0.0 || shadow_position.z > 1.0)
+        return 1.0;
+    vec2 texel_size = 1.0/vec2(textureSize(sampler2DArrayShadow(shadowMaps, shadowSampler), 0).xy);
+    float lit = 0.0;
+    for(int y = -1; y <= 1; ++y)
+        for(int x = -1; x <= 1; ++x)
+            lit += texture(sampler2DArrayShadow(shadowMaps, shadowSampler), vec4(texcoord+vec2(x, y)*texel_size, shadow_map_index, shadow_position.z));
+    return lit/9.0;
 }
 
-float light_attenuation(float distance, float radius) {
-    float attenuation = clamp(1.0-distance*distance/(radius*radius), 0.0, 1.0);
-    return attenuation*attenuation;
+// Selects the cascade by the view depth and blends into the next one at the end of each cascade
+float shadow_factor(int shadow_map_index, int cascade_count, vec3 position) {
+    if(shadow_map_index < 0)
+        return 1.0;
+    float view_depth = dot(camera.world_matrix[3].xyz-position, camera.world_matrix[2].xyz);
+    for(int cascade = 0; cascade < cascade_count; ++cascade) {
+        ShadowMapSettings settings = shadow_maps[shadow_map_index+cascade];
+        if(view_depth > settings.split_far)
+            continue;
+        float lit = sample_shadow_map(shadow_map_index+cascade, position);
+        float blend = (view_depth-(settings.split_far-settings.blend_distance))/settings.blend_distance;
+        if(cascade+1 < cascade_count && blend > 0.0)
+            lit = mix(lit, sample_shadow_map(shadow_map_index+cascade+1, position), blend);
+        return lit;
+    }
+    return 1.0;
 }
 
 #define light_gbuffer(LightSettings) \
     ivec2 at = ivec2(gl_FragCoord.xy); \
-    vec3 position = texelFetch(gBufferPosition, at, 0).xyz; \
-    vec3 normal = vec3(texelFetch(gBufferNormal, at, 0).xyz)/32767.0; \
+    vec3 position = reconstruct_position(at, textureSize(gBufferDepth, 0), texelFetch(gBufferDepth, at, 0).r); \
+    vec3 normal = fetch_gbuffer_normal(at); \
     vec3 albedo = texelFetch(gBufferAlbedo, at, 0).xyz; \
     vec3 material = texelFetch(gBufferMaterial, at, 0).xyz;
 
@@ -38,3 +72,12 @@ float light_attenuation(float distance, float radius) {
     float specular = specular_factor(light_direction, position, normal, material.g*255.0+1.0); \
     float diffuse = max(0.0, dot(normal, light_direction))*attenuation; \
     gBufferColor.rgb = (albedo*diffuse+vec3(material.r*specular))*light_setting.color;
+
+// The LIGHT_OVERDRAW permutation replaces the shading, the additive blending counts how many light volumes cover each pixel
+#ifdef LIGHT_OVERDRAW
+#define light_overdraw() \
+    gBufferColor = vec4(1.0, 0.0, 0.0, 0.0); \
+    return;
+#else
+#define light_overdraw()
+#endif
@@ -27,13 +27,16 @@ const float M_PI = radians(180.0);
     vec3 view_ray_hit = (camera.inverse_view_matrix*vClipPos).xyz; \
     vec3 view_ray_direction = normalize(view_ray_hit-view_ray_origin);
 
+// Reverse-Z: depth 1 is the near plane, points behind the camera get a negative depth
 #define frag_depth(position) \
     float csp_z = dot(position-view_ray_origin, camera.world_matrix[2].xyz); \
-    float csp_w = abs(csp_z*camera.projection_matrix[2][3]); \
+    float csp_w = csp_z*camera.projection_matrix[2][3]; \
     csp_z = csp_z*camera.projection_matrix[2][2]+camera.projection_matrix[3][2]; \
     gl_FragDepth = csp_z/csp_w;
 
+#define is_frag_depth_clipped() (gl_FragDepth < 0.0 || gl_FragDepth > 1.0)
+
 #define clip_frag_depth(position) \
     frag_depth(position) \
-    if(gl_FragDepth < 0.0) \
+    if(is_frag_depth_clipped()) \
         discard;
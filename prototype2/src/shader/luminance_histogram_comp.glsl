@@ -0,0 +1,40 @@
+#version 460
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(local_size_x=16, local_size_y=16, local_size_z=1) in;
+
+layout(set=0, binding=0) uniform texture2D gBufferColor;
+
+layout(set=0, binding=1) buffer LuminanceHistogram {
+    uint bins[256];
+};
+
+layout(push_constant) uniform AutoExposure {
+    float min_log_luminance;
+    float log_luminance_range;
+    float adaptation_factor;
+    uint pixel_count;
+} settings;
+
+shared uint local_bins[256];
+
+// Bin 0 is reserved for black pixels, they are excluded from the average
+uint luminance_bin(vec3 color) {
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    if(luminance < 0.0001)
+        return 0;
+    float log_luminance = clamp((log2(luminance)-settings.min_log_luminance)/settings.log_luminance_range, 0.0, 1.0);
+    return uint(log_luminance*254.0+1.0);
+}
+
+void main() {
+    local_bins[gl_LocalInvocationIndex] = 0;
+    barrier();
+
+    ivec2 at = ivec2(gl_GlobalInvocationID.xy);
+    if(all(lessThan(at, textureSize(gBufferColor, 0))))
+        atomicAdd(local_bins[luminance_bin(texelFetch(gBufferColor, at, 0).rgb)], 1);
+    barrier();
+
+    atomicAdd(bins[gl_LocalInvocationIndex], local_bins[gl_LocalInvocationIndex]);
+}
@@ -0,0 +1,72 @@
+#version 460
+#extension GL_EXT_samplerless_texture_functions : require
+// permutations: DIMENSIONS=2 | DIMENSIONS=3
+// permutations: | POT
+
+layout(local_size_x=32, local_size_y=32, local_size_z=1) in;
+
+#if DIMENSIONS == 2
+layout(set=0, binding=0) uniform texture2DArray src_texture;
+layout(set=0, binding=1) uniform writeonly image2DArray dst_texture;
+#define ivecN ivec2
+#define vecN vec2
+#define axes xy
+#define depth_taps 1
+#else
+layout(set=0, binding=0) uniform texture3D src_texture;
+layout(set=0, binding=1) uniform writeonly image3D dst_texture;
+#define ivecN ivec3
+#define vecN vec3
+#define axes xyz
+#define depth_taps 2
+#endif
+
+#define interpolationFactor(axis) \
+    if((prev_size.axis&1) == 1) { \
+        w0s.axis = w2e.axis = float(next_size.axis)/float(prev_size.axis); \
+        w0e.axis = w2s.axis = 1.0/float(prev_size.axis); \
+    } else { \
+        w0s.axis = w0e.axis = 0.5; \
+        w2s.axis = w2e.axis = 0.0; \
+    }
+
+#define sampleRow(y, z) ( \
+    texelFetch(src_texture, src_uv+ivec3(0, y, z), 0)*w[0].x+ \
+    texelFetch(src_texture, src_uv+ivec3(1, y, z), 0)*w[1].x+ \
+    texelFetch(src_texture, src_uv+ivec3(2, y, z), 0)*w[2].x)
+
+#define sampleLayer(z) (sampleRow(0, z)*w[0].y+sampleRow(1, z)*w[1].y+sampleRow(2, z)*w[2].y)
+
+void main() {
+    ivec3 dst_uv = ivec3(gl_GlobalInvocationID.xyz);
+    ivec3 src_uv = dst_uv;
+    src_uv.axes *= 2;
+
+#ifdef POT
+    vec4 color = vec4(0.0);
+    for(int z = 0; z < depth_taps; ++z)
+        for(int y = 0; y < 2; ++y)
+            for(int x = 0; x < 2; ++x)
+                color += texelFetch(src_texture, src_uv+ivec3(x, y, z), 0);
+    imageStore(dst_texture, dst_uv, color/float(depth_taps*4));
+#else
+    ivecN prev_size = textureSize(src_texture, 0).axes;
+    ivecN next_size = imageSize(dst_texture).axes;
+    vecN w0s, w0e, w2s, w2e;
+    interpolationFactor(x)
+    interpolationFactor(y)
+#if DIMENSIONS == 3
+    interpolationFactor(z)
+#endif
+
+    vecN uv = vecN(gl_GlobalInvocationID.axes)/vecN(next_size);
+    vecN w[] = { mix(w0s, w0e, uv), w0s, mix(w2s, w2e, uv) };
+
+#if DIMENSIONS == 2
+    vec4 color = sampleLayer(0);
+#else
+    vec4 color = sampleLayer(0)*w[0].z+sampleLayer(1)*w[1].z+sampleLayer(2)*w[2].z;
+#endif
+    imageStore(dst_texture, dst_uv, color);
+#endif
+}
@@ -0,0 +1,53 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out vec2 motionVector;
+
+layout(set=0, binding=0) uniform utexture2D gBufferInstance;
+layout(set=0, binding=1) uniform texture2D gBufferDepth;
+
+#include "include/camera_uniforms.glsl"
+
+layout(set=2, binding=0) readonly buffer InstancesPreviousWorldMatrix {
+    mat4 instances_previous_world_matrix[];
+};
+
+layout(set=2, binding=1) readonly buffer InstancesInverseWorldMatrix {
+    mat4 instances_inverse_world_matrix[];
+};
+
+layout(set=2, binding=2) uniform PreviousCameraUniforms {
+    mat4 world_matrix;
+    mat4 projection_matrix;
+    mat4 inverse_view_matrix;
+    mat4 view_matrix;
+} previous_camera;
+
+layout(push_constant) uniform MotionVector {
+    vec2 jitter;
+    vec2 previous_jitter;
+} settings;
+
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    // Reverse-Z: nothing was rendered where the depth is still cleared
+    float depth = texelFetch(gBufferDepth, at, 0).r;
+    if(depth == 0.0) {
+        motionVector = vec2(0.0);
+        return;
+    }
+    ivec2 texture_size = textureSize(gBufferDepth, 0);
+    vec2 texcoord = gl_FragCoord.xy/vec2(texture_size);
+    vec3 position = reconstruct_position(at, texture_size, depth);
+    uint instance_index = texelFetch(gBufferInstance, at, 0).r;
+    vec3 previous_position = position;
+    mat4 previous_world_matrix = instances_previous_world_matrix[instance_index];
+    // The previous world matrix is zero if the instance buffers were just reallocated
+    if(previous_world_matrix[3][3] != 0.0)
+        previous_position = (previous_world_matrix*(instances_inverse_world_matrix[instance_index]*vec4(position, 1.0))).xyz;
+    vec4 previous_clip_position = previous_camera.view_matrix*vec4(previous_position, 1.0);
+    vec2 previous_texcoord = previous_clip_position.xy/previous_clip_position.w*vec2(0.5, -0.5)+vec2(0.5);
+    // Without the jitter, so that a static scene has no motion
+    motionVector = (texcoord-settings.jitter)-(previous_texcoord-settings.previous_jitter);
+}
@@ -1,16 +1,21 @@
 #version 460
 #extension GL_GOOGLE_include_directive : require
+// permutations: | LIGHT_OVERDRAW
 
 layout(location=0) in flat mat4 inverse_world_matrix;
 layout(location=4) in flat vec3 world_direction;
 struct ParallelLightSettings {
     vec3 color;
+    int shadow_map_index;
+    int shadow_cascade_count;
 };
 layout(location=5) in flat ParallelLightSettings light_setting;
 
 #include "include/light_shared.glsl"
 
 void main() {
+    light_overdraw()
+
     light_gbuffer(ParallelLightSettings)
 
     vec3 relative_position = (inverse_world_matrix*vec4(position, 1.0)).xyz;
@@ -22,4 +27,6 @@ void main() {
     float attenuation = 1.0;
     vec3 light_direction = world_direction;
     diffuse_specular_color()
+
+    gBufferColor.rgb *= shadow_factor(light_setting.shadow_map_index, light_setting.shadow_cascade_count, position);
 }
@@ -5,12 +5,16 @@ layout(location=0) in mat4 world_matrix;
 layout(location=4) in mat4 inverse_world_matrix;
 layout(location=8) in mat4 mvp_matrix;
 layout(location=12) in vec3 light_setting_color;
-layout(location=13) in vec3 aPosition;
+layout(location=13) in int light_setting_shadow_map_index;
+layout(location=14) in int light_setting_shadow_cascade_count;
+layout(location=15) in vec3 aPosition;
 
 layout(location=0) out mat4 out_inverse_world_matrix;
 layout(location=4) out vec3 out_world_direction;
 struct ParallelLightSettings {
     vec3 color;
+    int shadow_map_index;
+    int shadow_cascade_count;
 };
 layout(location=5) out ParallelLightSettings out_light_setting;
 
@@ -21,4 +25,6 @@ void main() {
     out_inverse_world_matrix = inverse_world_matrix;
     out_world_direction = normalize(world_matrix[2].xyz);
     out_light_setting.color = light_setting_color;
+    out_light_setting.shadow_map_index = light_setting_shadow_map_index;
+    out_light_setting.shadow_cascade_count = light_setting_shadow_cascade_count;
 }
@@ -7,12 +7,14 @@ layout(location=0) in flat vec3 world_position;
 layout(location=1) in flat float world_radius;
 layout(location=2) in flat vec4 color;
 layout(location=3) in vec4 vClipPos;
-layout(location=0) out vec4 gBufferPosition;
-layout(location=1) out ivec4 gBufferNormal;
+layout(location=4) in flat int instance_index;
+layout(location=0) out uint gBufferInstance;
+layout(location=1) out ivec2 gBufferNormal;
 layout(location=2) out vec4 gBufferAlbedo;
 layout(location=3) out vec4 gBufferMaterial;
 
 #include "include/camera_uniforms.glsl"
+#include "include/gbuffer.glsl"
 
 void main() {
     compute_view_ray()
@@ -21,9 +23,9 @@ void main() {
     if(line_sphere_intersection(view_ray_origin, view_ray_direction, world_position, world_radius, intersections) == 0.0)
         discard;
 
-    gBufferNormal = ivec4(normalize(intersections[0]-world_position)*32767.0, 0.0);
-    gBufferPosition = vec4(intersections[0], 0.0);
-    clip_frag_depth(gBufferPosition.xyz)
+    write_gbuffer_normal(normalize(intersections[0]-world_position))
+    gBufferInstance = uint(instance_index);
+    clip_frag_depth(intersections[0])
 
     gBufferAlbedo = color;
     gBufferMaterial = vec4(0.0);
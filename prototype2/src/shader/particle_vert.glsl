@@ -5,6 +5,7 @@ layout(location=0) out vec3 out_world_position;
 layout(location=1) out float out_world_radius;
 layout(location=2) out vec4 out_color;
 layout(location=3) out vec4 vClipPos;
+layout(location=4) out flat int out_instance_index;
 
 #include "include/billboard_vert.glsl"
 
@@ -22,5 +23,6 @@ void main() {
     out_world_position = (world_matrix*vec4(particle_attributes[particle_index].position_and_radius.xyz, 1.0)).xyz;
     out_world_radius = particle_attributes[particle_index].position_and_radius.w;
     out_color = particle_attributes[particle_index].color;
+    out_instance_index = gl_InstanceIndex;
     billboard_sphere(out_world_position, out_world_radius);
 }
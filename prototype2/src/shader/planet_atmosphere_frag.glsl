@@ -9,7 +9,7 @@ layout(location=1) in flat float world_radius;
 layout(location=2) in vec4 vClipPos;
 layout(location=0) out vec4 gBufferColor;
 
-layout(set=0, binding=0) uniform texture2D gBufferPosition;
+layout(set=0, binding=0) uniform texture2D gBufferDepth;
 
 #include "include/camera_uniforms.glsl"
 
@@ -39,17 +39,19 @@ void main() {
     vec3 atmosphere_intersections[2];
     if(line_sphere_intersection(view_ray_origin, view_ray_direction, world_position, atmosphere_radius, atmosphere_intersections) == 0.0)
         discard;
-    vec3 position = texelFetch(gBufferPosition, ivec2(gl_FragCoord.xy), 0).xyz;
-    if(position != vec3(0.0))
-        view_ray_hit = position;
+    // Reverse-Z: nothing was rendered where the depth is still cleared
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(gBufferDepth, at, 0).r;
+    if(depth != 0.0)
+        view_ray_hit = reconstruct_position(at, textureSize(gBufferDepth, 0), depth);
 
     if(dot(view_ray_hit-view_ray_origin, view_ray_direction) > dot(atmosphere_intersections[1]-view_ray_origin, view_ray_direction))
         view_ray_hit = atmosphere_intersections[1];
 
     frag_depth(atmosphere_intersections[0])
-    if(gl_FragDepth < 0.0) {
-        gl_FragDepth = 0.0;
-        vec3 point_on_near_plane = view_ray_origin-camera.world_matrix[2].xyz*(camera.projection_matrix[3][2]/camera.projection_matrix[2][2]);
+    if(is_frag_depth_clipped()) {
+        gl_FragDepth = 1.0;
+        vec3 point_on_near_plane = view_ray_origin+camera.world_matrix[2].xyz*(camera.projection_matrix[3][2]/(camera.projection_matrix[2][3]-camera.projection_matrix[2][2]));
         atmosphere_intersections[0] = view_ray_origin+view_ray_direction*line_plane_intersection(view_ray_origin, view_ray_direction, point_on_near_plane, camera.world_matrix[2].xyz);
     }
 
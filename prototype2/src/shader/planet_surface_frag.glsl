@@ -8,12 +8,14 @@ layout(location=0) in flat mat4 inverse_world_matrix;
 layout(location=4) in flat vec3 world_position;
 layout(location=5) in flat float world_radius;
 layout(location=6) in vec4 vClipPos;
-layout(location=0) out vec4 gBufferPosition;
-layout(location=1) out ivec4 gBufferNormal;
+layout(location=7) in flat int instance_index;
+layout(location=0) out uint gBufferInstance;
+layout(location=1) out ivec2 gBufferNormal;
 layout(location=2) out vec4 gBufferAlbedo;
 layout(location=3) out vec4 gBufferMaterial;
 
 #include "include/camera_uniforms.glsl"
+#include "include/gbuffer.glsl"
 
 layout(set=0, binding=0) uniform sampler diffuseSampler;
 layout(set=0, binding=1) uniform texture2DArray diffuseTexture;
@@ -126,10 +128,10 @@ void main() {
         discard;
 
     // Normal, Position and Depth
-    gBufferNormal = ivec4(normalize(intersections[0]-world_position)*32767.0, 0.0);
-    gBufferPosition = vec4(intersections[0], 0.0);
+    write_gbuffer_normal(normalize(intersections[0]-world_position))
+    gBufferInstance = uint(instance_index);
     gBufferMaterial = vec4(0.0);
-    clip_frag_depth(gBufferPosition.xyz)
+    clip_frag_depth(intersections[0])
 
     // Triangles and Barycentric
     vec3 direction = normalize((inverse_world_matrix*vec4(intersections[0], 1.0)).xyz);
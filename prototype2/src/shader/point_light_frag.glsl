@@ -1,5 +1,6 @@
 #version 460
 #extension GL_GOOGLE_include_directive : require
+// permutations: | LIGHT_OVERDRAW
 
 #include "include/light_shared.glsl"
 #include "include/ray_tracing_frag.glsl"
@@ -13,6 +14,8 @@ struct PointLightSettings {
 layout(location=8) in flat PointLightSettings light_setting;
 
 void main() {
+    light_overdraw()
+
     compute_view_ray()
 
     vec3 intersections[2];
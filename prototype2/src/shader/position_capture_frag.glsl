@@ -0,0 +1,16 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out vec4 position;
+
+layout(set=0, binding=0) uniform texture2D gBufferDepth;
+
+#include "include/camera_uniforms.glsl"
+
+// The GBuffer only stores the depth, the w component marks where a surface was rendered
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(gBufferDepth, at, 0).r;
+    position = (depth == 0.0) ? vec4(0.0) : vec4(reconstruct_position(at, textureSize(gBufferDepth, 0), depth), 1.0);
+}
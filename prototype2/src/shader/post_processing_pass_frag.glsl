@@ -5,6 +5,25 @@ layout(location=0) out vec4 frameColor;
 
 layout(set=0, binding=0) uniform texture2D gBufferColor;
 layout(set=0, binding=1) uniform texture2D gBufferDepth;
+layout(set=0, binding=2) uniform sampler linearSampler;
+layout(set=0, binding=3) uniform texture2D bloomTexture;
+
+layout(set=0, binding=4) readonly buffer Exposure {
+    float average_luminance;
+};
+
+layout(push_constant) uniform PostProcessing {
+    int tonemapping;
+    float exposure;
+    int enable_auto_exposure;
+    float bloom_intensity;
+} settings;
+
+const int TONEMAPPING_REINHARD = 1;
+const int TONEMAPPING_ACES = 2;
+
+// Middle gray, which the average luminance is mapped to by the auto exposure
+const float EXPOSURE_KEY_VALUE = 0.18;
 
 const mat3 SOBEL_X = mat3(
     1.0, 0.0, -1.0,
@@ -31,8 +50,30 @@ vec2 sobel() {
     return vec2(convoluteMatrices(SOBEL_X, imgMat), convoluteMatrices(SOBEL_Y, imgMat));
 }
 
+vec3 tonemap_reinhard(vec3 color) {
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    return color/(1.0+luminance);
+}
+
+// Curve fit of the ACES filmic reference rendering transform by Krzysztof Narkowicz
+vec3 tonemap_aces(vec3 color) {
+    return clamp((color*(2.51*color+0.03))/(color*(2.43*color+0.59)+0.14), 0.0, 1.0);
+}
+
 void main() {
     ivec2 at = ivec2(gl_FragCoord.xy);
     frameColor = texelFetch(gBufferColor, at, 0);
+    vec2 uv = gl_FragCoord.xy/vec2(textureSize(gBufferColor, 0));
+    frameColor.rgb += texture(sampler2D(bloomTexture, linearSampler), uv).rgb*settings.bloom_intensity;
+
+    float exposure = settings.exposure;
+    if(settings.enable_auto_exposure != 0)
+        exposure *= EXPOSURE_KEY_VALUE/max(average_luminance, 0.0001);
+    frameColor.rgb *= exposure;
+    if(settings.tonemapping == TONEMAPPING_REINHARD)
+        frameColor.rgb = tonemap_reinhard(frameColor.rgb);
+    else if(settings.tonemapping == TONEMAPPING_ACES)
+        frameColor.rgb = tonemap_aces(frameColor.rgb);
+
     frameColor.rgb *= vec3(max(0.0, 1.0-length(sobel())*50.0));
 }
@@ -10,11 +10,24 @@ layout(location=2) in vec4 vClipPos;
 #include "include/camera_uniforms.glsl"
 
 void main() {
-    compute_view_ray()
+    // Works for perspective and orthographic projections, so the ray starts at the near plane instead of the camera
+    vec4 near_point = camera.inverse_view_matrix*vec4(vClipPos.xy/vClipPos.w, 1.0, 1.0);
+    vec4 far_point = camera.inverse_view_matrix*vec4(vClipPos.xy/vClipPos.w, 0.5, 1.0);
+    vec3 view_ray_origin = near_point.xyz/near_point.w;
+    vec3 view_ray_direction = normalize(far_point.xyz/far_point.w-view_ray_origin);
 
     vec3 intersections[2];
     if(line_sphere_intersection(view_ray_origin, view_ray_direction, world_position, world_radius, intersections) == 0.0)
         discard;
 
-    clip_frag_depth(intersections[1])
+    vec3 relative_position = intersections[1]-camera.world_matrix[3].xyz;
+    vec4 clip_position = camera.projection_matrix*vec4(
+        dot(relative_position, camera.world_matrix[0].xyz),
+        dot(relative_position, camera.world_matrix[1].xyz),
+        dot(relative_position, camera.world_matrix[2].xyz),
+        1.0
+    );
+    gl_FragDepth = clip_position.z/clip_position.w;
+    if(is_frag_depth_clipped())
+        discard;
 }
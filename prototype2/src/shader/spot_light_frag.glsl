@@ -1,5 +1,6 @@
 #version 460
 #extension GL_GOOGLE_include_directive : require
+// permutations: | LIGHT_OVERDRAW
 
 layout(location=0) in flat vec3 world_direction;
 layout(location=1) in flat vec3 world_position;
@@ -8,16 +9,20 @@ struct SpotLightSettings {
     vec3 color;
     float outer_angle_cos;
     float inner_angle_cos;
+    int shadow_map_index;
 };
 layout(location=3) in flat SpotLightSettings light_setting;
 
 #include "include/light_shared.glsl"
 
 void main() {
+    light_overdraw()
+
     light_gbuffer(SpotLightSettings)
 
     light_attenuation_and_direction()
     diffuse_specular_color()
 
     gBufferColor.rgb *= smoothstep(light_setting.outer_angle_cos, light_setting.inner_angle_cos, dot(light_direction, world_direction));
+    gBufferColor.rgb *= shadow_factor(light_setting.shadow_map_index, 1, position);
 }
@@ -8,7 +8,8 @@ layout(location=12) in vec3 light_setting_color;
 layout(location=13) in float light_setting_radius;
 layout(location=14) in float light_setting_outer_angle_cos;
 layout(location=15) in float light_setting_inner_angle_cos;
-layout(location=16) in vec3 aPosition;
+layout(location=16) in int light_setting_shadow_map_index;
+layout(location=17) in vec3 aPosition;
 
 layout(location=0) out vec3 out_world_direction;
 layout(location=1) out vec3 out_world_position;
@@ -17,6 +18,7 @@ struct SpotLightSettings {
     vec3 color;
     float outer_angle_cos;
     float inner_angle_cos;
+    int shadow_map_index;
 };
 layout(location=3) out SpotLightSettings out_light_setting;
 
@@ -30,4 +32,5 @@ void main() {
     out_light_setting.color = light_setting_color;
     out_light_setting.outer_angle_cos = light_setting_outer_angle_cos;
     out_light_setting.inner_angle_cos = light_setting_inner_angle_cos;
+    out_light_setting.shadow_map_index = light_setting_shadow_map_index;
 }
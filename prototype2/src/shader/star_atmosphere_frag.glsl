@@ -11,6 +11,9 @@ layout(location=0) out vec4 gBufferColor;
 
 #include "include/camera_uniforms.glsl"
 
+// The corona is brighter than the bloom threshold, so that it glows
+const float CORONA_INTENSITY = 4.0;
+
 void main() {
     compute_view_ray()
 
@@ -32,4 +35,5 @@ void main() {
     float normalized_altitude = (altitude-star_radius)/(atmosphere_radius-star_radius);
     gBufferColor.rgb = vec3(1.0, 0.1, 0.0)*pow(max(0.0, simplex_noise(vec4(normal*10.0, animation_time))*0.5+0.5-normalized_altitude), 2.0);
     gBufferColor.rgb += vec3(0.4, 0.1, 0.0)*pow(1.0-normalized_altitude, 10.0);
+    gBufferColor.rgb *= CORONA_INTENSITY;
 }
@@ -11,10 +11,12 @@ layout(location=14) in vec2 aTexCoord;
 layout(location=0) out vec3 vPosition;
 layout(location=1) out vec3 vNormal;
 layout(location=2) out vec2 vTexCoord;
+layout(location=3) out flat int vInstanceIndex;
 
 void main() {
     vPosition = (world_matrix*vec4(aPosition, 1.0)).xyz;
     vNormal = (world_matrix*vec4(aNormal, 0.0)).xyz;
     vTexCoord = aTexCoord;
+    vInstanceIndex = gl_InstanceIndex;
     gl_Position = mvp_matrix*vec4(aPosition, 1.0);
 }
@@ -0,0 +1,46 @@
+#version 460
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out vec4 resolvedColor;
+
+layout(set=0, binding=0) uniform sampler linearSampler;
+layout(set=0, binding=1) uniform texture2D gBufferColor;
+layout(set=0, binding=2) uniform texture2D historyColor;
+layout(set=0, binding=3) uniform texture2D motionVectors;
+
+layout(push_constant) uniform TemporalAntiAliasing {
+    float blend_factor;
+} settings;
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+void main() {
+    ivec2 at = ivec2(gl_FragCoord.xy);
+    ivec2 texture_size = textureSize(gBufferColor, 0);
+    vec3 current = texelFetch(gBufferColor, at, 0).rgb;
+
+    // Neighborhood clamping: the history may only contain colors which are near the current ones
+    vec3 neighborhood_min = current;
+    vec3 neighborhood_max = current;
+    for(int y = -1; y <= 1; ++y)
+        for(int x = -1; x <= 1; ++x) {
+            vec3 neighbor = texelFetch(gBufferColor, clamp(at+ivec2(x, y), ivec2(0), texture_size-ivec2(1)), 0).rgb;
+            neighborhood_min = min(neighborhood_min, neighbor);
+            neighborhood_max = max(neighborhood_max, neighbor);
+        }
+
+    vec2 history_texcoord = gl_FragCoord.xy/vec2(texture_size)-texelFetch(motionVectors, at, 0).xy;
+    if(any(lessThan(history_texcoord, vec2(0.0))) || any(greaterThan(history_texcoord, vec2(1.0)))) {
+        resolvedColor = vec4(current, 1.0);
+        return;
+    }
+    vec3 history = texture(sampler2D(historyColor, linearSampler), history_texcoord).rgb;
+    history = clamp(history, neighborhood_min, neighborhood_max);
+
+    // Weighted by the inverse luminance, so that single bright pixels do not flicker
+    float current_weight = settings.blend_factor/(1.0+luminance(current));
+    float history_weight = (1.0-settings.blend_factor)/(1.0+luminance(history));
+    resolvedColor = vec4((current*current_weight+history*history_weight)/max(current_weight+history_weight, 0.0001), 1.0);
+}
@@ -0,0 +1,11 @@
+#version 460
+#extension GL_EXT_samplerless_texture_functions : require
+
+layout(location=0) out vec4 destination;
+
+layout(set=0, binding=0) uniform texture2D source;
+
+// Components which the destination format does not have are discarded
+void main() {
+    destination = texelFetch(source, ivec2(gl_FragCoord.xy), 0);
+}
@@ -5,6 +5,7 @@ layout(location=0) out mat4 out_inverse_world_matrix;
 layout(location=4) out vec3 out_world_position;
 layout(location=5) out float out_world_radius;
 layout(location=6) out vec4 vClipPos;
+layout(location=7) out flat int out_instance_index;
 
 #include "include/billboard_vert.glsl"
 
@@ -12,5 +13,6 @@ void main() {
     out_inverse_world_matrix = inverse_world_matrix;
     out_world_position = world_matrix[3].xyz;
     out_world_radius = length(world_matrix[2].xyz);
+    out_instance_index = gl_InstanceIndex;
     billboard_sphere(out_world_position, out_world_radius);
 }
@@ -0,0 +1,63 @@
+#![allow(dead_code)]
+// Shared by build.rs and the shader hot reloading, so both resolve includes and report errors the same way
+
+use crate::shader_permutation::{parse_permutations, variant_name};
+
+fn shader_kind(src_path: &std::path::Path) -> Result<shaderc::ShaderKind, String> {
+    match src_path.file_stem().and_then(|x| x.to_str()).and_then(|x| x.split('_').last()) {
+        Some("vert") => Ok(shaderc::ShaderKind::Vertex),
+        Some("frag") => Ok(shaderc::ShaderKind::Fragment),
+        Some("comp") => Ok(shaderc::ShaderKind::Compute),
+        _ => Err(format!("{}: file name has to end in _vert, _frag or _comp", src_path.display())),
+    }
+}
+
+/// Includes are resolved relative to the file which contains the directive
+fn resolve_include(requested_source: &str, requesting_source: &str) -> shaderc::IncludeCallbackResult {
+    let path = std::path::Path::new(requesting_source).parent().unwrap().join(requested_source);
+    let content = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(shaderc::ResolvedInclude {
+        resolved_name: path.to_str().unwrap().to_owned(),
+        content,
+    })
+}
+
+/// Compiles GLSL to SPIR-V, the shader stage is the suffix of the file name
+pub struct ShaderCompiler {
+    compiler: shaderc::Compiler,
+}
+
+impl ShaderCompiler {
+    pub fn new() -> Self {
+        Self {
+            compiler: shaderc::Compiler::new().unwrap(),
+        }
+    }
+
+    /// Writes one module per permutation to dst_path and returns their paths.
+    /// Errors contain the file and line, the previous modules are left untouched on failure.
+    pub fn compile_file(&mut self, src_path: &std::path::Path, dst_path: &std::path::Path) -> Result<Vec<std::path::PathBuf>, String> {
+        let kind = shader_kind(src_path)?;
+        let source = std::fs::read_to_string(src_path).map_err(|error| format!("{}: {}", src_path.display(), error))?;
+        let file_stem = src_path.file_stem().unwrap().to_str().unwrap();
+        let mut modules = Vec::new();
+        for defines in parse_permutations(&source) {
+            let mut options = shaderc::CompileOptions::new().unwrap();
+            options.set_include_callback(|requested_source, _include_type, requesting_source, _include_depth| {
+                resolve_include(requested_source, requesting_source)
+            });
+            for define in defines.iter() {
+                let mut define = define.splitn(2, '=');
+                options.add_macro_definition(define.next().unwrap(), define.next());
+            }
+            let name = variant_name(file_stem, &defines);
+            let artifact = self.compiler.compile_into_spirv(&source, kind, src_path.to_str().unwrap(), "main", Some(&options))
+                .map_err(|error| if defines.is_empty() { error.to_string() } else { format!("{}\nin permutation {}", error.to_string().trim_end(), name) })?;
+            modules.push((dst_path.join(format!("{}.spv", name)), artifact));
+        }
+        for (module_path, artifact) in modules.iter() {
+            std::fs::write(module_path, artifact.as_binary_u8()).map_err(|error| format!("{}: {}", module_path.display(), error))?;
+        }
+        Ok(modules.into_iter().map(|(module_path, _)| module_path).collect())
+    }
+}
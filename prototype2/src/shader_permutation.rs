@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+// Shared by build.rs, the shader compiler and the asset pack, which looks up modules by their variant name, without depending on shaderc
+
+const PERMUTATIONS_DIRECTIVE: &str = "// permutations:";
+
+/// Each "// permutations: A=1 B | A=2 |" line is one axis of alternatives separated by "|",
+/// an alternative is a list of defines and may be empty. All combinations of the axes are compiled.
+pub fn parse_permutations(source: &str) -> Vec<Vec<String>> {
+    let mut permutations: Vec<Vec<String>> = vec![Vec::new()];
+    for line in source.lines() {
+        let line = line.trim();
+        if !line.starts_with(PERMUTATIONS_DIRECTIVE) {
+            continue;
+        }
+        let alternatives: Vec<Vec<String>> = line[PERMUTATIONS_DIRECTIVE.len()..].split('|')
+            .map(|alternative| alternative.split_whitespace().map(|define| define.to_string()).collect())
+            .collect();
+        permutations = permutations.iter()
+            .flat_map(|defines| alternatives.iter().map(move |alternative| defines.iter().chain(alternative.iter()).cloned().collect()))
+            .collect();
+    }
+    permutations
+}
+
+/// Name of the module compiled from the shader with these defines, e.g. "mipmap_comp[DIMENSIONS=2,POT]".
+/// The defines are sorted, so the order in which they are requested does not matter.
+pub fn variant_name<S: AsRef<str>>(name: &str, defines: &[S]) -> String {
+    if defines.is_empty() {
+        return name.to_string();
+    }
+    let mut defines: Vec<&str> = defines.iter().map(|define| define.as_ref()).collect();
+    defines.sort();
+    format!("{}[{}]", name, defines.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_strings(permutations: &[&[&str]]) -> Vec<Vec<String>> {
+        permutations.iter().map(|defines| defines.iter().map(|define| define.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn without_permutations() {
+        assert_eq!(parse_permutations("#version 460\nvoid main() {}\n"), to_strings(&[&[]]));
+    }
+
+    #[test]
+    fn cross_product_of_lines() {
+        let source = "#version 460\n// permutations: A=1 D=3 | A=2\n  // permutations: B | C\nvoid main() {}\n";
+        assert_eq!(parse_permutations(source), to_strings(&[
+            &["A=1", "D=3", "B"],
+            &["A=1", "D=3", "C"],
+            &["A=2", "B"],
+            &["A=2", "C"],
+        ]));
+    }
+
+    #[test]
+    fn empty_alternative() {
+        assert_eq!(parse_permutations("// permutations: | POT\n"), to_strings(&[&[], &["POT"]]));
+        assert_eq!(parse_permutations("// permutations: A=1 | A=2\n// permutations: POT |\n"), to_strings(&[
+            &["A=1", "POT"],
+            &["A=1"],
+            &["A=2", "POT"],
+            &["A=2"],
+        ]));
+    }
+
+    #[test]
+    fn variant_names() {
+        assert_eq!(variant_name::<&str>("point_light_frag", &[]), "point_light_frag");
+        assert_eq!(variant_name("mipmap_comp", &["DIMENSIONS=2", "POT"]), "mipmap_comp[DIMENSIONS=2,POT]");
+        // Lookups find the module regardless of the order in which the defines are listed
+        assert_eq!(variant_name("mipmap_comp", &["POT", "DIMENSIONS=2"]), "mipmap_comp[DIMENSIONS=2,POT]");
+        assert_eq!(variant_name("mipmap_comp", &["POT".to_string(), "DIMENSIONS=2".to_string()]), variant_name("mipmap_comp", &["DIMENSIONS=2", "POT"]));
+    }
+}
@@ -0,0 +1,87 @@
+use iced_wgpu::wgpu;
+
+/// Absolute, so that the sources are found regardless of the working directory
+const SOURCE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader");
+const INCLUDE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader/include");
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn collect_modification_times(path: &str) -> std::collections::HashMap<std::path::PathBuf, std::time::SystemTime> {
+    let mut modification_times = std::collections::HashMap::new();
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let entry_path = entry.path();
+            if entry_path.extension().and_then(|x| x.to_str()) != Some("glsl") {
+                continue;
+            }
+            if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+                modification_times.insert(entry_path, modified);
+            }
+        }
+    }
+    modification_times
+}
+
+fn changed_paths<'a>(previous: &std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>, current: &'a std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>) -> Vec<&'a std::path::PathBuf> {
+    current.iter().filter(|(path, modified)| previous.get(*path) != Some(modified)).map(|(path, _)| path).collect()
+}
+
+/// Development mode which polls the GLSL sources for changes and recompiles them.
+/// A changed include recompiles all shaders, as the includes are not tracked per shader.
+pub struct ShaderWatcher {
+    shader_compiler: crate::shader_compiler::ShaderCompiler,
+    sources: std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>,
+    includes: std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>,
+    last_poll_time: std::time::Instant,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        Self {
+            shader_compiler: crate::shader_compiler::ShaderCompiler::new(),
+            sources: collect_modification_times(SOURCE_PATH),
+            includes: collect_modification_times(INCLUDE_PATH),
+            last_poll_time: std::time::Instant::now(),
+        }
+    }
+
+    /// Time at which the next poll should happen, so that an idle event loop wakes up for it
+    pub fn next_poll_time(&self) -> std::time::Instant {
+        self.last_poll_time+POLL_INTERVAL
+    }
+
+    /// Returns true if any shader module was replaced, then the pipelines have to be recreated.
+    /// Shaders which fail to compile are logged and keep their previous module.
+    pub fn poll(&mut self, device: &wgpu::Device, asset_pack: &mut crate::assets::AssetPack) -> bool {
+        let now = std::time::Instant::now();
+        if now < self.next_poll_time() {
+            return false;
+        }
+        self.last_poll_time = now;
+        let sources = collect_modification_times(SOURCE_PATH);
+        let includes = collect_modification_times(INCLUDE_PATH);
+        let mut changed_sources: Vec<&std::path::PathBuf> = if changed_paths(&self.includes, &includes).is_empty() {
+            changed_paths(&self.sources, &sources)
+        } else {
+            sources.keys().collect()
+        };
+        changed_sources.sort();
+        let mut reloaded = false;
+        for src_path in changed_sources {
+            match self.shader_compiler.compile_file(src_path, std::path::Path::new(crate::assets::SHADER_MODULE_DIRECTORY)) {
+                Ok(dst_paths) => {
+                    for dst_path in dst_paths {
+                        match asset_pack.reload_shader_module(device, dst_path.clone()) {
+                            Ok(true) => reloaded = true,
+                            Ok(false) => eprintln!("Shader {:?} is not reloadable, it was not loaded at startup and no pipeline uses it before a restart", dst_path),
+                            Err(error) => eprintln!("Could not load shader {:?}: {:?}", dst_path, error),
+                        }
+                    }
+                },
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        self.sources = sources;
+        self.includes = includes;
+        reloaded
+    }
+}
@@ -1,29 +1,30 @@
 #![allow(dead_code)]
 
 use iced_wgpu::{wgpu, wgpu::vertex_attr_array};
+use crate::pipeline_layout::{BindGroupLayout, PipelineLayout, PipelineLayoutDescriptor};
 
 pub struct StarRenderer {
     surface_pipeline: wgpu::RenderPipeline,
-    surface_bind_group_layout: wgpu::BindGroupLayout,
+    surface_bind_group_layout: BindGroupLayout,
     surface_bind_group: wgpu::BindGroup,
     atmosphere_pipeline: wgpu::RenderPipeline,
 }
 
 impl StarRenderer {
     pub fn new(device: &wgpu::Device, renderer: &crate::renderer::Renderer, asset_pack: &crate::assets::AssetPack) -> Self {
-        let surface_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let surface_bind_group_layout = BindGroupLayout::new(device, &wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[],
         });
 
         let surface_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[],
                 bind_group_layouts: &[&surface_bind_group_layout, &renderer.bind_group_layouts.camera_uniforms_bind_group_layout],
             });
 
-        let surface_pipeline = device.create_render_pipeline(&surface_pass_pipeline_descriptor!(
+        let surface_pipeline = surface_pipeline_layout.create_render_pipeline(device, asset_pack, &surface_pass_pipeline_descriptor!(
             asset_pack,
             surface_pipeline_layout,
             "assets/shader_modules/uv_sphere_billboard_vert",
@@ -45,7 +46,7 @@ impl StarRenderer {
         ));
 
         let atmosphere_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            PipelineLayout::new(device, &PipelineLayoutDescriptor {
                 label: None,
                 push_constant_ranges: &[],
                 bind_group_layouts: &[
@@ -55,7 +56,7 @@ impl StarRenderer {
             });
 
         let atmosphere_pipeline =
-            device.create_render_pipeline(&volumetric_pass_pipeline_descriptor!(
+            atmosphere_pipeline_layout.create_render_pipeline(device, asset_pack, &volumetric_pass_pipeline_descriptor!(
                 asset_pack, renderer,
                 atmosphere_pipeline_layout,
                 "assets/shader_modules/sphere_billboard_vert",